@@ -21,10 +21,14 @@
 ;;
 ;; Variables:
 ;;   `lispi-binary'        — path to the compiled unix-side binary (required)
+;;   `lispi-args'          — extra arguments for it (--device, --baud, ...)
 ;;   `lispi-overlay-prefix' — string prepended to results (default "=> ")
 
 ;;; Code:
 
+(require 'cl-lib)
+(require 'json)
+
 ;;;; Customization
 
 (defgroup lispi nil
@@ -37,6 +41,13 @@
   :type 'string
   :group 'lispi)
 
+(defcustom lispi-args nil
+  "Extra command-line arguments for `lispi-binary'.
+For example (\"--device\" \"/dev/ttyUSB1\" \"--baud\" \"115200\"), or
+\(\"connect\") to share a Pi served by `unix-side serve'."
+  :type '(repeat string)
+  :group 'lispi)
+
 (defcustom lispi-overlay-prefix "=> "
   "Prefix shown before result overlays."
   :type 'string
@@ -54,47 +65,67 @@
   "Face for the fold indicator on multi-line results."
   :group 'lispi)
 
+(defface lispi-eval-overlay-error
+  '((t :foreground "firebrick" :weight bold))
+  "Face for inline overlays showing an evaluation error."
+  :group 'lispi)
+
 ;;;; Process management
 
 (defvar lispi--process nil
   "The subprocess running the unix-side binary.")
 
 (defvar lispi--output-buffer ""
-  "Accumulator for partial output from the subprocess.")
+  "Accumulator for a partial line of output from the subprocess.")
 
-(defvar lispi--callback nil
-  "Pending callback to invoke with the next complete result.")
+(defvar lispi--callbacks (make-hash-table)
+  "Pending callbacks, keyed by request id.")
+
+(defvar lispi--next-id 0
+  "Id of the last request sent.")
 
 (defvar lispi--ready nil
-  "Non-nil once the initial prompt has been received.")
+  "Non-nil once the binary has reported that it is connected.")
+
+(defun lispi--console-output (text)
+  "Append console output TEXT from the Pi to the *lispi output* buffer."
+  (with-current-buffer (get-buffer-create "*lispi output*")
+    (goto-char (point-max))
+    (insert text)))
+
+(defun lispi--dispatch (event)
+  "Handle one EVENT (an alist) from the binary's --json output."
+  (let ((id (alist-get 'id event))
+        (kind (alist-get 'kind event))
+        (text (alist-get 'text event)))
+    (pcase kind
+      ("ready"
+       (setq lispi--ready t)
+       (message "lispi: connected"))
+      ("output" (lispi--console-output text))
+      ((or "value" "error")
+       (let ((cb (gethash id lispi--callbacks)))
+         (remhash id lispi--callbacks)
+         (cond
+          (cb (funcall cb text (equal kind "error")))
+          ((equal kind "error") (message "lispi: %s" text))))))))
 
 (defun lispi--process-filter (_proc output)
-  "Accumulate OUTPUT from the subprocess and dispatch when complete."
+  "Accumulate OUTPUT from the subprocess and dispatch complete lines.
+Every line is a JSON object; see unix-side/src/json.rs."
   (setq lispi--output-buffer (concat lispi--output-buffer output))
-  ;; A complete response ends with "\n> " (result + prompt).
-  ;; Also match bare "> " for the initial startup prompt.
-  (when (string-match-p "\n> \\'" lispi--output-buffer)
-    (let ((text (string-trim
-                 (replace-regexp-in-string "\n> \\'" "" lispi--output-buffer))))
-      (setq lispi--output-buffer "")
-      (if lispi--ready
-          (when lispi--callback
-            (let ((cb lispi--callback))
-              (setq lispi--callback nil)
-              (funcall cb text)))
-        (setq lispi--ready t)
-        (message "lispi: connected"))))
-  ;; Bare initial prompt (no preceding newline).
-  (when (and (not lispi--ready)
-             (string-match-p "\\`> \\'" lispi--output-buffer))
-    (setq lispi--output-buffer "")
-    (setq lispi--ready t)
-    (message "lispi: connected")))
+  (let ((lines (split-string lispi--output-buffer "\n")))
+    ;; the last element is an incomplete line (or "")
+    (setq lispi--output-buffer (car (last lines)))
+    (dolist (line (butlast lines))
+      (unless (string-empty-p line)
+        (lispi--dispatch
+         (json-parse-string line :object-type 'alist :null-object nil))))))
 
 (defun lispi--sentinel (_proc event)
   "Handle process EVENT."
   (setq lispi--ready nil)
-  (setq lispi--callback nil)
+  (clrhash lispi--callbacks)
   (setq lispi--output-buffer "")
   (message "lispi: %s" (string-trim event)))
 
@@ -104,13 +135,18 @@
   (when (and lispi--process (process-live-p lispi--process))
     (user-error "lispi: already connected"))
   (setq lispi--ready nil
-        lispi--output-buffer ""
-        lispi--callback nil)
-  (let ((proc (start-process "lispi" nil lispi-binary)))
-    (set-process-filter proc #'lispi--process-filter)
-    (set-process-sentinel proc #'lispi--sentinel)
-    (set-process-query-on-exit-flag proc nil)
-    (setq lispi--process proc))
+        lispi--output-buffer "")
+  (clrhash lispi--callbacks)
+  (setq lispi--process
+        (make-process
+         :name "lispi"
+         :command (cons lispi-binary (append lispi-args '("--json")))
+         :connection-type 'pipe
+         ;; status messages go to stderr, away from the JSON
+         :stderr (get-buffer-create " *lispi stderr*")
+         :filter #'lispi--process-filter
+         :sentinel #'lispi--sentinel))
+  (set-process-query-on-exit-flag lispi--process nil)
   (message "lispi: starting %s …" lispi-binary))
 
 (defun lispi-disconnect ()
@@ -121,51 +157,46 @@
   (setq lispi--process nil
         lispi--ready nil))
 
-;;;; Sending expressions
+(defun lispi-interrupt ()
+  "Abandon the evaluation the Pi is running; it fails with \"interrupted\"."
+  (interactive)
+  ;; with nothing running, unix-side would take the signal as a
+  ;; request to quit
+  (when (and lispi--process (process-live-p lispi--process)
+             (> (hash-table-count lispi--callbacks) 0))
+    (interrupt-process lispi--process)))
 
-(defun lispi--strip-comments (str)
-  "Remove ; line-comments from STR, respecting string literals."
-  (let ((lines (split-string str "\n")))
-    (mapconcat
-     (lambda (line)
-       (let ((i 0) (in-str nil) (len (length line)))
-         (while (< i len)
-           (let ((ch (aref line i)))
-             (cond
-              ((and (not in-str) (= ch ?\;))
-               (setq len i))
-              ((= ch ?\")
-               (setq in-str (not in-str))
-               (setq i (1+ i)))
-              ((and in-str (= ch ?\\))
-               (setq i (+ i 2)))
-              (t (setq i (1+ i))))))
-         (substring line 0 len)))
-     lines " ")))
+;;;; Sending expressions
 
 (defun lispi--send (expr callback)
-  "Send EXPR string to the subprocess, call CALLBACK with the result."
+  "Send EXPR string to the subprocess.
+CALLBACK is called with the result text and a flag that is non-nil
+if it is an error."
   (unless (and lispi--process (process-live-p lispi--process))
     (user-error "lispi: not connected — run M-x lispi-connect"))
   (unless lispi--ready
-    (user-error "lispi: waiting for initial prompt"))
-  (when lispi--callback
-    (user-error "lispi: evaluation already in progress"))
-  (setq lispi--callback callback)
-  ;; The binary reads one line at a time, so collapse multi-line
-  ;; expressions into a single line.  Strip ; comments first so they
-  ;; don't swallow the rest of the expression after newline removal.
-  (let ((one-line (string-trim (lispi--strip-comments expr))))
-    (process-send-string lispi--process (concat one-line "\n"))))
+    (user-error "lispi: waiting for the connection"))
+  (let ((id (cl-incf lispi--next-id)))
+    (puthash id callback lispi--callbacks)
+    (process-send-string
+     lispi--process
+     (concat (json-encode `((id . ,id) (code . ,(string-trim expr)))) "\n"))))
 
 ;;;; Overlays
 
 (defvar-local lispi--overlays nil
   "List of active result overlays in this buffer.")
 
-(defun lispi--display-result (pos result)
+(defun lispi--result-face (folded errorp)
+  "Face for a result overlay, FOLDED or not, showing an error if ERRORP."
+  (cond (errorp 'lispi-eval-overlay-error)
+        (folded 'lispi-eval-overlay-folded)
+        (t 'lispi-eval-overlay)))
+
+(defun lispi--display-result (pos result &optional errorp)
   "Show RESULT as an overlay after POS in the current buffer.
-Removes any existing lispi overlay at POS first."
+ERRORP means RESULT is an error message.  Removes any existing
+lispi overlay at POS first."
   ;; Remove any prior lispi overlay at or near this position.
   ;; We scan our own list since overlays-at misses zero-width overlays.
   (dolist (ov (copy-sequence lispi--overlays))
@@ -182,11 +213,10 @@ Removes any existing lispi overlay at POS first."
     (overlay-put ov 'lispi-result t)
     (overlay-put ov 'lispi-full-result result)
     (overlay-put ov 'lispi-folded multiline)
+    (overlay-put ov 'lispi-error errorp)
     (overlay-put ov 'after-string
                  (propertize (concat "  " display-text)
-                             'face (if multiline
-                                       'lispi-eval-overlay-folded
-                                     'lispi-eval-overlay)))
+                             'face (lispi--result-face multiline errorp)))
     ;; Evaporate on modification.
     (overlay-put ov 'modification-hooks
                  (list (lambda (o &rest _) (lispi--remove-overlay o))))
@@ -229,9 +259,9 @@ zero-width overlays sitting at line boundaries."
       (overlay-put ov 'lispi-folded new-folded)
       (overlay-put ov 'after-string
                    (propertize (concat "  " display-text)
-                               'face (if new-folded
-                                         'lispi-eval-overlay-folded
-                                       'lispi-eval-overlay))))))
+                               'face (lispi--result-face
+                                      new-folded
+                                      (overlay-get ov 'lispi-error)))))))
 
 (defun lispi-remove-overlays ()
   "Remove all lispi result overlays from the current buffer."
@@ -285,9 +315,9 @@ on or just after a closing paren, scans backward first."
         (pos end)
         (buf (current-buffer)))
     (lispi--send expr
-                 (lambda (result)
+                 (lambda (result errorp)
                    (with-current-buffer buf
-                     (lispi--display-result pos result))))))
+                     (lispi--display-result pos result errorp))))))
 
 (defun lispi-eval-last-sexp ()
   "Evaluate the sexp before point and show the result as an overlay."
@@ -339,7 +369,7 @@ on or just after a closing paren, scans backward first."
       (nreverse sexps))))
 
 (defun lispi-eval-buffer ()
-  "Evaluate every top-level sexp in the buffer sequentially."
+  "Evaluate every top-level sexp in the buffer in order, stopping at an error."
   (interactive)
   (let* ((buf (current-buffer))
          (sexps (lispi--collect-top-level-sexps))
@@ -359,18 +389,23 @@ on or just after a closing paren, scans backward first."
                               (buffer-substring-no-properties beg end))))
                  (cl-incf idx)
                  (lispi--send expr
-                              (lambda (result)
+                              (lambda (result errorp)
                                 (with-current-buffer buf
-                                  (lispi--display-result end result))
-                                (eval-next)))))))
+                                  (lispi--display-result end result errorp))
+                                (if errorp
+                                    (message "lispi: stopped at error in form %d of %d"
+                                             idx total)
+                                  (eval-next))))))))
         (eval-next)))))
 
 (defun lispi-eval-string (expr)
   "Evaluate EXPR entered in the minibuffer."
   (interactive "sEval: ")
   (lispi--send expr
-               (lambda (result)
-                 (message "lispi: %s%s" lispi-overlay-prefix result))))
+               (lambda (result errorp)
+                 (message "lispi: %s%s"
+                          (if errorp "error: " lispi-overlay-prefix)
+                          result))))
 
 ;;;; Minor mode
 
@@ -385,6 +420,7 @@ on or just after a closing paren, scans backward first."
     (define-key map (kbd "C-c C-a") #'lispi-eval-buffer)
     (define-key map (kbd "C-c C-k") #'lispi-remove-overlays)
     (define-key map (kbd "C-c C-d") #'lispi-disconnect)
+    (define-key map (kbd "C-c C-g") #'lispi-interrupt)
     (define-key map (kbd "C-c C-f") #'lispi-toggle-fold-at-point)
     map)
   "Keymap for `lispi-mode'.")
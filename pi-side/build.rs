@@ -1,5 +1,43 @@
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
 fn main() {
     let manifest_dir = std::env::var("CARGO_MANIFEST_DIR").unwrap();
     println!("cargo:rustc-link-arg=-T{}/memory.ld", manifest_dir);
     println!("cargo:rerun-if-changed=memory.ld");
+
+    // build id advertised in the connect-time hello
+    let rev = Command::new("git")
+        .args(["rev-parse", "--short", "HEAD"])
+        .output()
+        .ok()
+        .filter(|o| o.status.success())
+        .map(|o| String::from_utf8_lossy(&o.stdout).trim().to_string())
+        .unwrap_or_else(|| "unknown".to_string());
+    println!("cargo:rustc-env=LISPI_BUILD_ID={}", rev);
+    watch_head(&Path::new(&manifest_dir).join("../.git"));
+}
+
+/// Rerun when HEAD moves. HEAD itself only changes on a checkout; a
+/// commit changes the branch ref it names (or `packed-refs`), and both
+/// are logged in `logs/HEAD`. Paths that don't exist aren't watched,
+/// since cargo would then rerun on every build.
+fn watch_head(git: &Path) {
+    let Ok(head) = fs::read_to_string(git.join("HEAD")) else {
+        // not built from a checkout: the id can't change under us
+        println!("cargo:rerun-if-changed=build.rs");
+        return;
+    };
+    let mut watched = vec![
+        git.join("HEAD"),
+        git.join("logs/HEAD"),
+        git.join("packed-refs"),
+    ];
+    if let Some(name) = head.strip_prefix("ref:") {
+        watched.push(git.join(name.trim()));
+    }
+    for path in watched.iter().filter(|p| p.exists()) {
+        println!("cargo:rerun-if-changed={}", path.display());
+    }
 }
@@ -49,6 +49,22 @@ _start:
     mcr p15, 0, r0, c1, c0, 0      // write SCTLR
     mcr p15, 0, r0, c7, c5, 4      // prefetch flush
 
+    // Turn on the VFP before any Rust runs: the target is hard-float,
+    // so the compiler may use it anywhere. Grant full access to cp10
+    // and cp11 (CPACR bits 20-23), then set FPEXC.EN. FPSCR goes to
+    // RunFast mode (flush-to-zero, default NaN, no traps), where the
+    // VFP11 handles every case in hardware instead of bouncing
+    // subnormals to support code we don't have.
+    mrc p15, 0, r0, c1, c0, 2      // read CPACR
+    orr r0, r0, #(0xF << 20)       // cp10, cp11: full access
+    mcr p15, 0, r0, c1, c0, 2      // write CPACR
+    mov r0, #0
+    mcr p15, 0, r0, c7, c5, 4      // prefetch flush
+    mov r0, #(1 << 30)
+    vmsr fpexc, r0                 // FPEXC.EN
+    mov r0, #(3 << 24)
+    vmsr fpscr, r0                 // FZ | DN
+
     // Clear the BSS (not very efficient; could be faster)
     mov r0, #0
     ldr r1, ={BSS_START}
@@ -0,0 +1,74 @@
+//! Kernel side of the host file service (see `shared::host`): the
+//! `@host/...` syscalls send their request and wait here for the unix
+//! side to answer.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::time::Duration;
+
+use shared::host::{HostError, max_file_len};
+use shared::{Control, Message, MessageKind};
+
+use super::{interrupt, link};
+use crate::utils::timer;
+
+/// How long the unix side gets to answer. It is serving a local file, so
+/// running out means nobody is there.
+const REPLY_TIMEOUT_MS: u64 = 5000;
+
+/// The contents of `path` on the host.
+pub fn read(path: &str) -> Result<Vec<u8>, &'static str> {
+    request(Control::HostRead { path: path.into() })
+}
+
+/// Replace `path` on the host with `data`, or append `data` to it.
+pub fn write(path: &str, data: &[u8], append: bool) -> Result<(), &'static str> {
+    let max = link::with(|framer| max_file_len(framer.max_payload));
+    if path.len() + data.len() > max {
+        return Err(HostError::TooLarge.message());
+    }
+    request(Control::HostWrite {
+        path: String::from(path),
+        data: data.to_vec(),
+        append,
+    })
+    .map(drop)
+}
+
+fn request(control: Control) -> Result<Vec<u8>, &'static str> {
+    link::with(|framer| {
+        framer
+            .send_message(&Message::control(&control))
+            .map_err(|_| "host: unix-side didn't take the request.")?;
+        let deadline = Duration::from_millis(REPLY_TIMEOUT_MS);
+        let start = timer::now_us();
+        // anything else that comes in stays queued, in order, for whoever
+        // reads the link next
+        let answers = |m: &Message| {
+            m.kind == MessageKind::Control
+                && matches!(
+                    Control::decode(&m.body),
+                    Some(Control::HostReply(_) | Control::Interrupt)
+                )
+        };
+        loop {
+            match framer
+                .take_queued(answers)
+                .and_then(|m| Control::decode(&m.body))
+            {
+                Some(Control::HostReply(reply)) => return reply.map_err(HostError::message),
+                // Ctrl-C while the host was busy
+                Some(Control::Interrupt) => {
+                    interrupt::raise();
+                    return Err(interrupt::INTERRUPTED);
+                }
+                _ => {}
+            }
+            let elapsed = Duration::from_micros(timer::now_us().wrapping_sub(start) as u64);
+            let Some(left) = deadline.checked_sub(elapsed) else {
+                return Err("host: unix-side didn't answer.");
+            };
+            let _ = framer.poll(left);
+        }
+    })
+}
@@ -0,0 +1,61 @@
+//! Ctrl-C from the unix side (`Control::Interrupt`, see
+//! `shared::control`). `main` only reads the UART between requests, so
+//! the interpreter calls `check` as it goes, and JIT'd code gets checked
+//! each time it is entered or returns. Once an interrupt has arrived it
+//! stays pending, failing every check, until `clear` at the start of the
+//! next request.
+
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use shared::{Control, MessageKind};
+
+use super::{link, uart};
+
+/// The error an interrupted evaluation fails with.
+pub const INTERRUPTED: &str = "interrupted";
+
+static PENDING: AtomicBool = AtomicBool::new(false);
+
+/// True once the unix side has asked us to stop. Cheap unless a byte is
+/// waiting on the UART or a message is queued on the link.
+pub fn pending() -> bool {
+    if PENDING.load(Ordering::Relaxed) {
+        return true;
+    }
+    // Read through the link's framer, so its sequence numbers stay right,
+    // and leave anything that isn't an interrupt (a host reply, or the
+    // request being evaluated again because our ack got lost) queued on
+    // it. An interrupt may also have been queued while the framer waited
+    // for an ack. If the framer is busy further up the stack, whoever
+    // has it sees the interrupt (see `host`).
+    let interrupted = link::try_with(|framer| {
+        if uart::has_data() {
+            let _ = framer.poll(framer.timeout);
+        }
+        framer
+            .take_queued(|msg| {
+                msg.kind == MessageKind::Control
+                    && Control::decode(&msg.body) == Some(Control::Interrupt)
+            })
+            .is_some()
+    });
+    if interrupted == Some(true) {
+        PENDING.store(true, Ordering::Relaxed);
+    }
+    PENDING.load(Ordering::Relaxed)
+}
+
+/// `Err(INTERRUPTED)` once the unix side has asked us to stop.
+pub fn check() -> Result<(), &'static str> {
+    if pending() { Err(INTERRUPTED) } else { Ok(()) }
+}
+
+/// Note an interrupt that arrived some other way (see `host`).
+pub fn raise() {
+    PENDING.store(true, Ordering::Relaxed);
+}
+
+/// Forget an interrupt that arrived for an earlier request.
+pub fn clear() {
+    PENDING.store(false, Ordering::Relaxed);
+}
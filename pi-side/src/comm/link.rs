@@ -0,0 +1,34 @@
+//! The kernel's end of the framed link. `main` answers requests on it,
+//! but some syscalls talk to the unix side in the middle of an evaluation
+//! (see `host`), and they have to use the same framer: a second one would
+//! start its own sequence numbers, and the unix side drops a frame that
+//! repeats the last one's.
+
+use core::cell::SyncUnsafeCell;
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use shared::Framer;
+
+use super::uart::PiUart;
+
+static FRAMER: SyncUnsafeCell<Option<Framer<PiUart>>> = SyncUnsafeCell::new(None);
+static BUSY: AtomicBool = AtomicBool::new(false);
+
+/// Run `f` with the link's framer. Not re-entrant: `f` mustn't evaluate
+/// lisp.
+pub fn with<R>(f: impl FnOnce(&mut Framer<PiUart>) -> R) -> R {
+    try_with(f).expect("link framer already in use")
+}
+
+/// `with`, or None if the framer is already in use further up the stack
+/// (`print` falls back to the bare UART then).
+pub fn try_with<R>(f: impl FnOnce(&mut Framer<PiUart>) -> R) -> Option<R> {
+    if BUSY.swap(true, Ordering::Acquire) {
+        return None;
+    }
+    // SAFETY: one core, and BUSY keeps this the only reference
+    let framer = unsafe { (*FRAMER.get()).get_or_insert_with(|| Framer::pi_side(PiUart)) };
+    let result = f(framer);
+    BUSY.store(false, Ordering::Release);
+    Some(result)
+}
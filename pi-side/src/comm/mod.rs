@@ -1,5 +1,11 @@
 #[allow(unused)]
 pub mod gpio;
+pub mod host;
+pub mod interrupt;
+pub mod link;
+#[allow(unused)]
+pub mod pl011;
+pub mod transfer;
 #[allow(unused)]
 pub mod uart;
 #[macro_use]
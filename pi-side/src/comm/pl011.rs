@@ -0,0 +1,129 @@
+//! PL011 "full" UART. Only used for baud rates the mini-UART's coarse
+//! 250 MHz / 8 divisor can't hit accurately (460800 and up); see
+//! `uart::init_baud`.
+
+use crate::comm::gpio::{self, GpioFunc};
+use crate::utils::memory;
+use crate::utils::memory::dmb;
+
+const PL011_BASE: usize = 0x2020_1000;
+const PL011_DR: usize = PL011_BASE;
+const PL011_FR: usize = PL011_BASE + 0x18;
+const PL011_IBRD: usize = PL011_BASE + 0x24;
+const PL011_FBRD: usize = PL011_BASE + 0x28;
+const PL011_LCRH: usize = PL011_BASE + 0x2C;
+const PL011_CR: usize = PL011_BASE + 0x30;
+const PL011_IMSC: usize = PL011_BASE + 0x38;
+const PL011_ICR: usize = PL011_BASE + 0x44;
+
+// flag register bits
+const FR_BUSY: u32 = 1 << 3;
+const FR_RXFE: u32 = 1 << 4;
+const FR_TXFF: u32 = 1 << 5;
+const FR_TXFE: u32 = 1 << 7;
+
+// line control: 8-bit words, FIFOs enabled
+const LCRH_WLEN8: u32 = 0b11 << 5;
+const LCRH_FEN: u32 = 1 << 4;
+
+// control: uart enable, tx enable, rx enable
+const CR_UARTEN: u32 = 1 << 0;
+const CR_TXE: u32 = 1 << 8;
+const CR_RXE: u32 = 1 << 9;
+
+/// Reference clock feeding the PL011. This is the firmware's
+/// `init_uart_clock` (48 MHz by default; configurable in config.txt).
+const UART_CLK: u32 = 48_000_000;
+
+/// Integer and fractional baud divisors for `baud`:
+/// divisor = UART_CLK / (16 * baud), with the fraction in 1/64ths.
+fn divisors(baud: u32) -> (u32, u32) {
+    // UART_CLK * 64 / (16 * baud), rounded to nearest
+    let scaled = ((UART_CLK as u64 * 4) + baud as u64 / 2) / baud as u64;
+    ((scaled >> 6) as u32, (scaled & 0x3F) as u32)
+}
+
+/// Route GPIO 14/15 to the PL011 and configure it for 8n1 at `baud`,
+/// no interrupts.
+pub fn init(baud: u32) {
+    unsafe {
+        dmb();
+        // disable while we configure, and let any in-flight byte drain
+        memory::put32(PL011_CR, 0);
+        while memory::get32(PL011_FR) & FR_BUSY != 0 {}
+        // flush the FIFOs by disabling them
+        memory::put32(PL011_LCRH, 0);
+        dmb();
+
+        // set GPIO 14,15 to alt0 (PL011)
+        gpio::gpio_set_function(14, GpioFunc::Alt0);
+        gpio::gpio_set_function(15, GpioFunc::Alt0);
+        dmb();
+
+        // clear pending interrupts and mask all of them
+        memory::put32(PL011_ICR, 0x7FF);
+        memory::put32(PL011_IMSC, 0);
+
+        let (ibrd, fbrd) = divisors(baud);
+        memory::put32(PL011_IBRD, ibrd);
+        memory::put32(PL011_FBRD, fbrd);
+        // LCRH must be written after the divisors to latch them
+        memory::put32(PL011_LCRH, LCRH_WLEN8 | LCRH_FEN);
+        memory::put32(PL011_CR, CR_UARTEN | CR_TXE | CR_RXE);
+        dmb();
+    }
+}
+
+/// Disable the PL011. Flushes TX first.
+pub fn disable() {
+    flush_tx();
+    unsafe {
+        memory::put32(PL011_CR, 0);
+    }
+}
+
+/// Returns true if the RX FIFO has at least one byte.
+pub fn has_data() -> bool {
+    unsafe { memory::get32(PL011_FR) & FR_RXFE == 0 }
+}
+
+/// Returns true if the TX FIFO has room for at least one byte.
+pub fn can_put8() -> bool {
+    unsafe { memory::get32(PL011_FR) & FR_TXFF == 0 }
+}
+
+/// Returns true if the TX FIFO is empty AND the transmitter is idle.
+pub fn tx_is_empty() -> bool {
+    unsafe {
+        let fr = memory::get32(PL011_FR);
+        fr & FR_TXFE != 0 && fr & FR_BUSY == 0
+    }
+}
+
+/// Block until at least one byte is available, then return it.
+pub fn get8() -> u8 {
+    unsafe {
+        dmb();
+        while !has_data() {}
+        let r = memory::get32(PL011_DR) & 0xFF;
+        dmb();
+        r as u8
+    }
+}
+
+/// Write one byte to the TX FIFO, blocking until space is available.
+pub fn put8(c: u8) {
+    unsafe {
+        dmb();
+        while !can_put8() {}
+        memory::put32(PL011_DR, c as u32);
+        dmb();
+    }
+}
+
+/// Block until all TX bytes have been transmitted.
+pub fn flush_tx() {
+    while !tx_is_empty() {
+        core::hint::black_box(0);
+    }
+}
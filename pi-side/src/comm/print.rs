@@ -1,3 +1,41 @@
+//! `print!` and `println!`. Once a unix side has said hello, its framer
+//! throws away anything on the line that isn't a frame, so console
+//! output goes out as `MessageKind::Output` messages instead, posted
+//! without waiting for an ack so a print can't stall the kernel when
+//! nobody is reading. Before that (the boot banner), and whenever the
+//! framer can't be used (it is what panicked), it is written to the
+//! UART as is.
+
+use alloc::fmt::format;
+use core::fmt::{self, Write};
+use core::sync::atomic::{AtomicBool, Ordering};
+
+use shared::{Message, MessageKind};
+
+use crate::comm::{link, uart};
+
+/// Whether output is framed.
+static FRAMED: AtomicBool = AtomicBool::new(false);
+
+/// Frame output from now on (`on`), or go back to the bare UART.
+pub fn set_framed(on: bool) {
+    FRAMED.store(on, Ordering::Relaxed);
+}
+
+/// Print `args`, as one `Output` message if output is framed.
+pub fn write(args: fmt::Arguments) {
+    if FRAMED.load(Ordering::Relaxed) {
+        let text = format(args);
+        let output = Message::new(MessageKind::Output, 0, text.as_bytes());
+        if link::try_with(|framer| framer.post_message(&output)).is_none() {
+            uart::write_bytes(text.as_bytes());
+        }
+    } else {
+        let _ = UartProxy.write_fmt(args);
+    }
+    uart::flush();
+}
+
 pub struct UartProxy;
 
 impl ::core::fmt::Write for UartProxy {
@@ -10,22 +48,15 @@ impl ::core::fmt::Write for UartProxy {
 #[macro_export]
 macro_rules! print {
     ($($args:tt)*) => {
-        {
-            #[allow(unused)]
-            use ::core::fmt::Write;
-            let _ = ::core::write!(&mut $crate::comm::print::UartProxy, $($args)*);
-            $crate::comm::uart::flush();
-        }
+        $crate::comm::print::write(::core::format_args!($($args)*))
     }
 }
 #[macro_export]
 macro_rules! println {
+    () => {
+        $crate::print!("\n")
+    };
     ($($args:tt)*) => {
-        {
-            #[allow(unused)]
-            use ::core::fmt::Write;
-            let _ = ::core::writeln!(&mut $crate::comm::print::UartProxy, $($args)*);
-            $crate::comm::uart::flush();
-        }
+        $crate::comm::print::write(::core::format_args!("{}\n", ::core::format_args!($($args)*)))
     }
 }
@@ -0,0 +1,142 @@
+//! Pi side of the bulk transfer protocol (see `shared::transfer`).
+
+use alloc::alloc::{Layout, alloc_zeroed};
+use shared::transfer::{self, Chunk, Progress};
+use shared::{Control, Framer, Message, MessageKind};
+
+use crate::comm::uart::PiUart;
+
+/// State of the most recent upload, kept across `Upload` requests so an
+/// interrupted upload to the same place can pick up where it stopped.
+#[derive(Default)]
+pub struct Uploads {
+    current: Option<Progress>,
+}
+
+impl Uploads {
+    /// Handle `Control::Upload`: start a new upload (allocating if
+    /// `addr == 0`) or resume the current one, and report where to start.
+    /// A range past the end of the address space is refused (addr 0).
+    pub fn begin(&mut self, framer: &mut Framer<PiUart>, addr: u32, len: u32) {
+        let progress = match self.current {
+            Some(p) if addr != 0 && p.addr == addr && p.len == len => p,
+            _ => {
+                let addr = match addr {
+                    0 => allocate(len),
+                    _ if addr.checked_add(len).is_none() => 0,
+                    _ => addr,
+                };
+                Progress {
+                    addr,
+                    len,
+                    offset: 0,
+                }
+            }
+        };
+        // a refused upload (addr 0) isn't worth remembering
+        self.current = (progress.addr != 0).then_some(progress);
+        reply(framer, progress);
+    }
+
+    /// Handle `Control::QueryTransfer`.
+    pub fn query(&self, framer: &mut Framer<PiUart>) {
+        let progress = self.current.unwrap_or(Progress {
+            addr: 0,
+            len: 0,
+            offset: 0,
+        });
+        reply(framer, progress);
+    }
+
+    /// Handle a `MessageKind::Blob`. Chunks that don't continue the
+    /// current upload exactly, or fail their checksum, are dropped; the
+    /// unix side finds out via `query` and resends.
+    pub fn chunk(&mut self, msg: Message) {
+        let Some(p) = self.current.as_mut() else {
+            return;
+        };
+        let Some(chunk) = Chunk::decode(msg.body) else {
+            return;
+        };
+        let end = chunk.offset as usize + chunk.data.len();
+        if chunk.offset != p.offset || end > p.len as usize {
+            return;
+        }
+        // `begin` made sure the whole range fits
+        let Some(dst) = p.addr.checked_add(chunk.offset) else {
+            return;
+        };
+        // a corrupt chunk mustn't touch the target, which may be live
+        if shared::crc::crc32(&chunk.data) != chunk.crc {
+            return;
+        }
+        let dst = dst as *mut u8;
+        unsafe {
+            core::ptr::copy_nonoverlapping(chunk.data.as_ptr(), dst, chunk.data.len());
+            // check what actually landed in memory, not just what arrived
+            let landed = core::slice::from_raw_parts(dst, chunk.data.len());
+            if shared::crc::crc32(landed) != chunk.crc {
+                return;
+            }
+        }
+        p.offset = end as u32;
+    }
+}
+
+/// Handle `Control::Download`: stream `addr + offset .. addr + len` as
+/// chunks, then report completion. Stops early if the link fails; the
+/// unix side re-requests from the first byte it is missing. A range
+/// past the end of the address space is refused (addr 0).
+pub fn download(framer: &mut Framer<PiUart>, addr: u32, len: u32, offset: u32) {
+    if addr.checked_add(len).is_none() {
+        reply(
+            framer,
+            Progress {
+                addr: 0,
+                len,
+                offset: 0,
+            },
+        );
+        return;
+    }
+    let step = transfer::chunk_len(framer.max_payload);
+    let mut at = offset;
+    while at < len {
+        let n = step.min((len - at) as usize);
+        let Some(src) = addr.checked_add(at) else {
+            break;
+        };
+        let data = unsafe { core::slice::from_raw_parts(src as *const u8, n) };
+        let chunk = Chunk::new(at, data);
+        if framer
+            .send_message(&Message::new(MessageKind::Blob, 0, &chunk.encode()))
+            .is_err()
+        {
+            return;
+        }
+        at += n as u32;
+    }
+    reply(
+        framer,
+        Progress {
+            addr,
+            len,
+            offset: at,
+        },
+    );
+}
+
+fn reply(framer: &mut Framer<PiUart>, progress: Progress) {
+    let _ = framer.send_message(&Message::control(&Control::Transfer(progress)));
+}
+
+/// Allocate a zeroed buffer for an upload, sized and aligned like
+/// `(@alloc32 (ceil len/4))` so it can later be released with `@free32`.
+/// Returns 0 on failure.
+fn allocate(len: u32) -> u32 {
+    let size = (len as usize).div_ceil(4).max(1) * 4;
+    let Ok(layout) = Layout::from_size_align(size, 4) else {
+        return 0;
+    };
+    unsafe { alloc_zeroed(layout) as u32 }
+}
@@ -1,6 +1,11 @@
+use core::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use core::time::Duration;
+
 use crate::comm::gpio::{self, GpioFunc};
+use crate::comm::pl011;
 use crate::utils::memory;
 use crate::utils::memory::dmb;
+use crate::utils::timer;
 
 const AUX_ENABLES: usize = 0x2021_5004;
 const AUX_MU_IO_REG: usize = 0x2021_5040;
@@ -13,8 +18,59 @@ const AUX_MU_CNTL_REG: usize = 0x2021_5060;
 const AUX_MU_STAT_REG: usize = 0x2021_5064;
 const AUX_MU_BAUD: usize = 0x2021_5068;
 
-/// Initialize mini-UART to 8n1 115200 baud, no interrupts.
+/// Baud rates `init_baud` can configure.
+pub const SUPPORTED_BAUD_RATES: &[u32] = &[115200, 230400, 460800, 921600];
+
+/// Fastest rate we run on the mini-UART. Its divisor is
+/// sys_clk / (8 * (reg + 1)), which is already ~1% off at 460800 and
+/// ~3% off at 921600, so faster rates move the pins to the PL011.
+const MINI_UART_MAX_BAUD: u32 = 230400;
+
+/// Whether GPIO 14/15 are currently routed to the PL011 instead of the
+/// mini-UART. Every byte-level accessor below dispatches on this.
+static PL011_ACTIVE: AtomicBool = AtomicBool::new(false);
+
+static CURRENT_BAUD: AtomicU32 = AtomicU32::new(shared::BAUD_RATE);
+
+fn pl011_active() -> bool {
+    PL011_ACTIVE.load(Ordering::Relaxed)
+}
+
+/// The baud rate the link is currently running at.
+pub fn current_baud() -> u32 {
+    CURRENT_BAUD.load(Ordering::Relaxed)
+}
+
+/// Initialize mini-UART to 8n1 at `shared::BAUD_RATE`, no interrupts.
 pub fn init() {
+    init_mini(shared::BAUD_RATE);
+}
+
+/// Re-initialize the link at `baud`, picking the mini-UART or the PL011
+/// as appropriate. Drains pending TX first. Returns false (and leaves the
+/// UART alone) if `baud` isn't one of `SUPPORTED_BAUD_RATES`.
+pub fn init_baud(baud: u32) -> bool {
+    if !SUPPORTED_BAUD_RATES.contains(&baud) {
+        return false;
+    }
+    flush_tx();
+    if baud <= MINI_UART_MAX_BAUD {
+        if pl011_active() {
+            pl011::disable();
+        }
+        init_mini(baud);
+    } else {
+        if !pl011_active() {
+            disable();
+        }
+        pl011::init(baud);
+        PL011_ACTIVE.store(true, Ordering::Relaxed);
+        CURRENT_BAUD.store(baud, Ordering::Relaxed);
+    }
+    true
+}
+
+fn init_mini(baud: u32) {
     unsafe {
         dmb();
         // set GPIO 14,15 to alt5 (mini-UART)
@@ -39,12 +95,14 @@ pub fn init() {
         memory::put32(AUX_MU_LCR_REG, 0b11);
         // baudrate = system_clock / (8 * (reg + 1))  =>  reg = sys_clk/(8*baud) - 1
         const SYS_CLK: u32 = 250_000_000;
-        let baud_reg = SYS_CLK / (8 * shared::BAUD_RATE) - 1;
+        let baud_reg = SYS_CLK / (8 * baud) - 1;
         memory::put32(AUX_MU_BAUD, baud_reg);
         // enable tx and rx
         memory::put32(AUX_MU_CNTL_REG, 0b11);
         dmb();
     }
+    PL011_ACTIVE.store(false, Ordering::Relaxed);
+    CURRENT_BAUD.store(baud, Ordering::Relaxed);
 }
 
 /// Disable the mini-UART. Flushes TX first.
@@ -58,6 +116,9 @@ pub fn disable() {
 
 /// Block until at least one byte is available, then return it.
 pub fn get8() -> u8 {
+    if pl011_active() {
+        return pl011::get8();
+    }
     unsafe {
         dmb();
         // bit 0 of STAT = symbol available
@@ -68,6 +129,18 @@ pub fn get8() -> u8 {
     }
 }
 
+/// Block for at most `timeout_us` microseconds waiting for a byte.
+/// Returns None if nothing arrived in time.
+pub fn get8_timeout(timeout_us: u32) -> Option<u8> {
+    let start = timer::now_us();
+    while !has_data() {
+        if timer::now_us().wrapping_sub(start) >= timeout_us {
+            return None;
+        }
+    }
+    Some(get8())
+}
+
 /// Non-blocking read: returns Some(byte) if data available, None otherwise.
 pub fn get8_async() -> Option<u8> {
     if has_data() { Some(get8()) } else { None }
@@ -75,16 +148,25 @@ pub fn get8_async() -> Option<u8> {
 
 /// Returns true if the RX FIFO has at least one byte.
 pub fn has_data() -> bool {
+    if pl011_active() {
+        return pl011::has_data();
+    }
     unsafe { (memory::get32(AUX_MU_STAT_REG) & 1) != 0 }
 }
 
 /// Returns true if the TX FIFO has room for at least one byte.
 pub fn can_put8() -> bool {
+    if pl011_active() {
+        return pl011::can_put8();
+    }
     unsafe { (memory::get32(AUX_MU_STAT_REG) & 0b10) != 0 }
 }
 
 /// Write one byte to the TX FIFO, blocking until space is available.
 pub fn put8(c: u8) {
+    if pl011_active() {
+        return pl011::put8(c);
+    }
     unsafe {
         dmb();
         while !can_put8() {}
@@ -95,6 +177,9 @@ pub fn put8(c: u8) {
 
 /// Returns true if the TX FIFO is empty AND the transmitter is idle.
 pub fn tx_is_empty() -> bool {
+    if pl011_active() {
+        return pl011::tx_is_empty();
+    }
     unsafe { (memory::get32(AUX_MU_LSR_REG) & (1 << 6)) != 0 }
 }
 
@@ -147,15 +232,15 @@ impl shared::Transport for PiUart {
     fn put8(&mut self, b: u8) {
         put8(b);
     }
-    fn get8(&mut self) -> u8 {
-        get8()
+    fn get8(&mut self, timeout: Option<Duration>) -> Result<u8, shared::TransportError> {
+        match timeout {
+            None => Ok(get8()),
+            Some(t) => get8_timeout(t.as_micros() as u32).ok_or(shared::TransportError::Timeout),
+        }
     }
     fn put32(&mut self, v: u32) {
         put32(v);
     }
-    fn get32(&mut self) -> u32 {
-        get32()
-    }
     fn flush(&mut self) {
         flush_tx();
     }
@@ -31,15 +31,27 @@ pub type Symbol = String<SYMB_NAME_LEN>;
 /// most LISPs use pointer identity (`eq?`) for procedures anyway.
 #[derive(Clone, Debug, PartialEq)]
 pub struct Closure {
+    /// The required params.
     pub params: Vec<Rc<Symbol>>,
+    /// The `&optional` params, each with the form giving its default.
+    pub optional: Vec<(Rc<Symbol>, Rc<Value>)>,
+    /// The `&rest` param, bound to a list of the arguments left over.
+    pub rest: Option<Rc<Symbol>>,
     pub body: Rc<Value>,
     pub env: Rc<environment::Environment>,
     pub hits: Rc<Cell<u64>>,
 }
 
+impl Closure {
+    /// True if the closure has `&optional` or `&rest` params, so takes
+    /// a varying number of arguments.
+    pub fn variadic(&self) -> bool {
+        !self.optional.is_empty() || self.rest.is_some()
+    }
+}
+
 #[derive(Clone, Debug, PartialEq)]
 pub struct Macro {
-    pub params: Vec<Rc<Symbol>>,
     pub closure: Closure,
 }
 
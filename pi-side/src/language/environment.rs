@@ -19,6 +19,7 @@ use heapless::String;
 
 use super::ast;
 use super::constants::SYMB_NAME_LEN;
+use super::source::SourceMap;
 
 /// A single mutable binding slot, shareable across multiple environments.
 pub type Binding = Rc<RefCell<Rc<ast::Value>>>;
@@ -50,15 +51,26 @@ pub struct Image {
     /// Stack of scope frames. Bottom = global, top = innermost scope.
     /// Lookups search top-to-bottom; inserts go into the top (Owned) frame.
     frames: Vec<Frame>,
+    /// Where the parsed forms still around were read from.
+    spans: SourceMap,
 }
 
 impl Image {
     pub fn new() -> Self {
         Image {
             frames: vec![Frame::Owned(BTreeMap::new())],
+            spans: SourceMap::default(),
         }
     }
 
+    pub fn spans(&self) -> &SourceMap {
+        &self.spans
+    }
+
+    pub fn spans_mut(&mut self) -> &mut SourceMap {
+        &mut self.spans
+    }
+
     /// Push an empty scope frame (for params, let-bindings, etc.).
     pub fn push_frame(&mut self) {
         self.frames.push(Frame::Owned(BTreeMap::new()));
@@ -76,6 +88,17 @@ impl Image {
         self.frames.pop();
     }
 
+    /// Number of frames on the stack, for `unwind`.
+    pub fn depth(&self) -> usize {
+        self.frames.len()
+    }
+
+    /// Drop the frames an evaluation that bailed out with an error left
+    /// above `depth`.
+    pub fn unwind(&mut self, depth: usize) {
+        self.frames.truncate(depth.max(1));
+    }
+
     /// Create a **fresh** binding for `name` with `value` in the top frame.
     /// The top frame must be Owned.
     pub fn insert(&mut self, name: String<SYMB_NAME_LEN>, value: Rc<ast::Value>) {
@@ -87,6 +110,14 @@ impl Image {
         }
     }
 
+    /// Names bound in the global frame, in order.
+    pub fn global_names(&self) -> Vec<alloc::string::String> {
+        match &self.frames[0] {
+            Frame::Owned(m) => m.keys().map(|k| k.as_str().into()).collect(),
+            Frame::Shared(m) => m.keys().map(|k| k.as_str().into()).collect(),
+        }
+    }
+
     /// Look up the current value of `name`, searching top-to-bottom.
     /// If the resolved value is a Closure, bump its hit counter — used
     /// for hot-path tracking (e.g. JIT compilation candidates).
@@ -0,0 +1,283 @@
+//! Evaluation errors.
+//!
+//! An `Error` says what went wrong (`ErrorKind` and a message), what it
+//! went wrong with (the offending value, printed, if there was one), and
+//! where: `call_closure` adds the call it was evaluating to every error
+//! that unwinds through it, so by the time one reaches `main` it carries
+//! a backtrace of lisp calls, innermost first. Tail calls reuse their
+//! caller's frame, so a loop written as tail recursion shows up once.
+//! Forms the parser read carry their source position along: the
+//! innermost one an error unwinds through is noted as where it happened,
+//! and the calls in the backtrace are shown with theirs.
+//!
+//! Lisp code raises its own errors with `error`, and `throw` is an error
+//! too, carrying its tag and value up to the `catch` for that tag (see
+//! `Error::catch`).
+
+use alloc::borrow::Cow;
+use alloc::format;
+use alloc::rc::Rc;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
+
+use super::ast::Value;
+use super::source::SourceMap;
+
+/// Calls kept in a backtrace; a runaway recursion would otherwise
+/// collect one per level.
+const MAX_BACKTRACE: usize = 12;
+
+/// Longest a value or call is printed in an error.
+const MAX_SHOWN: usize = 64;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ErrorKind {
+    /// A symbol with no binding.
+    Unbound,
+    /// A value of the wrong type, or a head that can't be called.
+    Type,
+    /// Too few or too many arguments.
+    Arity,
+    /// A malformed form: a bad parameter or binding list, an unquote
+    /// outside a quasiquote...
+    Syntax,
+    /// An index or range outside an array.
+    Bounds,
+    /// Division by zero.
+    Arithmetic,
+    /// Alignment, layout or allocation trouble in the memory syscalls.
+    Memory,
+    /// The unix side couldn't serve a `@host/...` request.
+    Host,
+    /// The JIT refused a closure.
+    Jit,
+    /// The unix side asked us to stop.
+    Interrupted,
+    /// Raised by `(error ...)`.
+    User,
+    /// A `throw` no `catch` was waiting for.
+    Throw,
+}
+
+impl ErrorKind {
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Unbound => "unbound symbol",
+            Self::Type => "type error",
+            Self::Arity => "arity error",
+            Self::Syntax => "syntax error",
+            Self::Bounds => "out of bounds",
+            Self::Arithmetic => "arithmetic error",
+            Self::Memory => "memory error",
+            Self::Host => "host error",
+            Self::Jit => "jit error",
+            Self::Interrupted => "interrupted",
+            Self::User => "user error",
+            Self::Throw => "uncaught throw",
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Error {
+    pub kind: ErrorKind,
+    pub message: Cow<'static, str>,
+    /// The offending symbol or value, printed.
+    pub value: Option<String>,
+    /// The tag of a `throw`.
+    pub tag: Option<Rc<Value>>,
+    /// The value of a `throw`, or what `(error ...)` was given.
+    pub payload: Option<Rc<Value>>,
+    /// The innermost form with a known source position that the error
+    /// happened in, printed with it.
+    pub at: Option<String>,
+    /// The calls the error unwound through, printed, innermost first.
+    pub backtrace: Vec<String>,
+    /// Calls left out of `backtrace` past `MAX_BACKTRACE`.
+    pub elided: usize,
+}
+
+impl Error {
+    pub fn new(kind: ErrorKind, message: impl Into<Cow<'static, str>>) -> Self {
+        Self {
+            kind,
+            message: message.into(),
+            value: None,
+            tag: None,
+            payload: None,
+            at: None,
+            backtrace: Vec::new(),
+            elided: 0,
+        }
+    }
+
+    pub fn unbound(name: &str) -> Self {
+        Self::new(ErrorKind::Unbound, String::from(name))
+    }
+
+    pub fn type_error(message: &'static str) -> Self {
+        Self::new(ErrorKind::Type, message)
+    }
+
+    pub fn arity(message: &'static str) -> Self {
+        Self::new(ErrorKind::Arity, message)
+    }
+
+    pub fn syntax(message: &'static str) -> Self {
+        Self::new(ErrorKind::Syntax, message)
+    }
+
+    pub fn bounds(message: &'static str) -> Self {
+        Self::new(ErrorKind::Bounds, message)
+    }
+
+    pub fn arithmetic(message: &'static str) -> Self {
+        Self::new(ErrorKind::Arithmetic, message)
+    }
+
+    pub fn memory(message: &'static str) -> Self {
+        Self::new(ErrorKind::Memory, message)
+    }
+
+    pub fn jit(message: &'static str) -> Self {
+        Self::new(ErrorKind::Jit, message)
+    }
+
+    pub fn interrupted() -> Self {
+        Self::new(ErrorKind::Interrupted, "stopped from the unix side.")
+    }
+
+    /// `(error message [payload])`.
+    pub fn user(message: String, payload: Option<Value>) -> Self {
+        let mut e = Self::new(ErrorKind::User, message);
+        if let Some(p) = payload {
+            e = e.with(&p);
+            e.payload = Some(Rc::new(p));
+        }
+        e
+    }
+
+    /// `(throw tag value)`, on its way to the `catch` for `tag`.
+    pub fn throw(tag: Value, value: Value) -> Self {
+        let mut e = Self::new(ErrorKind::Throw, "no catch for this tag.").with(&tag);
+        e.tag = Some(Rc::new(tag));
+        e.payload = Some(Rc::new(value));
+        e
+    }
+
+    /// What `(catch tag ...)` returns for this error, or `None` if it
+    /// doesn't catch it. A `throw` is caught by its tag; any other
+    /// error by the tag `error`, as `(kind message payload)`: `kind` a
+    /// symbol like `type-error`, `payload` what `(error ...)` was given,
+    /// or the offending value, printed. Interrupts aren't caught.
+    pub fn catch(&self, tag: &Value) -> Option<Value> {
+        match self.kind {
+            ErrorKind::Throw if self.tag.as_deref() == Some(tag) => {
+                self.payload.as_deref().cloned()
+            }
+            ErrorKind::Throw | ErrorKind::Interrupted => None,
+            _ => {
+                let Value::Symbol(s) = tag else { return None };
+                if s.as_str() != "error" {
+                    return None;
+                }
+                let mut kind = super::ast::Symbol::new();
+                for c in self.kind.name().chars() {
+                    let _ = kind.push(if c == ' ' { '-' } else { c });
+                }
+                let payload = match (&self.payload, &self.value) {
+                    (Some(p), _) => Value::clone(p),
+                    (None, Some(v)) => Value::String(v.clone()),
+                    (None, None) => Value::Nil,
+                };
+                let message = Value::String(String::from(&*self.message));
+                Some(Value::cons(
+                    Value::Symbol(Rc::new(kind)),
+                    Value::cons(message, Value::cons(payload, Value::Nil)),
+                ))
+            }
+        }
+    }
+
+    /// A failed `comm::host` request (which fails with `INTERRUPTED` on
+    /// Ctrl-C).
+    pub fn host(message: &'static str) -> Self {
+        if message == crate::comm::interrupt::INTERRUPTED {
+            Self::interrupted()
+        } else {
+            Self::new(ErrorKind::Host, message)
+        }
+    }
+
+    /// Note `value` as what the error is about.
+    pub fn with_value(mut self, value: &impl fmt::Display) -> Self {
+        self.value = Some(shorten(format!("{}", value)));
+        self
+    }
+
+    /// Note `value` as what the error is about, strings quoted.
+    pub fn with(self, value: &Value) -> Self {
+        match value {
+            Value::String(s) => self.with_value(&format_args!("{:?}", s)),
+            v => self.with_value(v),
+        }
+    }
+
+    /// Note `form` as where the error happened, if it was read from a
+    /// source and no form inside it (or call it made) was.
+    pub fn within(mut self, form: &Value, spans: &SourceMap) -> Self {
+        if self.at.is_none() && self.backtrace.is_empty() {
+            self.at = spans.get(form).map(|_| located(form, spans));
+        }
+        self
+    }
+
+    /// Add `call` to the backtrace, the error having unwound through it.
+    pub fn called_from(mut self, call: &Value, spans: &SourceMap) -> Self {
+        if self.backtrace.len() < MAX_BACKTRACE {
+            self.backtrace.push(located(call, spans));
+        } else {
+            self.elided += 1;
+        }
+        self
+    }
+}
+
+/// `form`, shortened, and where it was read from if known.
+fn located(form: &Value, spans: &SourceMap) -> String {
+    let shown = shorten(format!("{}", form));
+    match spans.get(form) {
+        Some(span) => format!("{} [{}]", shown, span),
+        None => shown,
+    }
+}
+
+fn shorten(mut s: String) -> String {
+    if let Some((i, _)) = s.char_indices().nth(MAX_SHOWN) {
+        s.truncate(i);
+        s.push_str("...");
+    }
+    s
+}
+
+/// `kind: message`, the value on the same line, then where it happened
+/// and one line per call.
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.kind.name(), self.message)?;
+        if let Some(v) = &self.value {
+            write!(f, " Got: {}", v)?;
+        }
+        if let Some(at) = &self.at {
+            write!(f, "\n  at {}", at)?;
+        }
+        for call in &self.backtrace {
+            write!(f, "\n  in {}", call)?;
+        }
+        if self.elided > 0 {
+            write!(f, "\n  ... and {} more", self.elided)?;
+        }
+        Ok(())
+    }
+}
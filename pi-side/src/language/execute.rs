@@ -1,41 +1,46 @@
 //! LISP Interpreter
 
+use alloc::format;
 use alloc::rc::Rc;
 use alloc::vec::Vec;
+use core::fmt;
+
+use crate::comm::interrupt;
 
 use super::ast::{Closure, Value};
 use super::environment::Image;
+use super::error::{Error, ErrorKind};
 use super::special::execute_special;
 use super::syscalls::execute_syscall;
 
 /// Call a closure with already-prepared argument values.
 /// Contains the trampoline loop for tail-call optimization:
 /// if the body returns a TailCall token, rebind params and loop
-/// instead of recursing.
-fn call_closure(
+/// instead of recursing. An error from the body (or from binding the
+/// params) gets `call`, the form that made the call, added to its
+/// backtrace.
+pub(super) fn call_closure(
     c: &Closure,
     arg_vals: Vec<Rc<Value>>,
     image: &mut Image,
-) -> Result<Value, &'static str> {
+    call: &Value,
+) -> Result<Value, Error> {
     let mut closure = c.clone();
     let mut args = arg_vals;
 
     loop {
+        // a runaway recursion, tail or not, passes through here
+        interrupt::check().map_err(|_| Error::interrupted())?;
+
         // push captured env as a shared frame — O(1), just Rc::clone
         image.push_env(&closure.env);
         // push a fresh frame for parameter bindings
         image.push_frame();
 
-        closure
-            .params
-            .iter()
-            .zip(args.into_iter())
-            .for_each(|(param, val)| {
-                image.insert((**param).clone(), val);
-            });
-
         // body is always in tail position
-        let result = eval(Rc::clone(&closure.body), image, true)?;
+        let result = bind_params(&closure, args, image)
+            .and_then(|()| eval(Rc::clone(&closure.body), image, true))
+            .map_err(|e| e.called_from(call, image.spans()))?;
 
         image.pop_frame(); // params
         image.pop_frame(); // captured env
@@ -51,16 +56,70 @@ fn call_closure(
     }
 }
 
+/// Bind `args` to the params of `c` in the top frame: the required
+/// ones, then the `&optional` ones, evaluating the defaults of those
+/// not given in order (so a default can use the params before it),
+/// then a list of the rest to the `&rest` one.
+fn bind_params(c: &Closure, args: Vec<Rc<Value>>, image: &mut Image) -> Result<(), Error> {
+    let (min, max) = (c.params.len(), c.params.len() + c.optional.len());
+    let n = args.len();
+    if n < min || (c.rest.is_none() && n > max) {
+        let expected = match (c.rest.is_some(), min == max) {
+            (true, _) => format!("at least {}", min),
+            (false, true) => format!("{}", min),
+            (false, false) => format!("{} to {}", min, max),
+        };
+        return Err(arity_error(expected, max, n));
+    }
+    let mut args = args.into_iter();
+    for (param, val) in c.params.iter().zip(args.by_ref()) {
+        image.insert((**param).clone(), val);
+    }
+    for (param, default) in &c.optional {
+        let val = match args.next() {
+            Some(val) => val,
+            None => Rc::new(evaluate(Rc::clone(default), image)?),
+        };
+        image.insert((**param).clone(), val);
+    }
+    if let Some(rest) = &c.rest {
+        let list = args
+            .rev()
+            .fold(Value::Nil, |list, val| Value::Cons(val, Rc::new(list)));
+        image.insert((**rest).clone(), Rc::new(list));
+    }
+    Ok(())
+}
+
+/// `Expected <expected> argument(s), got <n>.`, `most` deciding the
+/// plural.
+fn arity_error(expected: impl fmt::Display, most: usize, n: usize) -> Error {
+    let s = if most == 1 { "" } else { "s" };
+    let message = format!("Expected {} argument{}, got {}.", expected, s, n);
+    Error::new(ErrorKind::Arity, message)
+}
+
+/// The elements of the list `list`, unevaluated.
+pub(super) fn list_items(list: &Value) -> Vec<Rc<Value>> {
+    let mut items = Vec::new();
+    let mut current = list;
+    while let Value::Cons(car, cdr) = current {
+        items.push(Rc::clone(car));
+        current = cdr;
+    }
+    items
+}
+
 /// Public API: evaluate an expression (never in tail position).
 #[allow(unused)]
-pub fn evaluate(sexp: Rc<Value>, image: &mut Image) -> Result<Value, &'static str> {
+pub fn evaluate(sexp: Rc<Value>, image: &mut Image) -> Result<Value, Error> {
     eval(sexp, image, false)
 }
 
 /// Internal: evaluate with tail-position flag.
 /// When `tail` is true and the result would be a closure call,
 /// returns a TailCall token instead of actually calling.
-pub(super) fn eval(sexp: Rc<Value>, image: &mut Image, tail: bool) -> Result<Value, &'static str> {
+pub(super) fn eval(sexp: Rc<Value>, image: &mut Image, tail: bool) -> Result<Value, Error> {
     match &*sexp {
         // fundamental values — return as-is
         Value::Nil
@@ -80,16 +139,19 @@ pub(super) fn eval(sexp: Rc<Value>, image: &mut Image, tail: bool) -> Result<Val
         // symbol — look up in environment
         Value::Symbol(s) => match image.get(s) {
             Some(v) => Ok((*v).clone()),
-            None => Err("Unknown symbol."),
+            None => Err(Error::unbound(s)),
         },
 
-        // list — execute it
-        Value::Cons(..) => exec(sexp, image, tail),
+        // list — execute it, noting where it was written if that fails
+        Value::Cons(..) => {
+            exec(Rc::clone(&sexp), image, tail).map_err(|e| e.within(&sexp, image.spans()))
+        }
     }
 }
 
 /// Execute a list sexp: the car is the action, dispatch on its type.
-fn exec(sexp: Rc<Value>, image: &mut Image, tail: bool) -> Result<Value, &'static str> {
+fn exec(sexp: Rc<Value>, image: &mut Image, tail: bool) -> Result<Value, Error> {
+    interrupt::check().map_err(|_| Error::interrupted())?;
     let action = sexp.car();
 
     // evaluate the head to figure out what we're calling (never tail)
@@ -98,38 +160,37 @@ fn exec(sexp: Rc<Value>, image: &mut Image, tail: bool) -> Result<Value, &'stati
     // match by value so we can move Closure into TailCall without cloning
     match resolved {
         Value::Closure(c) => {
-            // evaluate arguments in caller's scope (never tail)
-            let arg_vals: Vec<Rc<Value>> = c
-                .params
-                .iter()
-                .enumerate()
-                .map(|(i, _)| evaluate(sexp.nth(i + 1), image).map(|v| Rc::new(v)))
+            // evaluate arguments in caller's scope (never tail); how
+            // many there should be is `call_closure`'s business
+            let arg_vals: Vec<Rc<Value>> = list_items(&sexp.cdr())
+                .into_iter()
+                .map(|arg| evaluate(arg, image).map(Rc::new))
                 .collect::<Result<_, _>>()?;
 
             if tail {
                 // in tail position: return token, move closure — no clone
                 Ok(Value::TailCall(c, arg_vals))
             } else {
-                call_closure(&c, arg_vals, image)
+                call_closure(&c, arg_vals, image, &sexp)
             }
         }
         Value::Macro(m) => {
             // call the closure with UNEVALUATED args (raw sexps)
-            let arg_vals: Vec<Rc<Value>> = m
-                .params
-                .iter()
-                .enumerate()
-                .map(|(i, _)| sexp.nth(i + 1))
-                .collect();
+            let arg_vals = list_items(&sexp.cdr());
 
             // the closure returns the expanded sexp — then evaluate it
             // propagate tail: if macro call is in tail position, so is its expansion
-            let expanded = call_closure(&m.closure, arg_vals, image)?;
+            let expanded = call_closure(&m.closure, arg_vals, image, &sexp)?;
             eval(Rc::new(expanded), image, tail)
         }
         Value::Special(s) => execute_special(s.clone(), sexp, image, tail),
         Value::Syscall(s) => execute_syscall(s.clone(), sexp, image),
         Value::JittedClosure(jc) => {
+            let arity = jc.params.len();
+            if !sexp.nth_exists(arity) || sexp.nth_exists(arity + 1) {
+                let n = list_items(&sexp.cdr()).len();
+                return Err(arity_error(arity, arity, n));
+            }
             // Evaluate args in caller scope (never tail — the JIT body
             // is one straight-line trip, no interpreter trampoline).
             let arg_vals: Vec<Value> = jc
@@ -143,9 +204,10 @@ fn exec(sexp: Rc<Value>, image: &mut Image, tail: bool) -> Result<Value, &'stati
             // that don't match the compile-time dummy types.
             for (i, val) in arg_vals.iter().enumerate() {
                 if !jc.input_types[i].accepts(val) {
-                    return Err(
+                    return Err(Error::jit(
                         "jitted-closure: argument type mismatch — recompile (jit ...) with the new types.",
-                    );
+                    )
+                    .with(val));
                 }
             }
 
@@ -178,7 +240,10 @@ fn exec(sexp: Rc<Value>, image: &mut Image, tail: bool) -> Result<Value, &'stati
             }
             image.push_env(&Rc::new(param_frame));
 
-            let result = jc.executor.run(image);
+            let result = jc
+                .executor
+                .run(image)
+                .map_err(|e| e.called_from(&sexp, image.spans()));
 
             image.pop_frame();
             image.pop_frame();
@@ -191,6 +256,6 @@ fn exec(sexp: Rc<Value>, image: &mut Image, tail: bool) -> Result<Value, &'stati
 
             result
         }
-        _ => Err("Cannot execute: head is not callable."),
+        other => Err(Error::type_error("Cannot execute: head is not callable.").with(&other)),
     }
 }
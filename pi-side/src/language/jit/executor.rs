@@ -27,15 +27,16 @@ use alloc::rc::Rc;
 use alloc::vec::Vec;
 use core::cell::RefCell;
 
-use crate::comm::uart;
+use crate::comm::{interrupt, uart};
 use crate::language::ast::Value;
 use crate::language::environment::{Binding, Image};
+use crate::language::error::Error;
 use crate::language::execute::evaluate;
 use crate::language::number::Number;
 use crate::utils::memory::put32;
 
 use super::encodings::*;
-use super::ir::Name;
+use super::ir::{FLOATS_UNSUPPORTED, Name};
 use super::ir3::ImmNumber;
 use super::ir4::{Cond, Instr, Instruction, LIRSegment, Register};
 
@@ -150,6 +151,28 @@ static mut BINDING_SNAPSHOT_STACK: Vec<Rc<Value>> = Vec::new();
 /// entries consolidated at the root rather than scattered across the
 /// call chain.
 static mut CURRENT_EXECUTOR: *mut JitExecutor = core::ptr::null_mut();
+/// The first error a trip back into the interpreter failed with (a
+/// `throw` included). Emitted code can't unwind, so it carries on with
+/// nil, every later escape refusing to run, and the innermost `run`
+/// returns the error once the code is done.
+static mut ESCAPE_ERROR: Option<Error> = None;
+
+/// Keep `e` for `run` to return, unless an earlier error already is,
+/// and give emitted code the nil slot.
+fn escape_failed(e: Error) -> u32 {
+    unsafe {
+        let pending = &mut *core::ptr::addr_of_mut!(ESCAPE_ERROR);
+        if pending.is_none() {
+            *pending = Some(e);
+        }
+    }
+    0
+}
+
+/// The error kept by `escape_failed`, if any.
+fn take_escape_error() -> Option<Error> {
+    unsafe { (*core::ptr::addr_of_mut!(ESCAPE_ERROR)).take() }
+}
 /// Debug flag — when true, the next `JitExecutor::new()` dumps its LIR.
 pub static mut JIT_DUMP_NEXT: bool = false;
 
@@ -202,8 +225,12 @@ fn locals_get(id: usize) -> Option<Binding> {
 
 /// Materialize a `Value` into a freshly-allocated slot. Used when the
 /// JIT receives a runtime `Value` (e.g. a capture) it must surface to
-/// emitted code as a slot.
+/// emitted code as a slot. A float stops the code instead: emitted
+/// arithmetic would take whatever sits in its payload as an integer.
 fn intern_value(v: &Value) -> u32 {
+    if let Value::Number(Number::Float(_)) = v {
+        return escape_failed(Error::jit(FLOATS_UNSUPPORTED));
+    }
     unsafe {
         let id = alloc_slot();
         let slot = slot_at(id);
@@ -384,6 +411,9 @@ unsafe extern "C" fn h_unbox_local(local_id: u32) -> u32 {
             Value::Number(Number::Unsigned(u)) => return *u,
             Value::Number(Number::Addr(a)) => return *a as u32,
             Value::Bool(b) => return if *b { 1 } else { 0 },
+            Value::Number(Number::Float(_)) => {
+                return escape_failed(Error::jit(FLOATS_UNSUPPORTED));
+            }
             _ => return 0,
         }
     }
@@ -621,6 +651,9 @@ unsafe extern "C" fn h_cdr(slot_id: u32) -> u32 {
 
 unsafe extern "C" fn h_escape(slot_id: u32) -> u32 {
     unsafe {
+        if (*core::ptr::addr_of!(ESCAPE_ERROR)).is_some() {
+            return 0;
+        }
         // **Hottest fast path**: literal-pool sexp slots stash a
         // per-callsite cache of "what JittedClosure did we dispatch
         // to last time?" into the slot's `extra` field. Skip symbol
@@ -653,7 +686,7 @@ unsafe extern "C" fn h_escape(slot_id: u32) -> u32 {
 
         match evaluate(Rc::new(sexp), image_ref()) {
             Ok(result) => intern_value(&result),
-            Err(_) => 0,
+            Err(e) => escape_failed(e),
         }
     }
 }
@@ -691,7 +724,7 @@ unsafe fn run_jc_with_args(
         }
         match result {
             Ok(v) => intern_value(&v),
-            Err(_) => 0,
+            Err(e) => escape_failed(e),
         }
     }
 }
@@ -715,7 +748,7 @@ unsafe fn run_jc_unary(jc: &super::jit::JittedClosure, arg: Value, image: &mut I
         *jc.param_bindings[0].as_ptr() = restored;
         match result {
             Ok(v) => intern_value(&v),
-            Err(_) => 0,
+            Err(e) => escape_failed(e),
         }
     }
 }
@@ -729,6 +762,9 @@ unsafe fn dispatch_cached(sexp: &Value, jc: &Rc<super::jit::JittedClosure>) -> O
     unsafe {
         let image = image_ref();
         let arity = jc.params.len();
+        if sexp.nth_exists(arity + 1) {
+            return None;
+        }
         // Snapshot + write inline for the common arity-1 case (fib), Vec
         // for >1. For arity 1 we avoid the arg_vals Vec entirely.
         if arity == 1 {
@@ -775,8 +811,10 @@ unsafe fn try_autojit_call_returning_jc(
         };
         let image = image_ref();
         let resolved = image.get(&sym)?;
+        // the interpreter binds `&optional` / `&rest` params and
+        // reports a wrong number of arguments
         let closure = match &*resolved {
-            Value::Closure(c) => c.clone(),
+            Value::Closure(c) if !c.variadic() && !sexp.nth_exists(c.params.len() + 1) => c.clone(),
             _ => return None,
         };
         let mut arg_vals: Vec<Value> = Vec::with_capacity(closure.params.len());
@@ -818,8 +856,10 @@ unsafe fn try_autojit_call(sexp: &Value) -> Option<u32> {
     };
     let image = unsafe { image_ref() };
     let resolved = image.get(&sym)?;
+    // the interpreter binds `&optional` / `&rest` params and reports a
+    // wrong number of arguments
     let closure = match &*resolved {
-        Value::Closure(c) => c.clone(),
+        Value::Closure(c) if !c.variadic() && !sexp.nth_exists(c.params.len() + 1) => c.clone(),
         _ => return None,
     };
 
@@ -884,7 +924,7 @@ unsafe fn try_autojit_call(sexp: &Value) -> Option<u32> {
 
     Some(match result {
         Ok(v) => intern_value(&v),
-        Err(_) => 0,
+        Err(e) => escape_failed(e),
     })
 }
 
@@ -1046,7 +1086,7 @@ unsafe fn h_call_fallback(callee_value: &Rc<Value>, args: &[u32]) -> u32 {
         let sexp = Value::cons((**callee_value).clone(), tail);
         match evaluate(Rc::new(sexp), image_ref()) {
             Ok(v) => intern_value(&v),
-            Err(_) => 0,
+            Err(e) => escape_failed(e),
         }
     }
 }
@@ -1625,7 +1665,7 @@ impl JitExecutor {
         crate::println!("--- end dump ---");
     }
 
-    pub(crate) fn run(&self, image: &mut Image) -> Result<Value, &'static str> {
+    pub(crate) fn run(&self, image: &mut Image) -> Result<Value, Error> {
         // We share one persistent set of backing buffers across every
         // `run()` call and let each frame carve out its own "stack
         // frame" in slot space by saving and restoring `SLOT_BUMP`
@@ -1633,6 +1673,17 @@ impl JitExecutor {
         // 64KB allocation, which was making recursion-heavy programs
         // like fib catastrophically slow.
 
+        // Interrupt safepoint. Emitted code has no loops, so it can only
+        // run away by calling itself, which comes back through here:
+        // refusing to start lets each pending call return (with nil) in
+        // turn, and the check on the way out turns that into an error.
+        if interrupt::pending() {
+            return Err(Error::interrupted());
+        }
+        if let Some(e) = take_escape_error() {
+            return Err(e);
+        }
+
         let saved_image = unsafe { IMAGE };
         let saved_base = unsafe { SLOTS_BASE };
         let saved_len = unsafe { SLOTS_LEN };
@@ -1748,6 +1799,10 @@ impl JitExecutor {
             }
         }
 
+        if let Some(e) = take_escape_error() {
+            return Err(e);
+        }
+        interrupt::check().map_err(|_| Error::interrupted())?;
         Ok(result)
     }
 }
@@ -33,6 +33,7 @@ use super::scope::{JitImage, LocalId, Resolution};
 use crate::language::ast::Value;
 use crate::language::constants::SYMB_NAME_LEN;
 use crate::language::environment::Binding;
+use crate::language::number::Number;
 use alloc::rc::Rc;
 use alloc::vec;
 use alloc::vec::Vec;
@@ -43,6 +44,13 @@ use heapless::String;
 /// binding under its source name in the Image's top frame.
 pub(crate) type Name = String<SYMB_NAME_LEN>;
 
+/// Why a body that touches a float isn't compiled. Only floats seen at
+/// compile time (literals, captures, arguments) are refused here; one
+/// that turns up while the code runs (an escape's result, a capture
+/// `set!` since) stops it with this error from `executor`, as emitted
+/// arithmetic reads slot payloads without checking their tags.
+pub(super) const FLOATS_UNSUPPORTED: &str = "floats are left to the interpreter.";
+
 /// SSA virtual register. Each emitted statement assigns its destination
 /// exactly once; later analysis (liveness, regalloc) depends on this.
 #[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
@@ -370,6 +378,9 @@ impl IRSegment {
         scope: &mut JitImage<'_>,
     ) -> Result<VReg, &'static str> {
         match &*sexp {
+            // floats don't fit the JIT's integer-only arithmetic; the
+            // closure stays interpreted
+            Value::Number(Number::Float(_)) => Err(FLOATS_UNSUPPORTED),
             // fundamental values — return as-is
             Value::Nil
             | Value::Bool(_)
@@ -395,6 +406,10 @@ impl IRSegment {
                         self.emit(IRStatement::LoadLocal(r.clone(), id));
                     }
                     Resolution::Capture(b) => {
+                        // same for a capture that holds one now
+                        if matches!(&**b.borrow(), Value::Number(Number::Float(_))) {
+                            return Err(FLOATS_UNSUPPORTED);
+                        }
                         self.emit(IRStatement::LoadCapture(r.clone(), b));
                     }
                     Resolution::Unbound => {
@@ -47,11 +47,13 @@ use super::executor::JitExecutor;
 /// We discriminate the four numeric / scalar shapes the JIT actually
 /// specializes on, plus a catch-all `Heap` bucket for cons/array/
 /// closure/etc. (the JIT treats those as opaque shadow-slot refs).
+/// `Float` is only there so `compile` can refuse it.
 #[derive(Clone, Debug, PartialEq, Eq, Ord, PartialOrd)]
 pub enum InputType {
     Integer,
     Unsigned,
     Addr,
+    Float,
     Bool,
     Nil,
     Heap,
@@ -64,6 +66,7 @@ impl InputType {
             Value::Number(Number::Integer(_)) => InputType::Integer,
             Value::Number(Number::Unsigned(_)) => InputType::Unsigned,
             Value::Number(Number::Addr(_)) => InputType::Addr,
+            Value::Number(Number::Float(_)) => InputType::Float,
             Value::Bool(_) => InputType::Bool,
             Value::Nil => InputType::Nil,
             _ => InputType::Heap,
@@ -164,6 +167,13 @@ impl JittedClosure {
         input_types: Vec<InputType>,
         image: &mut Image,
     ) -> Result<JittedClosure, &'static str> {
+        // binding `&optional` / `&rest` params is left to the interpreter
+        if closure.variadic() {
+            return Err("jit::compile: closures with &optional or &rest params aren't supported.");
+        }
+        if input_types.contains(&InputType::Float) {
+            return Err("jit::compile: float arguments aren't supported.");
+        }
         if closure.params.len() != dummies.len() || closure.params.len() != input_types.len() {
             return Err("jit::compile: arity mismatch between closure params and dummies/types.");
         }
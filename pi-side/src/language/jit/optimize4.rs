@@ -21,6 +21,7 @@ use super::ir3::ImmNumber;
 use super::ir4::{Cond, Instr, Instruction, LIRSegment, Register};
 use super::scope::LocalId;
 use crate::language::ast::Value;
+use crate::language::error::Error;
 use crate::language::number::Number;
 
 const PAYLOAD_OFFSET: i32 = 4;
@@ -783,11 +784,11 @@ fn imm_eq(a: AbsValue, b: ImmNumber) -> Option<bool> {
 fn fold_arith(
     a: AbsValue,
     b: AbsValue,
-    op: fn(Number, Number) -> Result<Number, &'static str>,
+    op: fn(Number, Number) -> Result<Number, Error>,
 ) -> Option<ImmNumber> {
     let na = number_from_abs(&a)?;
     let nb = number_from_abs(&b)?;
-    op(na, nb).ok().map(imm_from_number)
+    op(na, nb).ok().and_then(imm_from_number)
 }
 
 fn fold_bit(
@@ -809,7 +810,7 @@ fn fold_not(a: AbsValue) -> Option<ImmNumber> {
     match number_from_abs(&a)? {
         Number::Integer(i) => Some(ImmNumber::Integer(!i)),
         Number::Unsigned(u) => Some(ImmNumber::Unsigned(!u)),
-        Number::Addr(_) => None,
+        Number::Addr(_) | Number::Float(_) => None,
     }
 }
 
@@ -866,17 +867,19 @@ fn number_from_imm(n: ImmNumber) -> Number {
     }
 }
 
-fn imm_from_number(n: Number) -> ImmNumber {
+/// None for floats, which the JIT leaves to the interpreter.
+fn imm_from_number(n: Number) -> Option<ImmNumber> {
     match n {
-        Number::Integer(i) => ImmNumber::Integer(i),
-        Number::Unsigned(u) => ImmNumber::Unsigned(u),
-        Number::Addr(a) => ImmNumber::Addr(a),
+        Number::Integer(i) => Some(ImmNumber::Integer(i)),
+        Number::Unsigned(u) => Some(ImmNumber::Unsigned(u)),
+        Number::Addr(a) => Some(ImmNumber::Addr(a)),
+        Number::Float(_) => None,
     }
 }
 
 fn imm_from_value(v: &Value) -> Option<ImmNumber> {
     match v {
-        Value::Number(n) => Some(imm_from_number(*n)),
+        Value::Number(n) => imm_from_number(*n),
         Value::Bool(b) => Some(bool_imm(*b)),
         _ => None,
     }
@@ -504,8 +504,36 @@ impl IRSegment {
             // without executing it. Inherently a meta-level operation
             // that runs at interpret time, not run time.
             | Value::Special(Special::Macroexpand)
+
+            // --- non-local exits ---
+            // `catch` / `unwind-protect` have to see the error of a
+            // failing body, and `throw` / `error` raise one: only the
+            // interpreter unwinds. An escape that fails hands its error
+            // to `run`, which returns it once the emitted code is done.
+            | Value::Special(Special::Catch)
+            | Value::Special(Special::Throw)
+            | Value::Special(Special::Error)
+            | Value::Special(Special::UnwindProtect)
                 => Ok(self.escape(&value)),
 
+            // --- floating point ---
+            // Refused rather than escaped: emitted arithmetic only knows
+            // 32-bit integers, and would treat the float an escape
+            // hands back as one. A closure using these stays interpreted.
+            Value::Special(Special::Float)
+            | Value::Special(Special::Sqrt)
+            | Value::Special(Special::Floor)
+            | Value::Special(Special::Ceil)
+            | Value::Special(Special::Round)
+            | Value::Special(Special::Sin)
+            | Value::Special(Special::Cos)
+            | Value::Special(Special::Tan)
+            | Value::Special(Special::Atan2)
+            | Value::Special(Special::Exp)
+            | Value::Special(Special::Log)
+            | Value::Special(Special::Pow)
+                => Err("floats are left to the interpreter."),
+
             // --- catch-all ---
             // Anything we haven't matched: regular function calls
             // (closure invocations), syscalls, computed callees, etc.
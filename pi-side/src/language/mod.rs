@@ -1,10 +1,12 @@
 pub mod ast;
 pub mod constants;
 pub mod environment;
+pub mod error;
 pub mod execute;
 pub mod jit;
 pub mod number;
 pub mod parse;
+pub mod source;
 pub mod special;
 pub mod syscalls;
 
@@ -1,6 +1,6 @@
 //! Numeric type for the LISP interpreter.
-//! Unifies integers, unsigned integers, and addresses.
-//! Unsigned promotes when mixed with Integer.
+//! Unifies integers, unsigned integers, floats, and addresses.
+//! Unsigned promotes when mixed with Integer, and both promote to Float.
 //! Addr is a separate kind — it represents a raw memory address.
 //!
 //! Arithmetic rules:
@@ -8,6 +8,9 @@
 //!   Unsigned op Unsigned → Unsigned
 //!   Unsigned op Int  → Unsigned (promotes)
 //!   Int op Unsigned  → Unsigned (promotes)
+//!   Float op Int/Unsigned/Float → Float (promotes, either side)
+//!   Float with Addr  → error
+//!   Float shifts     → error
 //!   Addr +/- Int     → Addr  (pointer offset)
 //!   Addr +/- Unsigned → Addr (pointer offset)
 //!   Int + Addr       → Addr  (pointer offset, commutative)
@@ -15,15 +18,22 @@
 //!   Addr - Addr      → Int   (distance)
 //!   Addr * or / anything → error
 //!   Div by zero      → error
+//!   NaN or infinite Float result → error
 
 use core::fmt;
 
-/// A number: exact integer, unsigned integer, or raw address.
+use super::error::Error;
+
+/// A number: exact integer, unsigned integer, single-precision float,
+/// or raw address.
 /// Implements PartialEq and PartialOrd so you can use ==, <, >, <=, >=.
 #[derive(Clone, Copy, Debug)]
 pub enum Number {
     Integer(i32),
     Unsigned(u32),
+    /// An IEEE single, done in hardware by the VFP. Single rather than
+    /// double so it fits the same 32-bit word as the others.
+    Float(f32),
     /// A raw memory address. Kept separate from Integer so that address
     /// arithmetic is explicit.
     Addr(usize),
@@ -32,41 +42,106 @@ pub enum Number {
 impl Number {
     /// Coerce to Addr. Identity if already Addr, converts non-negative
     /// integers and unsigned.
-    pub fn as_addr(&self) -> Result<Number, &'static str> {
+    pub fn as_addr(&self) -> Result<Number, Error> {
         match self {
             Number::Addr(_) => Ok(*self),
             Number::Integer(i) => {
                 if *i < 0 {
-                    Err("Cannot convert negative integer to address.")
+                    Err(
+                        Error::type_error("Cannot convert negative integer to address.")
+                            .with_value(i),
+                    )
                 } else {
                     Ok(Number::Addr(*i as usize))
                 }
             }
             Number::Unsigned(u) => Ok(Number::Addr(*u as usize)),
+            Number::Float(f) => {
+                Err(Error::type_error("Cannot convert float to address.").with_value(f))
+            }
         }
     }
 
-    /// Extract the inner i32, or cast from unsigned/addr.
-    pub fn as_i32(&self) -> Result<i32, &'static str> {
+    /// Extract the inner i32, or cast from unsigned/addr. Floats are
+    /// truncated toward zero (saturating, NaN is 0).
+    pub fn as_i32(&self) -> Result<i32, Error> {
         match self {
             Number::Integer(i) => Ok(*i),
             Number::Unsigned(u) => Ok(*u as i32),
+            Number::Float(f) => Ok(*f as i32),
             Number::Addr(a) => Ok(*a as i32),
         }
     }
 
-    /// Extract the inner u32, or cast from integer/addr.
-    pub fn as_u32(&self) -> Result<u32, &'static str> {
+    /// Extract the inner u32, or cast from integer/addr. Floats are
+    /// truncated toward zero (saturating, NaN is 0).
+    pub fn as_u32(&self) -> Result<u32, Error> {
         match self {
             Number::Unsigned(u) => Ok(*u),
             Number::Integer(i) => Ok(*i as u32),
+            Number::Float(f) => Ok(*f as u32),
             Number::Addr(a) => Ok(*a as u32),
         }
     }
 
+    /// A Float, unless `x` is NaN or infinite: those have no literal to
+    /// print as, so like division by zero they are an arithmetic error.
+    pub fn float(x: f32) -> Result<Number, Error> {
+        if x.is_finite() {
+            Ok(Number::Float(x))
+        } else {
+            Err(Error::arithmetic("Float result is not finite.").with_value(&x))
+        }
+    }
+
+    /// Extract the inner f32, or convert from integer/unsigned (rounding
+    /// to the nearest float past 2^24). Addresses aren't converted.
+    pub fn as_f32(&self) -> Result<f32, Error> {
+        match self {
+            Number::Float(f) => Ok(*f),
+            Number::Integer(i) => Ok(*i as f32),
+            Number::Unsigned(u) => Ok(*u as f32),
+            Number::Addr(_) => {
+                Err(Error::type_error("Cannot convert address to float.").with_value(self))
+            }
+        }
+    }
+
+    /// Round a float to an Integer with `round` (`libm::floorf`, ...).
+    /// Integers and unsigned are already whole and come back as is.
+    pub fn to_integer(self, round: fn(f32) -> f32) -> Result<Number, Error> {
+        match self {
+            Number::Float(f) => {
+                let r = round(f);
+                // i32::MIN is exact as a float, i32::MAX rounds up to 2^31
+                if r >= i32::MIN as f32 && r < i32::MAX as f32 {
+                    Ok(Number::Integer(r as i32))
+                } else {
+                    Err(Error::arithmetic("Float out of integer range.").with_value(&self))
+                }
+            }
+            Number::Addr(_) => Err(Error::type_error("Cannot round an address.").with_value(&self)),
+            _ => Ok(self),
+        }
+    }
+
+    /// The exact value, for comparing floats with the other kinds.
+    /// None for addresses.
+    fn exact(&self) -> Option<f64> {
+        match self {
+            Number::Integer(i) => Some(*i as f64),
+            Number::Unsigned(u) => Some(*u as f64),
+            Number::Float(f) => Some(*f as f64),
+            Number::Addr(_) => None,
+        }
+    }
+
     /// Add two numbers. See module docs for lifting rules.
-    pub fn add(self, other: Number) -> Result<Number, &'static str> {
+    pub fn add(self, other: Number) -> Result<Number, Error> {
         match (self, other) {
+            (Number::Float(_), _) | (_, Number::Float(_)) => {
+                Number::float(self.as_f32()? + other.as_f32()?)
+            }
             (Number::Integer(a), Number::Integer(b)) => Ok(Number::Integer(a.wrapping_add(b))),
             (Number::Unsigned(a), Number::Unsigned(b)) => Ok(Number::Unsigned(a.wrapping_add(b))),
             (Number::Unsigned(a), Number::Integer(b)) => {
@@ -81,13 +156,18 @@ impl Number {
             (Number::Unsigned(a), Number::Addr(b)) => {
                 Ok(Number::Addr((a as usize).wrapping_add(b)))
             }
-            (Number::Addr(_), Number::Addr(_)) => Err("Cannot add two addresses."),
+            (Number::Addr(_), Number::Addr(_)) => {
+                Err(Error::type_error("Cannot add two addresses."))
+            }
         }
     }
 
     /// Subtract two numbers. Addr - Addr yields the integer distance.
-    pub fn sub(self, other: Number) -> Result<Number, &'static str> {
+    pub fn sub(self, other: Number) -> Result<Number, Error> {
         match (self, other) {
+            (Number::Float(_), _) | (_, Number::Float(_)) => {
+                Number::float(self.as_f32()? - other.as_f32()?)
+            }
             (Number::Integer(a), Number::Integer(b)) => Ok(Number::Integer(a.wrapping_sub(b))),
             (Number::Unsigned(a), Number::Unsigned(b)) => Ok(Number::Unsigned(a.wrapping_sub(b))),
             (Number::Unsigned(a), Number::Integer(b)) => {
@@ -100,14 +180,17 @@ impl Number {
             (Number::Addr(a), Number::Unsigned(b)) => Ok(Number::Addr(a.wrapping_sub(b as usize))),
             (Number::Addr(a), Number::Addr(b)) => Ok(Number::Integer(a.wrapping_sub(b) as i32)),
             (Number::Integer(_), Number::Addr(_)) | (Number::Unsigned(_), Number::Addr(_)) => {
-                Err("Cannot subtract address from integer.")
+                Err(Error::type_error("Cannot subtract address from integer."))
             }
         }
     }
 
     /// Multiply two numbers. Addresses cannot be multiplied.
-    pub fn mul(self, other: Number) -> Result<Number, &'static str> {
+    pub fn mul(self, other: Number) -> Result<Number, Error> {
         match (self, other) {
+            (Number::Float(_), _) | (_, Number::Float(_)) => {
+                Number::float(self.as_f32()? * other.as_f32()?)
+            }
             (Number::Integer(a), Number::Integer(b)) => Ok(Number::Integer(a.wrapping_mul(b))),
             (Number::Unsigned(a), Number::Unsigned(b)) => Ok(Number::Unsigned(a.wrapping_mul(b))),
             (Number::Unsigned(a), Number::Integer(b)) => {
@@ -116,38 +199,49 @@ impl Number {
             (Number::Integer(a), Number::Unsigned(b)) => {
                 Ok(Number::Unsigned((a as u32).wrapping_mul(b)))
             }
-            (Number::Addr(_), _) | (_, Number::Addr(_)) => Err("Cannot multiply addresses."),
+            (Number::Addr(_), _) | (_, Number::Addr(_)) => {
+                Err(Error::type_error("Cannot multiply addresses."))
+            }
         }
     }
 
     /// Divide two numbers. Addresses cannot be divided. Division by zero errors.
-    pub fn div(self, other: Number) -> Result<Number, &'static str> {
+    pub fn div(self, other: Number) -> Result<Number, Error> {
         match (self, other) {
-            (Number::Addr(_), _) | (_, Number::Addr(_)) => Err("Cannot divide addresses."),
+            (Number::Float(_), _) | (_, Number::Float(_)) => {
+                let (a, b) = (self.as_f32()?, other.as_f32()?);
+                if b == 0.0 {
+                    return Err(Error::arithmetic("Division by zero."));
+                }
+                Number::float(a / b)
+            }
+            (Number::Addr(_), _) | (_, Number::Addr(_)) => {
+                Err(Error::type_error("Cannot divide addresses."))
+            }
             (Number::Integer(a), Number::Integer(b)) => {
                 if b == 0 {
-                    Err("Division by zero.")
+                    Err(Error::arithmetic("Division by zero."))
                 } else {
                     Ok(Number::Integer(a / b))
                 }
             }
             (Number::Unsigned(a), Number::Unsigned(b)) => {
                 if b == 0 {
-                    Err("Division by zero.")
+                    Err(Error::arithmetic("Division by zero."))
                 } else {
                     Ok(Number::Unsigned(a / b))
                 }
             }
             (Number::Unsigned(a), Number::Integer(b)) => {
                 if b == 0 {
-                    Err("Division by zero.")
+                    Err(Error::arithmetic("Division by zero."))
                 } else {
                     Ok(Number::Unsigned(a / b as u32))
                 }
             }
             (Number::Integer(a), Number::Unsigned(b)) => {
                 if b == 0 {
-                    Err("Division by zero.")
+                    Err(Error::arithmetic("Division by zero."))
                 } else {
                     Ok(Number::Unsigned(a as u32 / b))
                 }
@@ -156,33 +250,42 @@ impl Number {
     }
 
     /// Modulo two numbers. Addresses cannot be used. Division by zero errors.
-    pub fn modulo(self, other: Number) -> Result<Number, &'static str> {
+    pub fn modulo(self, other: Number) -> Result<Number, Error> {
         match (self, other) {
-            (Number::Addr(_), _) | (_, Number::Addr(_)) => Err("Cannot modulo addresses."),
+            (Number::Float(_), _) | (_, Number::Float(_)) => {
+                let (a, b) = (self.as_f32()?, other.as_f32()?);
+                if b == 0.0 {
+                    return Err(Error::arithmetic("Division by zero."));
+                }
+                Number::float(libm::fmodf(a, b))
+            }
+            (Number::Addr(_), _) | (_, Number::Addr(_)) => {
+                Err(Error::type_error("Cannot modulo addresses."))
+            }
             (Number::Integer(a), Number::Integer(b)) => {
                 if b == 0 {
-                    Err("Division by zero.")
+                    Err(Error::arithmetic("Division by zero."))
                 } else {
                     Ok(Number::Integer(a % b))
                 }
             }
             (Number::Unsigned(a), Number::Unsigned(b)) => {
                 if b == 0 {
-                    Err("Division by zero.")
+                    Err(Error::arithmetic("Division by zero."))
                 } else {
                     Ok(Number::Unsigned(a % b))
                 }
             }
             (Number::Unsigned(a), Number::Integer(b)) => {
                 if b == 0 {
-                    Err("Division by zero.")
+                    Err(Error::arithmetic("Division by zero."))
                 } else {
                     Ok(Number::Unsigned(a % b as u32))
                 }
             }
             (Number::Integer(a), Number::Unsigned(b)) => {
                 if b == 0 {
-                    Err("Division by zero.")
+                    Err(Error::arithmetic("Division by zero."))
                 } else {
                     Ok(Number::Unsigned(a as u32 % b))
                 }
@@ -191,9 +294,14 @@ impl Number {
     }
 
     /// Left-shift. Addresses cannot be shifted.
-    pub fn lshift(self, other: Number) -> Result<Number, &'static str> {
+    pub fn lshift(self, other: Number) -> Result<Number, Error> {
         match (self, other) {
-            (Number::Addr(_), _) | (_, Number::Addr(_)) => Err("Cannot shift addresses."),
+            (Number::Float(_), _) | (_, Number::Float(_)) => {
+                Err(Error::type_error("Cannot shift floats."))
+            }
+            (Number::Addr(_), _) | (_, Number::Addr(_)) => {
+                Err(Error::type_error("Cannot shift addresses."))
+            }
             (Number::Integer(a), Number::Integer(b)) => {
                 Ok(Number::Integer(a.wrapping_shl(b as u32)))
             }
@@ -206,9 +314,14 @@ impl Number {
     }
 
     /// Right-shift. Arithmetic for Integer, logical for Unsigned. Addresses cannot be shifted.
-    pub fn rshift(self, other: Number) -> Result<Number, &'static str> {
+    pub fn rshift(self, other: Number) -> Result<Number, Error> {
         match (self, other) {
-            (Number::Addr(_), _) | (_, Number::Addr(_)) => Err("Cannot shift addresses."),
+            (Number::Float(_), _) | (_, Number::Float(_)) => {
+                Err(Error::type_error("Cannot shift floats."))
+            }
+            (Number::Addr(_), _) | (_, Number::Addr(_)) => {
+                Err(Error::type_error("Cannot shift addresses."))
+            }
             (Number::Integer(a), Number::Integer(b)) => {
                 Ok(Number::Integer(a.wrapping_shr(b as u32)))
             }
@@ -221,11 +334,29 @@ impl Number {
     }
 }
 
+/// Square root in one VFP instruction; NaN for negative `x`, which
+/// `Number::float` then refuses.
+pub fn sqrt(x: f32) -> f32 {
+    let r: f32;
+    unsafe {
+        core::arch::asm!(
+            "vsqrt.f32 {r}, {x}",
+            r = lateout(sreg) r,
+            x = in(sreg) x,
+            options(pure, nomem, nostack),
+        );
+    }
+    r
+}
+
 impl fmt::Display for Number {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             Number::Integer(i) => write!(f, "{}", i),
             Number::Unsigned(u) => write!(f, "u{}", u),
+            // `Debug` always shows a `.` or an exponent, so it reads back
+            // as a float (`Number::float` keeps NaN and infinities out)
+            Number::Float(x) => write!(f, "{:?}", x),
             Number::Addr(a) => write!(f, "0x{:x}", a),
         }
     }
@@ -246,6 +377,10 @@ impl PartialEq for Number {
             // addr/unsigned: compare as usize
             (Number::Addr(a), Number::Unsigned(b)) => *a == *b as usize,
             (Number::Unsigned(a), Number::Addr(b)) => *a as usize == *b,
+            // float/anything: compare exactly, never equal to an addr
+            (Number::Float(_), _) | (_, Number::Float(_)) => {
+                matches!((self.exact(), other.exact()), (Some(a), Some(b)) if a == b)
+            }
         }
     }
 }
@@ -259,6 +394,10 @@ impl PartialOrd for Number {
             // unsigned/int: compare as i64 to handle sign correctly
             (Number::Unsigned(a), Number::Integer(b)) => (*a as i64).partial_cmp(&(*b as i64)),
             (Number::Integer(a), Number::Unsigned(b)) => (*a as i64).partial_cmp(&(*b as i64)),
+            // float/int/unsigned: compare exactly, NaN incomparable
+            (Number::Float(_), _) | (_, Number::Float(_)) => {
+                self.exact()?.partial_cmp(&other.exact()?)
+            }
             // addr vs int: incomparable
             _ => None,
         }
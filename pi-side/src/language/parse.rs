@@ -14,31 +14,62 @@
 //!   42  -7               — integer (decimal)
 //!   0xFF                 — integer (hex)
 //!   0b1010               — integer (binary)
+//!   1.5  -0.25  2e-3     — float (decimal, with a `.` or exponent)
+//!   &optional &rest      — parameter list keywords (symbols)
 //!   "hello"              — string (supports \n \t \\ \")
 //!   + - * / % > < ~ | & << >>  — operator specials
 //!   nil true false       — literal values
 //!   defun lambda if ...  — named special forms
 //!   anything-else        — symbol
 //!   ; comment            — line comment (to end of line)
+//!
+//! Every list read is noted in the `SourceMap` with its `Span`, so errors
+//! can point back at it; parse errors give the line and column they
+//! happened at.
 
+use alloc::format;
 use alloc::rc::Rc;
 use alloc::string::String as AllocString;
 use alloc::vec::Vec;
+use core::cell::RefCell;
+use core::fmt;
 
 use nom::{
     IResult, Parser,
     branch::alt,
     bytes::complete::take_while1,
-    character::complete::{char, digit1, hex_digit1, multispace0, satisfy},
-    combinator::opt,
+    character::complete::{char, digit1, hex_digit1, multispace0, one_of, satisfy},
+    combinator::{cut, opt},
 };
 
 use super::ast::{Special, Syscall, Value};
 use super::constants::SYMB_NAME_LEN;
 use super::number::Number;
+use super::source::{Source, SourceMap, Span};
 
 type Symbol = heapless::String<SYMB_NAME_LEN>;
 
+/// The lists read so far, each with the first car and the offsets it
+/// started and ended at. Every input the parsers see is a suffix of the
+/// text, `len` bytes long, that parsing started on.
+struct Lists {
+    len: usize,
+    found: RefCell<Vec<(Rc<Value>, usize, usize)>>,
+}
+
+impl Lists {
+    /// Note `list`, read from `input` up to `rest`.
+    fn note(&self, list: &Value, input: &str, rest: &str) {
+        if let Value::Cons(car, _) = list {
+            self.found.borrow_mut().push((
+                Rc::clone(car),
+                self.len - input.len(),
+                self.len - rest.len(),
+            ));
+        }
+    }
+}
+
 // ---------------------------------------------------------------------------
 // whitespace & comments
 // ---------------------------------------------------------------------------
@@ -62,14 +93,13 @@ fn ws(mut input: &str) -> IResult<&str, ()> {
 
 /// Parse a double-quoted string with escape sequences (\n \t \\ \").
 fn parse_string(input: &str) -> IResult<&str, Value> {
-    let (input, _) = char('"')(input)?;
+    let (mut rest, _) = char('"')(input)?;
     let mut s = AllocString::new();
-    let mut rest = input;
     let mut escape = false;
     loop {
         if rest.is_empty() {
             return Err(nom::Err::Failure(nom::error::Error::new(
-                rest,
+                input,
                 nom::error::ErrorKind::Char,
             )));
         }
@@ -218,9 +248,39 @@ fn parse_integer(input: &str) -> IResult<&str, Value> {
     Ok((rest, Value::Number(Number::Integer(i))))
 }
 
-/// Parse a number: try hex/binary first (0x/0b prefixes), then decimal integer.
+/// Parse a float literal: [-]digits, then `.digits`, an exponent
+/// `e[+-]digits`, or both. Without either it's an integer.
+fn parse_float(input: &str) -> IResult<&str, Value> {
+    let (i, _) = opt(char('-')).parse(input)?;
+    let (i, _) = digit1(i)?;
+    let (i, fraction) = opt((char('.'), digit1)).parse(i)?;
+    let (rest, exponent) = opt((one_of("eE"), opt(one_of("+-")), digit1)).parse(i)?;
+    if fraction.is_none() && exponent.is_none() {
+        return Err(nom::Err::Error(nom::error::Error::new(
+            input,
+            nom::error::ErrorKind::Float,
+        )));
+    }
+    let slice = &input[..input.len() - rest.len()];
+    match slice.parse::<f32>() {
+        Ok(f) if f.is_finite() => Ok((rest, Value::Number(Number::Float(f)))),
+        _ => Err(nom::Err::Failure(nom::error::Error::new(
+            input,
+            nom::error::ErrorKind::Float,
+        ))),
+    }
+}
+
+/// Parse a number: try floats first (they start like decimal integers),
+/// then hex/binary (0x/0b prefixes), then decimal integer.
 fn parse_number(input: &str) -> IResult<&str, Value> {
-    alt((parse_hex_integer, parse_bin_integer, parse_integer)).parse(input)
+    alt((
+        parse_float,
+        parse_hex_integer,
+        parse_bin_integer,
+        parse_integer,
+    ))
+    .parse(input)
 }
 
 // ---------------------------------------------------------------------------
@@ -259,24 +319,24 @@ fn wrap_special(head: Special, inner: Value) -> Value {
 }
 
 /// Parse 'expr — desugars to (quote expr). Returns expr literally, unevaluated.
-fn parse_quote(input: &str) -> IResult<&str, Value> {
+fn parse_quote<'a>(input: &'a str, lists: &Lists) -> IResult<&'a str, Value> {
     let (rest, _) = char('\'')(input)?;
-    let (rest, inner) = parse_value(rest)?;
+    let (rest, inner) = cut(|i| parse_value(i, lists)).parse(rest)?;
     Ok((rest, wrap_special(Special::Quote, inner)))
 }
 
 /// Parse `expr — desugars to (quasiquote expr).
-fn parse_quasiquote(input: &str) -> IResult<&str, Value> {
+fn parse_quasiquote<'a>(input: &'a str, lists: &Lists) -> IResult<&'a str, Value> {
     let (rest, _) = char('`')(input)?;
-    let (rest, inner) = parse_value(rest)?;
+    let (rest, inner) = cut(|i| parse_value(i, lists)).parse(rest)?;
     Ok((rest, wrap_special(Special::Quasiquote, inner)))
 }
 
 /// Parse ,expr → (unquote expr); ,@expr → (unquote-splicing expr).
-fn parse_unquote(input: &str) -> IResult<&str, Value> {
+fn parse_unquote<'a>(input: &'a str, lists: &Lists) -> IResult<&'a str, Value> {
     let (rest, _) = char(',')(input)?;
     let (rest, splice) = opt(char('@')).parse(rest)?;
-    let (rest, inner) = parse_value(rest)?;
+    let (rest, inner) = cut(|i| parse_value(i, lists)).parse(rest)?;
     let head = if splice.is_some() {
         Special::UnquoteSplicing
     } else {
@@ -302,7 +362,7 @@ fn is_cxr_pattern(s: &str) -> bool {
 }
 
 /// Parse a parenthesised list: ( value* )
-fn parse_list(input: &str) -> IResult<&str, Value> {
+fn parse_list<'a>(input: &'a str, lists: &Lists) -> IResult<&'a str, Value> {
     let (mut rest, _) = char('(')(input)?;
     let mut items: Vec<Value> = Vec::new();
     loop {
@@ -318,7 +378,7 @@ fn parse_list(input: &str) -> IResult<&str, Value> {
                 nom::error::ErrorKind::Char,
             )));
         }
-        let (r, val) = parse_value(rest)?;
+        let (r, val) = cut(|i| parse_value(i, lists)).parse(rest)?;
         items.push(val);
         rest = r;
     }
@@ -343,6 +403,7 @@ fn parse_list(input: &str) -> IResult<&str, Value> {
                 };
                 result = Value::cons(Value::Special(special), Value::cons(result, Value::Nil));
             }
+            lists.note(&result, input, rest);
             return Ok((rest, result));
         }
     }
@@ -352,6 +413,7 @@ fn parse_list(input: &str) -> IResult<&str, Value> {
     for item in items.into_iter().rev() {
         result = Value::Cons(Rc::new(item), Rc::new(result));
     }
+    lists.note(&result, input, rest);
     Ok((rest, result))
 }
 
@@ -431,14 +493,17 @@ fn match_literal(word: &str) -> Option<Value> {
 /// Parse a bare word: named specials (case-insensitive), literals, or user
 /// symbols (case-sensitive, preserved exactly as written).
 fn parse_identifier(input: &str) -> IResult<&str, Value> {
+    // `&optional` / `&rest`: a `&` then a word (a lone `&` is BinAnd)
+    let body = input.strip_prefix('&').unwrap_or(input);
     // first char must be an ident-start (not `/` or `-`)
-    if !input.starts_with(|c: char| is_ident_start(c)) {
+    if !body.starts_with(|c: char| is_ident_start(c)) {
         return Err(nom::Err::Error(nom::error::Error::new(
             input,
             nom::error::ErrorKind::Alpha,
         )));
     }
-    let (rest, word) = take_while1(|c: char| is_ident_char(c))(input)?;
+    let (rest, _) = take_while1(|c: char| is_ident_char(c))(body)?;
+    let word = &input[..input.len() - rest.len()];
 
     // literals (case-insensitive)
     if let Some(val) = match_literal(word) {
@@ -465,16 +530,16 @@ fn parse_identifier(input: &str) -> IResult<&str, Value> {
 // ---------------------------------------------------------------------------
 
 /// Parse a single value, consuming leading whitespace/comments.
-fn parse_value(input: &str) -> IResult<&str, Value> {
+fn parse_value<'a>(input: &'a str, lists: &Lists) -> IResult<&'a str, Value> {
     let (input, _) = ws(input)?;
     alt((
         parse_string,
         parse_address,
         parse_unsigned,
-        parse_quote,
-        parse_quasiquote,
-        parse_unquote,
-        parse_list,
+        |i| parse_quote(i, lists),
+        |i| parse_quasiquote(i, lists),
+        |i| parse_unquote(i, lists),
+        |i| parse_list(i, lists),
         parse_syscall,
         parse_number,
         parse_operator,
@@ -484,17 +549,65 @@ fn parse_value(input: &str) -> IResult<&str, Value> {
     .parse(input)
 }
 
-/// Public entry point: parse one value from the input.
-/// Returns the parsed Value, or a descriptive error string on failure.
-pub fn parse(input: &str) -> Result<Value, AllocString> {
-    let (_, val) = parse_value(input).map_err(|e| AllocString::from(alloc::format!("{}", e)))?;
-    Ok(val)
+/// A form that couldn't be read: what was wrong, and where.
+#[derive(Debug)]
+pub struct ParseError {
+    pub source: Rc<Source>,
+    pub offset: usize,
+    pub message: AllocString,
 }
 
-/// Parse one value and return both the value and remaining input.
-/// Useful for REPL-style incremental parsing.
-#[allow(unused)]
-pub fn parse_with_rest(input: &str) -> Result<(Value, &str), AllocString> {
-    let (rest, val) = parse_value(input).map_err(|e| AllocString::from(alloc::format!("{}", e)))?;
-    Ok((val, rest))
+/// `name:line:column: message`.
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.source.locate(self.offset), self.message)
+    }
+}
+
+/// Read one value from `source`, starting at byte `start`. Returns the
+/// value and the offset just past it, and notes the span of every list
+/// in it in `spans`.
+pub fn parse(
+    source: &Rc<Source>,
+    start: usize,
+    spans: &mut SourceMap,
+) -> Result<(Value, usize), ParseError> {
+    let text = source.text.as_str();
+    let lists = Lists {
+        len: text.len(),
+        found: RefCell::new(Vec::new()),
+    };
+    let (offset, message) = match parse_value(&text[start..], &lists) {
+        Ok((rest, val)) => {
+            spans.prune();
+            for (car, start, end) in lists.found.into_inner() {
+                let source = Rc::clone(source);
+                spans.insert(&car, Span { source, start, end });
+            }
+            return Ok((val, text.len() - rest.len()));
+        }
+        Err(nom::Err::Error(e) | nom::Err::Failure(e)) => {
+            (text.len() - e.input.len(), describe(e.code, e.input))
+        }
+        Err(nom::Err::Incomplete(_)) => (text.len(), "unexpected end of input.".into()),
+    };
+    Err(ParseError {
+        source: Rc::clone(source),
+        offset,
+        message,
+    })
+}
+
+/// What a parser failing with `code` at `input` ran into.
+fn describe(code: nom::error::ErrorKind, input: &str) -> AllocString {
+    use nom::error::ErrorKind;
+    match (code, input.chars().next()) {
+        (_, None) => "unexpected end of input.".into(),
+        (ErrorKind::Char, Some('(')) => "unclosed list.".into(),
+        (ErrorKind::Char, Some('"')) => "unterminated string.".into(),
+        (ErrorKind::TooLarge, _) => "symbol name too long.".into(),
+        (ErrorKind::HexDigit | ErrorKind::Digit, Some('#' | 'u')) => "number out of range.".into(),
+        (ErrorKind::Float, _) => "number out of range.".into(),
+        (_, Some(c)) => format!("unexpected `{}`.", c),
+    }
 }
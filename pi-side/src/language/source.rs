@@ -0,0 +1,122 @@
+//! Where forms came from.
+//!
+//! Parsing a `Source` notes the byte range of every list it reads as a
+//! `Span`, and the `Image` keeps them in a `SourceMap` so errors can say
+//! where the form they happened in was written. A list is known by its
+//! first cons cell, which the evaluator passes around as is, so the map
+//! is keyed by the address of that cell's car. It only holds a `Weak`
+//! to it: the form can be dropped as usual, and its address can't be
+//! reused while the entry is there to be mistaken for it.
+
+use alloc::collections::BTreeMap;
+use alloc::rc::{Rc, Weak};
+use alloc::string::String;
+use core::fmt;
+
+use super::ast::Value;
+
+/// Text that forms are parsed from, and what to call it in errors
+/// (nothing for what was typed at the prompt).
+#[derive(Debug)]
+pub struct Source {
+    pub name: Option<String>,
+    pub text: String,
+}
+
+impl Source {
+    pub fn new(name: Option<String>, text: impl Into<String>) -> Rc<Self> {
+        Rc::new(Self {
+            name,
+            text: text.into(),
+        })
+    }
+
+    /// 1-based line and column of the byte at `offset`.
+    pub fn position(&self, offset: usize) -> (usize, usize) {
+        let before = &self.text[..offset];
+        let line = before.matches('\n').count() + 1;
+        let column = before.rsplit('\n').next().map_or(0, |l| l.chars().count()) + 1;
+        (line, column)
+    }
+
+    /// `name:line:column` of the byte at `offset`.
+    pub fn locate(&self, offset: usize) -> Location<'_> {
+        Location {
+            source: self,
+            offset,
+        }
+    }
+}
+
+/// A position in a `Source`, shown as `name:line:column`.
+pub struct Location<'a> {
+    source: &'a Source,
+    offset: usize,
+}
+
+impl fmt::Display for Location<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(name) = &self.source.name {
+            write!(f, "{}:", name)?;
+        }
+        let (line, column) = self.source.position(self.offset);
+        write!(f, "{}:{}", line, column)
+    }
+}
+
+/// The bytes `start..end` of a source.
+#[derive(Clone, Debug)]
+pub struct Span {
+    pub source: Rc<Source>,
+    pub start: usize,
+    pub end: usize,
+}
+
+/// `name:line:column-column`, or `name:line:column-line:column` for a
+/// span over several lines; both ends inclusive.
+impl fmt::Display for Span {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.source.locate(self.start))?;
+        let (line, _) = self.source.position(self.start);
+        let last = self.source.text[..self.end]
+            .char_indices()
+            .next_back()
+            .map_or(self.start, |(i, _)| i.max(self.start));
+        let (end_line, end_column) = self.source.position(last);
+        if end_line == line {
+            write!(f, "-{}", end_column)
+        } else {
+            write!(f, "-{}:{}", end_line, end_column)
+        }
+    }
+}
+
+/// The spans of the parsed lists that are still around.
+#[derive(Clone, Debug, Default)]
+pub struct SourceMap {
+    spans: BTreeMap<usize, (Weak<Value>, Span)>,
+}
+
+impl SourceMap {
+    /// Note that the list whose first car is `car` was read from `span`.
+    pub fn insert(&mut self, car: &Rc<Value>, span: Span) {
+        self.spans
+            .insert(Rc::as_ptr(car) as usize, (Rc::downgrade(car), span));
+    }
+
+    /// Forget the lists that have been dropped.
+    pub fn prune(&mut self) {
+        self.spans.retain(|_, (car, _)| car.strong_count() > 0);
+    }
+
+    /// Where `form` was read from, if it is a list the parser made.
+    pub fn get(&self, form: &Value) -> Option<&Span> {
+        match form {
+            Value::Cons(car, _) => self
+                .spans
+                .get(&(Rc::as_ptr(car) as usize))
+                .map(|(_, span)| span),
+            _ => None,
+        }
+    }
+}
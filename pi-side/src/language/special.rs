@@ -8,8 +8,10 @@ use core::cell::Cell;
 use core::fmt::Write as _;
 use super::ast::{Closure, Macro, Symbol, Value};
 use super::environment::Image;
-use super::execute::{eval, evaluate};
+use super::error::{Error, ErrorKind};
+use super::execute::{call_closure, eval, evaluate, list_items};
 use super::number::Number;
+use crate::comm::interrupt;
 
 #[derive(Clone, PartialEq, Eq, Debug)]
 pub enum Special {
@@ -41,6 +43,18 @@ pub enum Special {
     Addr,
     Signed,
     Unsigned,
+    Float,
+    Sqrt,
+    Floor,
+    Ceil,
+    Round,
+    Sin,
+    Cos,
+    Tan,
+    Atan2,
+    Exp,
+    Log,
+    Pow,
     Let,
     List,
     Macroexpand,
@@ -61,6 +75,16 @@ pub enum Special {
     Unquote,
     UnquoteSplicing,
     Hits,
+    /// `(catch tag body...)` — evaluate `body`, returning early with
+    /// the value of a `(throw tag value)` inside it.
+    Catch,
+    /// `(throw tag value)` — unwind to the `catch` for `tag`.
+    Throw,
+    /// `(error message [payload])` — raise an error from lisp code.
+    Error,
+    /// `(unwind-protect body cleanup...)` — evaluate `body`, then
+    /// `cleanup` whether or not `body` failed.
+    UnwindProtect,
     /// `(ir <sexp>)` — run `<sexp>` through the JIT IR generator and
     /// print the resulting IRSegment. Returns nil.
     Ir,
@@ -97,181 +121,92 @@ pub enum Special {
 }
 
 impl Special {
+    /// Every name a special form answers to, aliases included.
+    pub const NAMES: &[(&str, Special)] = &[
+        ("add", Self::Add),
+        ("sub", Self::Sub),
+        ("mul", Self::Mul),
+        ("div", Self::Div),
+        ("gt", Self::Gt),
+        ("lt", Self::Lt),
+        ("gte", Self::Gte),
+        ("lte", Self::Lte),
+        ("eq", Self::Eq),
+        ("not", Self::Not),
+        ("and", Self::And),
+        ("or", Self::Or),
+        ("xor", Self::Xor),
+        ("binnot", Self::BinNot),
+        ("binor", Self::BinOr),
+        ("binand", Self::BinAnd),
+        ("defun", Self::Defun),
+        ("defmacro", Self::Defmacro),
+        ("lambda", Self::Lambda),
+        ("fn", Self::Lambda),
+        ("if", Self::If),
+        ("set", Self::Set),
+        ("begin", Self::Begin),
+        ("car", Self::Car),
+        ("cdr", Self::Cdr),
+        ("null?", Self::Nullp),
+        ("nullp", Self::Nullp),
+        ("addr", Self::Addr),
+        ("signed", Self::Signed),
+        ("unsigned", Self::Unsigned),
+        ("float", Self::Float),
+        ("sqrt", Self::Sqrt),
+        ("floor", Self::Floor),
+        ("ceil", Self::Ceil),
+        ("round", Self::Round),
+        ("sin", Self::Sin),
+        ("cos", Self::Cos),
+        ("tan", Self::Tan),
+        ("atan2", Self::Atan2),
+        ("exp", Self::Exp),
+        ("log", Self::Log),
+        ("pow", Self::Pow),
+        ("let", Self::Let),
+        ("list", Self::List),
+        ("macroexpand", Self::Macroexpand),
+        ("cons", Self::Cons),
+        ("lshift", Self::Lshift),
+        ("rshift", Self::Rshift),
+        ("mod", Self::Mod),
+        ("array", Self::Array),
+        ("full", Self::Full),
+        ("unpack", Self::Unpack),
+        ("getidx", Self::GetIdx),
+        ("putidx", Self::PutIdx),
+        ("readidx", Self::ReadIdx),
+        ("fillidx", Self::FillIdx),
+        ("fullidx", Self::FullIdx),
+        ("quote", Self::Quote),
+        ("quasiquote", Self::Quasiquote),
+        ("unquote", Self::Unquote),
+        ("unquote-splicing", Self::UnquoteSplicing),
+        ("hits", Self::Hits),
+        ("catch", Self::Catch),
+        ("throw", Self::Throw),
+        ("error", Self::Error),
+        ("unwind-protect", Self::UnwindProtect),
+        ("ir", Self::Ir),
+        ("oir", Self::Oir),
+        ("ir2", Self::Ir2),
+        ("oir2", Self::Oir2),
+        ("ir3", Self::Ir3),
+        ("ir4", Self::Ir4),
+        ("oir4", Self::Oir4),
+        ("jitexec", Self::JitExec),
+        ("jit", Self::Jit),
+    ];
+
     /// Look up a special form by name (case-insensitive).
     /// Returns None for user-defined symbols.
     pub fn from_name(name: &str) -> Option<Self> {
-        if name.eq_ignore_ascii_case("add") {
-            return Some(Self::Add);
-        }
-        if name.eq_ignore_ascii_case("sub") {
-            return Some(Self::Sub);
-        }
-        if name.eq_ignore_ascii_case("mul") {
-            return Some(Self::Mul);
-        }
-        if name.eq_ignore_ascii_case("div") {
-            return Some(Self::Div);
-        }
-        if name.eq_ignore_ascii_case("gt") {
-            return Some(Self::Gt);
-        }
-        if name.eq_ignore_ascii_case("lt") {
-            return Some(Self::Lt);
-        }
-        if name.eq_ignore_ascii_case("gte") {
-            return Some(Self::Gte);
-        }
-        if name.eq_ignore_ascii_case("lte") {
-            return Some(Self::Lte);
-        }
-        if name.eq_ignore_ascii_case("eq") {
-            return Some(Self::Eq);
-        }
-        if name.eq_ignore_ascii_case("not") {
-            return Some(Self::Not);
-        }
-        if name.eq_ignore_ascii_case("and") {
-            return Some(Self::And);
-        }
-        if name.eq_ignore_ascii_case("or") {
-            return Some(Self::Or);
-        }
-        if name.eq_ignore_ascii_case("xor") {
-            return Some(Self::Xor);
-        }
-        if name.eq_ignore_ascii_case("binnot") {
-            return Some(Self::BinNot);
-        }
-        if name.eq_ignore_ascii_case("binor") {
-            return Some(Self::BinOr);
-        }
-        if name.eq_ignore_ascii_case("binand") {
-            return Some(Self::BinAnd);
-        }
-        if name.eq_ignore_ascii_case("defun") {
-            return Some(Self::Defun);
-        }
-        if name.eq_ignore_ascii_case("defmacro") {
-            return Some(Self::Defmacro);
-        }
-        if name.eq_ignore_ascii_case("lambda") || name.eq_ignore_ascii_case("fn") {
-            return Some(Self::Lambda);
-        }
-        if name.eq_ignore_ascii_case("if") {
-            return Some(Self::If);
-        }
-        if name.eq_ignore_ascii_case("set") {
-            return Some(Self::Set);
-        }
-        if name.eq_ignore_ascii_case("begin") {
-            return Some(Self::Begin);
-        }
-        if name.eq_ignore_ascii_case("car") {
-            return Some(Self::Car);
-        }
-        if name.eq_ignore_ascii_case("cdr") {
-            return Some(Self::Cdr);
-        }
-        if name.eq_ignore_ascii_case("null?") || name.eq_ignore_ascii_case("nullp") {
-            return Some(Self::Nullp);
-        }
-        if name.eq_ignore_ascii_case("addr") {
-            return Some(Self::Addr);
-        }
-        if name.eq_ignore_ascii_case("signed") {
-            return Some(Self::Signed);
-        }
-        if name.eq_ignore_ascii_case("unsigned") {
-            return Some(Self::Unsigned);
-        }
-        if name.eq_ignore_ascii_case("let") {
-            return Some(Self::Let);
-        }
-        if name.eq_ignore_ascii_case("list") {
-            return Some(Self::List);
-        }
-        if name.eq_ignore_ascii_case("macroexpand") {
-            return Some(Self::Macroexpand);
-        }
-        if name.eq_ignore_ascii_case("cons") {
-            return Some(Self::Cons);
-        }
-        if name.eq_ignore_ascii_case("lshift") {
-            return Some(Self::Lshift);
-        }
-        if name.eq_ignore_ascii_case("rshift") {
-            return Some(Self::Rshift);
-        }
-        if name.eq_ignore_ascii_case("mod") {
-            return Some(Self::Mod);
-        }
-        if name.eq_ignore_ascii_case("array") {
-            return Some(Self::Array);
-        }
-        if name.eq_ignore_ascii_case("full") {
-            return Some(Self::Full);
-        }
-        if name.eq_ignore_ascii_case("unpack") {
-            return Some(Self::Unpack);
-        }
-        if name.eq_ignore_ascii_case("getidx") {
-            return Some(Self::GetIdx);
-        }
-        if name.eq_ignore_ascii_case("putidx") {
-            return Some(Self::PutIdx);
-        }
-        if name.eq_ignore_ascii_case("readidx") {
-            return Some(Self::ReadIdx);
-        }
-        if name.eq_ignore_ascii_case("fillidx") {
-            return Some(Self::FillIdx);
-        }
-        if name.eq_ignore_ascii_case("fullidx") {
-            return Some(Self::FullIdx);
-        }
-        if name.eq_ignore_ascii_case("quote") {
-            return Some(Self::Quote);
-        }
-        if name.eq_ignore_ascii_case("quasiquote") {
-            return Some(Self::Quasiquote);
-        }
-        if name.eq_ignore_ascii_case("unquote") {
-            return Some(Self::Unquote);
-        }
-        if name.eq_ignore_ascii_case("unquote-splicing") {
-            return Some(Self::UnquoteSplicing);
-        }
-        if name.eq_ignore_ascii_case("hits") {
-            return Some(Self::Hits);
-        }
-        if name.eq_ignore_ascii_case("ir") {
-            return Some(Self::Ir);
-        }
-        if name.eq_ignore_ascii_case("oir") {
-            return Some(Self::Oir);
-        }
-        if name.eq_ignore_ascii_case("ir2") {
-            return Some(Self::Ir2);
-        }
-        if name.eq_ignore_ascii_case("oir2") {
-            return Some(Self::Oir2);
-        }
-        if name.eq_ignore_ascii_case("ir3") {
-            return Some(Self::Ir3);
-        }
-        if name.eq_ignore_ascii_case("ir4") {
-            return Some(Self::Ir4);
-        }
-        if name.eq_ignore_ascii_case("oir4") {
-            return Some(Self::Oir4);
-        }
-        if name.eq_ignore_ascii_case("jitexec") {
-            return Some(Self::JitExec);
-        }
-        if name.eq_ignore_ascii_case("jit") {
-            return Some(Self::Jit);
-        }
-        None
+        Self::NAMES
+            .iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.clone())
     }
 }
 
@@ -290,18 +225,15 @@ fn dump_to_string<T: core::fmt::Debug>(seg: &T) -> AllocString {
 }
 
 /// Extract and evaluate two numeric arguments from sexp (special left right).
-fn extract_numeric_binop(
-    sexp: Rc<Value>,
-    image: &mut Image,
-) -> Result<(Number, Number), &'static str> {
+fn extract_numeric_binop(sexp: Rc<Value>, image: &mut Image) -> Result<(Number, Number), Error> {
     let left = sexp.nth(1);
     let right = sexp.nth(2);
 
     if !sexp.nth_exists(2) {
-        return Err("Expected 2 arguments, got fewer.");
+        return Err(Error::arity("Expected 2 arguments, got fewer."));
     }
     if sexp.nth_exists(3) {
-        return Err("Expected 2 arguments, got more.");
+        return Err(Error::arity("Expected 2 arguments, got more."));
     }
 
     let left_val = evaluate(left, image)?;
@@ -309,63 +241,65 @@ fn extract_numeric_binop(
 
     let l = match &left_val {
         Value::Number(n) => *n,
-        _ => return Err("Left operand is not a number."),
+        v => return Err(Error::type_error("Left operand is not a number.").with(v)),
     };
     let r = match &right_val {
         Value::Number(n) => *n,
-        _ => return Err("Right operand is not a number."),
+        v => return Err(Error::type_error("Right operand is not a number.").with(v)),
     };
 
     Ok((l, r))
 }
 
 /// Extract and evaluate a single argument of any type from sexp (special arg).
-fn extract_unary(sexp: Rc<Value>, image: &mut Image) -> Result<Value, &'static str> {
+fn extract_unary(sexp: Rc<Value>, image: &mut Image) -> Result<Value, Error> {
     let arg = sexp.nth(1);
     if !sexp.nth_exists(1) {
-        return Err("Expected 1 argument, got none.");
+        return Err(Error::arity("Expected 1 argument, got none."));
     }
     if sexp.nth_exists(2) {
-        return Err("Expected 1 argument, got more.");
+        return Err(Error::arity("Expected 1 argument, got more."));
     }
     evaluate(arg, image)
 }
 
 /// Extract and evaluate a single numeric argument from sexp (special arg).
 /// Delegates to extract_unary, then checks that the result is a number.
-fn extract_numeric_unary(sexp: Rc<Value>, image: &mut Image) -> Result<Number, &'static str> {
+fn extract_numeric_unary(sexp: Rc<Value>, image: &mut Image) -> Result<Number, Error> {
     match &extract_unary(sexp, image)? {
         Value::Number(n) => Ok(*n),
-        _ => Err("Argument is not a number."),
+        v => Err(Error::type_error("Argument is not a number.").with(v)),
     }
 }
 
 /// Extract a usize from an already-evaluated Value.
-fn extract_usize(val: &Value, ctx: &'static str) -> Result<usize, &'static str> {
+fn extract_usize(val: &Value, ctx: &'static str) -> Result<usize, Error> {
     if let Value::Number(n) = val {
-        Ok(n.as_i32().map_err(|_| ctx)? as usize)
+        Ok(n.as_i32().map_err(|_| Error::type_error(ctx).with(val))? as usize)
     } else {
-        Err(ctx)
+        Err(Error::type_error(ctx).with(val))
     }
 }
 
 /// Extract a u32 from an already-evaluated Value.
-fn extract_u32(val: &Value, ctx: &'static str) -> Result<u32, &'static str> {
+fn extract_u32(val: &Value, ctx: &'static str) -> Result<u32, Error> {
     if let Value::Number(n) = val {
-        n.as_u32().map_err(|_| ctx)
+        n.as_u32().map_err(|_| Error::type_error(ctx).with(val))
     } else {
-        Err(ctx)
+        Err(Error::type_error(ctx).with(val))
     }
 }
 
 /// Extract a raw *mut u32 base pointer from a Number (must be Addr).
 /// The returned pointer is to the base; callers offset by index * sizeof(u32).
-fn extract_addr(n: &Number, ctx: &'static str) -> Result<*mut u32, &'static str> {
-    let a = n.as_addr().map_err(|_| ctx)?;
+fn extract_addr(n: &Number, ctx: &'static str) -> Result<*mut u32, Error> {
+    let a = n
+        .as_addr()
+        .map_err(|_| Error::type_error(ctx).with_value(n))?;
     if let Number::Addr(a) = a {
         Ok(a as *mut u32)
     } else {
-        Err(ctx)
+        Err(Error::type_error(ctx).with_value(n))
     }
 }
 
@@ -392,23 +326,23 @@ fn append_lists(a: Value, b: Value) -> Value {
 
 /// Expand a quasiquoted form: walks the structure, evaluating (unquote x)
 /// and splicing (unquote-splicing x) within enclosing lists.
-fn quasiquote_expand(v: &Value, image: &mut Image) -> Result<Value, &'static str> {
+fn quasiquote_expand(v: &Value, image: &mut Image) -> Result<Value, Error> {
     if let Value::Cons(car, cdr) = v {
         if matches!(&**car, Value::Special(Special::Unquote)) {
             return match &**cdr {
                 Value::Cons(arg, _) => evaluate(Rc::clone(arg), image),
-                _ => Err("unquote: expected 1 argument."),
+                _ => Err(Error::arity("unquote: expected 1 argument.")),
             };
         }
         if matches!(&**car, Value::Special(Special::UnquoteSplicing)) {
-            return Err("unquote-splicing: not inside a list.");
+            return Err(Error::syntax("unquote-splicing: not inside a list."));
         }
         return quasiquote_list(v, image);
     }
     Ok(v.clone())
 }
 
-fn quasiquote_list(v: &Value, image: &mut Image) -> Result<Value, &'static str> {
+fn quasiquote_list(v: &Value, image: &mut Image) -> Result<Value, Error> {
     match v {
         Value::Nil => Ok(Value::Nil),
         Value::Cons(car, cdr) => {
@@ -417,7 +351,7 @@ fn quasiquote_list(v: &Value, image: &mut Image) -> Result<Value, &'static str>
                 if matches!(&**head, Value::Special(Special::UnquoteSplicing)) {
                     let arg = match &**tail {
                         Value::Cons(a, _) => Rc::clone(a),
-                        _ => return Err("unquote-splicing: expected 1 argument."),
+                        _ => return Err(Error::arity("unquote-splicing: expected 1 argument.")),
                     };
                     let spliced = evaluate(arg, image)?;
                     let rest = quasiquote_list(cdr, image)?;
@@ -443,24 +377,113 @@ pub fn is_falsy(v: &Value) -> bool {
     )
 }
 
-/// Walk a cons list and collect each element as an Rc<Symbol>.
-/// Nil (empty list) returns an empty Vec.
-fn collect_symbol_list(list: &Value) -> Result<Vec<Rc<Symbol>>, &'static str> {
-    let mut params = Vec::new();
+/// Evaluate the elements of `sexp` from the `from`th on, none in tail
+/// position, returning the last value (nil if there are none).
+fn eval_body(sexp: &Value, from: usize, image: &mut Image) -> Result<Value, Error> {
+    let mut last_val = Value::Nil;
+    let mut i = from;
+    while sexp.nth_exists(i) {
+        last_val = evaluate(sexp.nth(i), image)?;
+        i += 1;
+    }
+    Ok(last_val)
+}
+
+/// A parameter list taken apart: `(a b &optional c (d 1) &rest more)`.
+struct Params {
+    required: Vec<Rc<Symbol>>,
+    optional: Vec<(Rc<Symbol>, Rc<Value>)>,
+    rest: Option<Rc<Symbol>>,
+}
+
+/// Where `collect_params` is in a parameter list.
+#[derive(PartialEq)]
+enum ParamState {
+    Required,
+    Optional,
+    Rest,
+    Done,
+}
+
+/// Walk a parameter list: symbols, then after `&optional` symbols or
+/// `(symbol default)` pairs (an optional param's default is nil unless
+/// given), then `&rest` and one more symbol. Nil (empty list) has no
+/// params. A name starting with `&` is never bound, so a keyword out of
+/// place (say `&optional` after `&rest`) is a syntax error.
+fn collect_params(list: &Value) -> Result<Params, Error> {
+    let mut params = Params {
+        required: Vec::new(),
+        optional: Vec::new(),
+        rest: None,
+    };
+    let mut state = ParamState::Required;
     let mut current = list;
     loop {
         match current {
             Value::Nil => break,
             Value::Cons(car, cdr) => {
-                match &**car {
-                    Value::Symbol(s) => params.push(Rc::clone(s)),
-                    _ => return Err("Expected symbol in parameter list."),
+                match (&**car, &state) {
+                    (Value::Symbol(s), ParamState::Required | ParamState::Optional)
+                        if s.as_str() == "&optional" =>
+                    {
+                        if state == ParamState::Optional {
+                            return Err(Error::syntax("&optional given twice.").with(car));
+                        }
+                        state = ParamState::Optional;
+                    }
+                    (Value::Symbol(s), ParamState::Required | ParamState::Optional)
+                        if s.as_str() == "&rest" =>
+                    {
+                        state = ParamState::Rest;
+                    }
+                    (_, ParamState::Done) => {
+                        return Err(Error::syntax("Expected only one name after &rest.").with(car));
+                    }
+                    (Value::Symbol(s), _) if s.starts_with('&') => {
+                        return Err(Error::syntax(
+                            "Misplaced or unknown &-keyword in parameter list.",
+                        )
+                        .with(car));
+                    }
+                    (Value::Symbol(s), ParamState::Required) => params.required.push(Rc::clone(s)),
+                    (Value::Symbol(s), ParamState::Optional) => {
+                        params.optional.push((Rc::clone(s), Rc::new(Value::Nil)));
+                    }
+                    (Value::Cons(name, default), ParamState::Optional) => {
+                        match (&**name, &**default) {
+                            (Value::Symbol(s), Value::Cons(form, more))
+                                if more.is_nil() && !s.starts_with('&') =>
+                            {
+                                params.optional.push((Rc::clone(s), Rc::clone(form)));
+                            }
+                            _ => {
+                                return Err(Error::syntax(
+                                    "Expected symbol or (symbol default) after &optional.",
+                                )
+                                .with(car));
+                            }
+                        }
+                    }
+                    (Value::Symbol(s), ParamState::Rest) => {
+                        params.rest = Some(Rc::clone(s));
+                        state = ParamState::Done;
+                    }
+                    _ => {
+                        return Err(Error::syntax("Expected symbol in parameter list.").with(car));
+                    }
                 }
                 current = cdr;
             }
-            _ => return Err("Malformed parameter list (not a proper list)."),
+            _ => {
+                return Err(Error::syntax(
+                    "Malformed parameter list (not a proper list).",
+                ));
+            }
         }
     }
+    if state == ParamState::Rest {
+        return Err(Error::syntax("Expected a name after &rest."));
+    }
     Ok(params)
 }
 
@@ -469,7 +492,7 @@ pub fn execute_special(
     sexp: Rc<Value>,
     image: &mut Image,
     tail: bool,
-) -> Result<Value, &'static str> {
+) -> Result<Value, Error> {
     match form {
         // --- comparators ---
         Special::Gt => {
@@ -536,6 +559,50 @@ pub fn execute_special(
             let n = extract_numeric_unary(sexp, image)?;
             Ok(Value::Number(Number::Unsigned(n.as_u32()?)))
         }
+        Special::Float => {
+            let n = extract_numeric_unary(sexp, image)?;
+            Ok(Value::Number(Number::Float(n.as_f32()?)))
+        }
+
+        // --- floating point math ---
+        // Arguments are converted with `as_f32`; out-of-domain inputs
+        // give NaN, which `Number::float` makes an arithmetic error, as
+        // it does an overflow. `floor`/`ceil`/`round` give an
+        // Integer, the rest a Float.
+        Special::Sqrt
+        | Special::Sin
+        | Special::Cos
+        | Special::Tan
+        | Special::Exp
+        | Special::Log => {
+            let x = extract_numeric_unary(sexp, image)?.as_f32()?;
+            let f = match form {
+                Special::Sqrt => super::number::sqrt,
+                Special::Sin => libm::sinf,
+                Special::Cos => libm::cosf,
+                Special::Tan => libm::tanf,
+                Special::Exp => libm::expf,
+                _ => libm::logf,
+            };
+            Ok(Value::Number(Number::float(f(x))?))
+        }
+        Special::Floor | Special::Ceil | Special::Round => {
+            let n = extract_numeric_unary(sexp, image)?;
+            let round = match form {
+                Special::Floor => libm::floorf,
+                Special::Ceil => libm::ceilf,
+                _ => libm::roundf,
+            };
+            Ok(Value::Number(n.to_integer(round)?))
+        }
+        Special::Atan2 | Special::Pow => {
+            let (l, r) = extract_numeric_binop(sexp, image)?;
+            let f = match form {
+                Special::Atan2 => libm::atan2f,
+                _ => libm::powf,
+            };
+            Ok(Value::Number(Number::float(f(l.as_f32()?, r.as_f32()?))?))
+        }
 
         // --- logic ---
 
@@ -551,7 +618,7 @@ pub fn execute_special(
                 Value::Number(Number::Integer(_)) => Ok(Value::Number(Number::Integer(0))),
                 Value::Number(Number::Unsigned(0)) => Ok(Value::Number(Number::Unsigned(1))),
                 Value::Number(Number::Unsigned(_)) => Ok(Value::Number(Number::Unsigned(0))),
-                _ => Err("not: expected bool or integer."),
+                v => Err(Error::type_error("not: expected bool or integer.").with(v)),
             }
         }
 
@@ -561,7 +628,7 @@ pub fn execute_special(
             match n {
                 Number::Integer(i) => Ok(Value::Number(Number::Integer(!i))),
                 Number::Unsigned(u) => Ok(Value::Number(Number::Unsigned(!u))),
-                _ => Err("binnot: expected integer or unsigned."),
+                _ => Err(Error::type_error("binnot: expected integer or unsigned.")),
             }
         }
 
@@ -581,7 +648,7 @@ pub fn execute_special(
                 (Number::Integer(a), Number::Unsigned(b)) => {
                     Ok(Value::Number(Number::Unsigned(a as u32 | b)))
                 }
-                _ => Err("binor: expected integers or unsigned."),
+                _ => Err(Error::type_error("binor: expected integers or unsigned.")),
             }
         }
 
@@ -601,7 +668,7 @@ pub fn execute_special(
                 (Number::Integer(a), Number::Unsigned(b)) => {
                     Ok(Value::Number(Number::Unsigned(a as u32 & b)))
                 }
-                _ => Err("binand: expected integers or unsigned."),
+                _ => Err(Error::type_error("binand: expected integers or unsigned.")),
             }
         }
 
@@ -612,10 +679,10 @@ pub fn execute_special(
             let left = sexp.nth(1);
             let right = sexp.nth(2);
             if !sexp.nth_exists(2) {
-                return Err("cons: expected 2 arguments.");
+                return Err(Error::arity("cons: expected 2 arguments."));
             }
             if sexp.nth_exists(3) {
-                return Err("cons: too many arguments.");
+                return Err(Error::arity("cons: too many arguments."));
             }
             let l = evaluate(left, image)?;
             let r = evaluate(right, image)?;
@@ -664,10 +731,10 @@ pub fn execute_special(
         // `quote`: return the argument literally, unevaluated.
         Special::Quote => {
             if !sexp.nth_exists(1) {
-                return Err("quote: expected 1 argument.");
+                return Err(Error::arity("quote: expected 1 argument."));
             }
             if sexp.nth_exists(2) {
-                return Err("quote: too many arguments.");
+                return Err(Error::arity("quote: too many arguments."));
             }
             Ok((*sexp.nth(1)).clone())
         }
@@ -676,31 +743,33 @@ pub fn execute_special(
         // (unquote-splicing x) splices a list result into the enclosing list.
         Special::Quasiquote => {
             if !sexp.nth_exists(1) {
-                return Err("quasiquote: expected 1 argument.");
+                return Err(Error::arity("quasiquote: expected 1 argument."));
             }
             if sexp.nth_exists(2) {
-                return Err("quasiquote: too many arguments.");
+                return Err(Error::arity("quasiquote: too many arguments."));
             }
             quasiquote_expand(&sexp.nth(1), image)
         }
 
-        Special::Unquote => Err("unquote: not inside a quasiquote."),
-        Special::UnquoteSplicing => Err("unquote-splicing: not inside a quasiquote."),
+        Special::Unquote => Err(Error::syntax("unquote: not inside a quasiquote.")),
+        Special::UnquoteSplicing => {
+            Err(Error::syntax("unquote-splicing: not inside a quasiquote."))
+        }
 
         // `(ir <sexp>)` — run sexp through the JIT IR generator and
         // print the resulting segment. Useful for debugging cgen.
         // Returns nil.
         Special::Ir => {
             if !sexp.nth_exists(1) {
-                return Err("ir: expected 1 argument.");
+                return Err(Error::arity("ir: expected 1 argument."));
             }
             if sexp.nth_exists(2) {
-                return Err("ir: too many arguments.");
+                return Err(Error::arity("ir: too many arguments."));
             }
             let arg = sexp.nth(1);
             let mut seg = super::jit::ir::IRSegment::new();
             let mut jit_scope = super::jit::scope::JitImage::new(image);
-            seg.cgen(arg, &mut jit_scope)?;
+            seg.cgen(arg, &mut jit_scope).map_err(Error::jit)?;
             Ok(Value::String(dump_to_string(&seg)))
         }
 
@@ -709,15 +778,15 @@ pub fn execute_special(
         // IRSegment so we reuse the existing pretty-printer.
         Special::Oir => {
             if !sexp.nth_exists(1) {
-                return Err("oir: expected 1 argument.");
+                return Err(Error::arity("oir: expected 1 argument."));
             }
             if sexp.nth_exists(2) {
-                return Err("oir: too many arguments.");
+                return Err(Error::arity("oir: too many arguments."));
             }
             let arg = sexp.nth(1);
             let mut seg = super::jit::ir::IRSegment::new();
             let mut jit_scope = super::jit::scope::JitImage::new(image);
-            seg.cgen(arg, &mut jit_scope)?;
+            seg.cgen(arg, &mut jit_scope).map_err(Error::jit)?;
             let optimized = super::jit::optimize::optimize(seg);
             let folded: super::jit::ir::IRSegment = optimized.into();
             Ok(Value::String(dump_to_string(&folded)))
@@ -728,15 +797,15 @@ pub fn execute_special(
         // asm-lowering doc comments per opcode.
         Special::Ir3 => {
             if !sexp.nth_exists(1) {
-                return Err("ir3: expected 1 argument.");
+                return Err(Error::arity("ir3: expected 1 argument."));
             }
             if sexp.nth_exists(2) {
-                return Err("ir3: too many arguments.");
+                return Err(Error::arity("ir3: too many arguments."));
             }
             let arg = sexp.nth(1);
             let mut seg = super::jit::ir::IRSegment::new();
             let mut jit_scope = super::jit::scope::JitImage::new(image);
-            seg.cgen(arg, &mut jit_scope)?;
+            seg.cgen(arg, &mut jit_scope).map_err(Error::jit)?;
             let optimized = super::jit::optimize::optimize(seg);
             let folded: super::jit::ir::IRSegment = optimized.into();
             let mir: super::jit::ir2::MIRSegment = folded.into();
@@ -766,34 +835,40 @@ pub fn execute_special(
         //      baked pointers stay valid, no recompile needed.
         Special::Jit => {
             if !sexp.nth_exists(1) {
-                return Err("jit: expected closure and dummy arguments.");
+                return Err(Error::arity("jit: expected closure and dummy arguments."));
             }
             let closure_expr = sexp.nth(1);
             let closure_val = evaluate(closure_expr, image)?;
             let closure = match closure_val {
                 Value::Closure(c) => c,
-                _ => return Err("jit: first argument must be a closure."),
+                v => {
+                    return Err(
+                        Error::type_error("jit: first argument must be a closure.").with(&v)
+                    );
+                }
             };
 
             let mut dummies: Vec<Value> = Vec::with_capacity(closure.params.len());
             for i in 0..closure.params.len() {
                 if !sexp.nth_exists(i + 2) {
-                    return Err("jit: not enough dummy inputs for closure arity.");
+                    return Err(Error::arity(
+                        "jit: not enough dummy inputs for closure arity.",
+                    ));
                 }
                 dummies.push(evaluate(sexp.nth(i + 2), image)?);
             }
             if sexp.nth_exists(closure.params.len() + 2) {
-                return Err("jit: too many dummy inputs for closure arity.");
+                return Err(Error::arity(
+                    "jit: too many dummy inputs for closure arity.",
+                ));
             }
 
-            let input_types: Vec<super::jit::jit::InputType> = dummies
-                .iter()
-                .map(super::jit::jit::InputType::of)
-                .collect();
+            let input_types: Vec<super::jit::jit::InputType> =
+                dummies.iter().map(super::jit::jit::InputType::of).collect();
 
-            let jc = super::jit::jit::JittedClosure::compile(
-                &closure, &dummies, input_types, image,
-            )?;
+            let jc =
+                super::jit::jit::JittedClosure::compile(&closure, &dummies, input_types, image)
+                    .map_err(Error::jit)?;
             Ok(Value::JittedClosure(Rc::new(jc)))
         }
 
@@ -802,15 +877,15 @@ pub fn execute_special(
         // computed (equivalent to `(eval <sexp>)`).
         Special::JitExec => {
             if !sexp.nth_exists(1) {
-                return Err("jitexec: expected 1 argument.");
+                return Err(Error::arity("jitexec: expected 1 argument."));
             }
             if sexp.nth_exists(2) {
-                return Err("jitexec: too many arguments.");
+                return Err(Error::arity("jitexec: too many arguments."));
             }
             let arg = sexp.nth(1);
             let mut seg = super::jit::ir::IRSegment::new();
             let mut jit_scope = super::jit::scope::JitImage::new(image);
-            seg.cgen(arg, &mut jit_scope)?;
+            seg.cgen(arg, &mut jit_scope).map_err(Error::jit)?;
             let optimized = super::jit::optimize::optimize(seg);
             let folded: super::jit::ir::IRSegment = optimized.into();
             let mir: super::jit::ir2::MIRSegment = folded.into();
@@ -826,15 +901,15 @@ pub fn execute_special(
         // Runs cgen → optimize → ir2 → optimize2 → ir3 → regalloc.
         Special::Ir4 => {
             if !sexp.nth_exists(1) {
-                return Err("ir4: expected 1 argument.");
+                return Err(Error::arity("ir4: expected 1 argument."));
             }
             if sexp.nth_exists(2) {
-                return Err("ir4: too many arguments.");
+                return Err(Error::arity("ir4: too many arguments."));
             }
             let arg = sexp.nth(1);
             let mut seg = super::jit::ir::IRSegment::new();
             let mut jit_scope = super::jit::scope::JitImage::new(image);
-            seg.cgen(arg, &mut jit_scope)?;
+            seg.cgen(arg, &mut jit_scope).map_err(Error::jit)?;
             let optimized = super::jit::optimize::optimize(seg);
             let folded: super::jit::ir::IRSegment = optimized.into();
             let mir: super::jit::ir2::MIRSegment = folded.into();
@@ -848,15 +923,15 @@ pub fn execute_special(
         // optimizer pass.
         Special::Oir4 => {
             if !sexp.nth_exists(1) {
-                return Err("oir4: expected 1 argument.");
+                return Err(Error::arity("oir4: expected 1 argument."));
             }
             if sexp.nth_exists(2) {
-                return Err("oir4: too many arguments.");
+                return Err(Error::arity("oir4: too many arguments."));
             }
             let arg = sexp.nth(1);
             let mut seg = super::jit::ir::IRSegment::new();
             let mut jit_scope = super::jit::scope::JitImage::new(image);
-            seg.cgen(arg, &mut jit_scope)?;
+            seg.cgen(arg, &mut jit_scope).map_err(Error::jit)?;
             let optimized = super::jit::optimize::optimize(seg);
             let folded: super::jit::ir::IRSegment = optimized.into();
             let mir: super::jit::ir2::MIRSegment = folded.into();
@@ -871,15 +946,15 @@ pub fn execute_special(
         // optimizer pass (peephole + SCCP + DCE on MIR).
         Special::Oir2 => {
             if !sexp.nth_exists(1) {
-                return Err("oir2: expected 1 argument.");
+                return Err(Error::arity("oir2: expected 1 argument."));
             }
             if sexp.nth_exists(2) {
-                return Err("oir2: too many arguments.");
+                return Err(Error::arity("oir2: too many arguments."));
             }
             let arg = sexp.nth(1);
             let mut seg = super::jit::ir::IRSegment::new();
             let mut jit_scope = super::jit::scope::JitImage::new(image);
-            seg.cgen(arg, &mut jit_scope)?;
+            seg.cgen(arg, &mut jit_scope).map_err(Error::jit)?;
             let optimized = super::jit::optimize::optimize(seg);
             let folded: super::jit::ir::IRSegment = optimized.into();
             let mir: super::jit::ir2::MIRSegment = folded.into();
@@ -892,15 +967,15 @@ pub fn execute_special(
         // resulting `MIRSegment`.
         Special::Ir2 => {
             if !sexp.nth_exists(1) {
-                return Err("ir2: expected 1 argument.");
+                return Err(Error::arity("ir2: expected 1 argument."));
             }
             if sexp.nth_exists(2) {
-                return Err("ir2: too many arguments.");
+                return Err(Error::arity("ir2: too many arguments."));
             }
             let arg = sexp.nth(1);
             let mut seg = super::jit::ir::IRSegment::new();
             let mut jit_scope = super::jit::scope::JitImage::new(image);
-            seg.cgen(arg, &mut jit_scope)?;
+            seg.cgen(arg, &mut jit_scope).map_err(Error::jit)?;
             let optimized = super::jit::optimize::optimize(seg);
             let folded: super::jit::ir::IRSegment = optimized.into();
             let mir: super::jit::ir2::MIRSegment = folded.into();
@@ -914,7 +989,11 @@ pub fn execute_special(
             let count = match &val {
                 Value::Closure(c) => c.hits.get(),
                 Value::Macro(m) => m.closure.hits.get(),
-                _ => return Err("hits: argument must be a closure or macro."),
+                v => {
+                    return Err(
+                        Error::type_error("hits: argument must be a closure or macro.").with(v),
+                    );
+                }
             };
             Ok(Value::Number(Number::Unsigned(count as u32)))
         }
@@ -929,16 +1008,20 @@ pub fn execute_special(
             let param_list = sexp.nth(1);
             let body = sexp.nth(2);
             if !sexp.nth_exists(2) {
-                return Err("lambda: missing body.");
+                return Err(Error::arity("lambda: missing body."));
             }
             if sexp.nth_exists(3) {
-                return Err("lambda: too many arguments (expected params and body).");
+                return Err(Error::arity(
+                    "lambda: too many arguments (expected params and body).",
+                ));
             }
 
-            let params = collect_symbol_list(&param_list)?;
+            let params = collect_params(&param_list)?;
 
             Ok(Value::Closure(Closure {
-                params,
+                params: params.required,
+                optional: params.optional,
+                rest: params.rest,
                 body,
                 env: image.snapshot(),
                 hits: Rc::new(Cell::new(0u64)),
@@ -954,15 +1037,15 @@ pub fn execute_special(
             let name_val = sexp.nth(1);
             let val_expr = sexp.nth(2);
             if !sexp.nth_exists(2) {
-                return Err("set: expected name and value.");
+                return Err(Error::arity("set: expected name and value."));
             }
             if sexp.nth_exists(3) {
-                return Err("set: too many arguments.");
+                return Err(Error::arity("set: too many arguments."));
             }
 
             let name = match &*name_val {
                 Value::Symbol(s) => (**s).clone(),
-                _ => return Err("set: first argument must be a symbol."),
+                _ => return Err(Error::syntax("set: first argument must be a symbol.")),
             };
 
             // pre-create binding so that the value expression (e.g. a lambda)
@@ -986,10 +1069,10 @@ pub fn execute_special(
             let params = sexp.nth(2);
             let body = sexp.nth(3);
             if !sexp.nth_exists(3) {
-                return Err("defun: expected name, params, and body.");
+                return Err(Error::arity("defun: expected name, params, and body."));
             }
             if sexp.nth_exists(4) {
-                return Err("defun: too many arguments.");
+                return Err(Error::arity("defun: too many arguments."));
             }
 
             // build (set name (lambda (params) body))
@@ -1013,7 +1096,7 @@ pub fn execute_special(
             let left = sexp.nth(1);
             let right = sexp.nth(2);
             if !sexp.nth_exists(2) {
-                return Err("and: expected 2 arguments.");
+                return Err(Error::arity("and: expected 2 arguments."));
             }
             let l = evaluate(left, image)?;
             if is_falsy(&l) {
@@ -1029,7 +1112,7 @@ pub fn execute_special(
             let left = sexp.nth(1);
             let right = sexp.nth(2);
             if !sexp.nth_exists(2) {
-                return Err("or: expected 2 arguments.");
+                return Err(Error::arity("or: expected 2 arguments."));
             }
             let l = evaluate(left, image)?;
             if !is_falsy(&l) {
@@ -1044,7 +1127,7 @@ pub fn execute_special(
             let left = sexp.nth(1);
             let right = sexp.nth(2);
             if !sexp.nth_exists(2) {
-                return Err("xor: expected 2 arguments.");
+                return Err(Error::arity("xor: expected 2 arguments."));
             }
             let l = evaluate(left, image)?;
             let r = evaluate(right, image)?;
@@ -1061,7 +1144,7 @@ pub fn execute_special(
             let then_branch = sexp.nth(2);
             let else_branch = sexp.nth(3);
             if !sexp.nth_exists(3) {
-                return Err("if: expected condition, then, and else.");
+                return Err(Error::arity("if: expected condition, then, and else."));
             }
             let c = evaluate(cond, image)?;
             if !is_falsy(&c) {
@@ -1089,6 +1172,93 @@ pub fn execute_special(
             }
             Ok(last_val)
         }
+        // --- non-local exits ---
+        // An error unwinds by returning `Err` all the way up, leaving
+        // the frames of the scopes it passes through on the Image; what
+        // stops it drops them with `image.unwind`. Bodies here are
+        // never in tail position, since a `TailCall` token would only
+        // be run after the form had returned.
+
+        // `catch`: (catch tag body...)
+        // Evaluates tag, then body. A `throw` to the tag inside body
+        // makes its value that of the catch; with the tag `error` so
+        // does any other error, as `(kind message payload)`.
+        Special::Catch => {
+            if !sexp.nth_exists(1) {
+                return Err(Error::arity("catch: expected a tag."));
+            }
+            let tag = evaluate(sexp.nth(1), image)?;
+            let depth = image.depth();
+            eval_body(&sexp, 2, image).or_else(|e| match e.catch(&tag) {
+                Some(v) => {
+                    image.unwind(depth);
+                    Ok(v)
+                }
+                None => Err(e),
+            })
+        }
+
+        // `throw`: (throw tag value)
+        Special::Throw => {
+            if !sexp.nth_exists(2) {
+                return Err(Error::arity("throw: expected a tag and a value."));
+            }
+            if sexp.nth_exists(3) {
+                return Err(Error::arity("throw: too many arguments."));
+            }
+            let tag = evaluate(sexp.nth(1), image)?;
+            let value = evaluate(sexp.nth(2), image)?;
+            Err(Error::throw(tag, value))
+        }
+
+        // `error`: (error message [payload])
+        // A message that isn't a string is printed.
+        Special::Error => {
+            if !sexp.nth_exists(1) {
+                return Err(Error::arity("error: expected a message."));
+            }
+            if sexp.nth_exists(3) {
+                return Err(Error::arity("error: too many arguments."));
+            }
+            let message = match evaluate(sexp.nth(1), image)? {
+                Value::String(s) => s,
+                v => alloc::format!("{}", v),
+            };
+            let payload = if sexp.nth_exists(2) {
+                Some(evaluate(sexp.nth(2), image)?)
+            } else {
+                None
+            };
+            Err(Error::user(message, payload))
+        }
+
+        // `unwind-protect`: (unwind-protect body cleanup...)
+        // Evaluates body, then cleanup, even if body failed (or was
+        // interrupted). Returns body's value, or fails with its error;
+        // an error in cleanup takes its place.
+        Special::UnwindProtect => {
+            if !sexp.nth_exists(1) {
+                return Err(Error::arity("unwind-protect: expected a body."));
+            }
+            let depth = image.depth();
+            let result = evaluate(sexp.nth(1), image);
+            if result.is_err() {
+                image.unwind(depth);
+            }
+            // an interrupt stays pending, which would stop the cleanup
+            // at its first call
+            let interrupted = matches!(&result, Err(e) if e.kind == ErrorKind::Interrupted);
+            if interrupted {
+                interrupt::clear();
+            }
+            let cleanup = eval_body(&sexp, 2, image);
+            if interrupted {
+                interrupt::raise();
+            }
+            cleanup?;
+            result
+        }
+
         // `let`: introduce local bindings, then evaluate body in that scope.
         //   (let (a 1 b 2 c 3) body)
         // The binding list is a flat cons list of name/value pairs.
@@ -1099,10 +1269,10 @@ pub fn execute_special(
             let bindings_list = sexp.nth(1);
             let body = sexp.nth(2);
             if !sexp.nth_exists(2) {
-                return Err("let: expected bindings and body.");
+                return Err(Error::arity("let: expected bindings and body."));
             }
             if sexp.nth_exists(3) {
-                return Err("let: too many arguments.");
+                return Err(Error::arity("let: too many arguments."));
             }
 
             // push a new frame for let-bindings
@@ -1119,7 +1289,7 @@ pub fn execute_special(
                             Value::Symbol(s) => (**s).clone(),
                             _ => {
                                 image.pop_frame();
-                                return Err("let: binding name must be a symbol.");
+                                return Err(Error::syntax("let: binding name must be a symbol."));
                             }
                         };
 
@@ -1128,7 +1298,9 @@ pub fn execute_special(
                             Value::Cons(val, tail) => (Rc::clone(val), &**tail),
                             _ => {
                                 image.pop_frame();
-                                return Err("let: odd number of elements in binding list.");
+                                return Err(Error::syntax(
+                                    "let: odd number of elements in binding list.",
+                                ));
                             }
                         };
 
@@ -1146,7 +1318,7 @@ pub fn execute_special(
                     }
                     _ => {
                         image.pop_frame();
-                        return Err("let: malformed binding list.");
+                        return Err(Error::syntax("let: malformed binding list."));
                     }
                 }
             }
@@ -1172,27 +1344,29 @@ pub fn execute_special(
             let param_list = sexp.nth(2);
             let body = sexp.nth(3);
             if !sexp.nth_exists(3) {
-                return Err("defmacro: expected name, params, and body.");
+                return Err(Error::arity("defmacro: expected name, params, and body."));
             }
             if sexp.nth_exists(4) {
-                return Err("defmacro: too many arguments.");
+                return Err(Error::arity("defmacro: too many arguments."));
             }
 
             let name = match &*name_val {
                 Value::Symbol(s) => (**s).clone(),
-                _ => return Err("defmacro: first argument must be a symbol."),
+                _ => return Err(Error::syntax("defmacro: first argument must be a symbol.")),
             };
 
-            let params = collect_symbol_list(&param_list)?;
+            let params = collect_params(&param_list)?;
 
             let closure = Closure {
-                params: params.clone(),
+                params: params.required,
+                optional: params.optional,
+                rest: params.rest,
                 body,
                 env: image.snapshot(),
                 hits: Rc::new(Cell::new(0u64)),
             };
 
-            let mac = Value::Macro(Macro { params, closure });
+            let mac = Value::Macro(Macro { closure });
 
             if let Some(binding) = image.binding(&name) {
                 *binding.borrow_mut() = Rc::new(mac.clone());
@@ -1210,10 +1384,10 @@ pub fn execute_special(
         Special::Macroexpand => {
             let arg = sexp.nth(1);
             if !sexp.nth_exists(1) {
-                return Err("macroexpand: expected 1 argument.");
+                return Err(Error::arity("macroexpand: expected 1 argument."));
             }
             if sexp.nth_exists(2) {
-                return Err("macroexpand: too many arguments.");
+                return Err(Error::arity("macroexpand: too many arguments."));
             }
 
             // arg should be a macro call sexp like (my-macro x y)
@@ -1222,40 +1396,18 @@ pub fn execute_special(
             let mac_val = match &*head {
                 Value::Symbol(s) => match image.get(s) {
                     Some(v) => v,
-                    None => return Err("macroexpand: unknown symbol."),
+                    None => return Err(Error::unbound(s)),
                 },
                 _ => head,
             };
 
             let m = match &*mac_val {
                 Value::Macro(m) => m.clone(),
-                _ => return Err("macroexpand: argument is not a macro call."),
+                _ => return Err(Error::syntax("macroexpand: argument is not a macro call.")),
             };
 
-            // collect unevaluated args
-            let arg_vals: Vec<Rc<Value>> = m
-                .params
-                .iter()
-                .enumerate()
-                .map(|(i, _)| arg.nth(i + 1))
-                .collect();
-
-            // push captured env + params frame, evaluate, pop both
-            image.push_env(&m.closure.env);  // O(1) — Rc::clone
-            image.push_frame();
-            m.params
-                .iter()
-                .zip(arg_vals.into_iter())
-                .for_each(|(param, val)| {
-                    image.insert((**param).clone(), val);
-                });
-
-            let expanded = evaluate(Rc::clone(&m.closure.body), image);
-
-            image.pop_frame();
-            image.pop_frame();
-
-            expanded
+            // call the macro's closure on the unevaluated args
+            call_closure(&m.closure, list_items(&arg.cdr()), image, &arg)
         }
 
         // --- arrays ---
@@ -1270,14 +1422,16 @@ pub fn execute_special(
                     Value::Nil => break,
                     Value::Cons(head, tail) => {
                         if let Value::Number(n) = head.as_ref() {
-                            let u = n.as_u32().map_err(|_| "array: elements must be u32.")?;
+                            let u = n
+                                .as_u32()
+                                .map_err(|_| Error::type_error("array: elements must be u32."))?;
                             v.push(u);
                             cur = tail.as_ref().clone();
                         } else {
-                            return Err("array: elements must be numbers.");
+                            return Err(Error::type_error("array: elements must be numbers."));
                         }
                     }
-                    _ => return Err("array: argument must be a list."),
+                    _ => return Err(Error::type_error("array: argument must be a list.")),
                 }
             }
             Ok(Value::array(v))
@@ -1286,8 +1440,13 @@ pub fn execute_special(
         // `(full n value)` — create a new array of n copies of value
         Special::Full => {
             let (l, r) = extract_numeric_binop(sexp.clone(), image)?;
-            let n = l.as_i32().map_err(|_| "full: first arg must be an integer.")? as usize;
-            let val = r.as_u32().map_err(|_| "full: second arg must be a u32.")?;
+            let n = l
+                .as_i32()
+                .map_err(|_| Error::type_error("full: first arg must be an integer."))?
+                as usize;
+            let val = r
+                .as_u32()
+                .map_err(|_| Error::type_error("full: second arg must be a u32."))?;
             Ok(Value::array_fill(n, val))
         }
 
@@ -1298,14 +1457,11 @@ pub fn execute_special(
                 let borrowed = a.borrow();
                 let mut result = Value::Nil;
                 for u in borrowed.iter().rev() {
-                    result = Value::cons(
-                        Value::Number(Number::Unsigned(*u)),
-                        result,
-                    );
+                    result = Value::cons(Value::Number(Number::Unsigned(*u)), result);
                 }
                 Ok(result)
             } else {
-                Err("unpack: argument must be an array.")
+                Err(Error::type_error("unpack: argument must be an array."))
             }
         }
 
@@ -1318,14 +1474,21 @@ pub fn execute_special(
             let val = match &target {
                 Value::Array(a) => {
                     let b = a.borrow();
-                    if i >= b.len() { return Err("getidx: index out of bounds."); }
+                    if i >= b.len() {
+                        return Err(Error::bounds("getidx: index out of bounds."));
+                    }
                     b[i]
                 }
                 Value::Number(n) => {
                     let base = extract_addr(n, "getidx: first arg")?;
                     unsafe { *(base.wrapping_add(i) as *const u32) }
                 }
-                _ => return Err("getidx: first arg must be an array or address."),
+                v => {
+                    return Err(Error::type_error(
+                        "getidx: first arg must be an array or address.",
+                    )
+                    .with(v));
+                }
             };
             Ok(Value::Number(Number::Unsigned(val)))
         }
@@ -1341,14 +1504,23 @@ pub fn execute_special(
             match &target {
                 Value::Array(a) => {
                     let mut b = a.borrow_mut();
-                    if i >= b.len() { return Err("putidx: index out of bounds."); }
+                    if i >= b.len() {
+                        return Err(Error::bounds("putidx: index out of bounds."));
+                    }
                     b[i] = val;
                 }
                 Value::Number(n) => {
                     let base = extract_addr(n, "putidx: first arg")?;
-                    unsafe { *(base.wrapping_add(i) as *mut u32) = val; }
+                    unsafe {
+                        *(base.wrapping_add(i) as *mut u32) = val;
+                    }
+                }
+                v => {
+                    return Err(Error::type_error(
+                        "putidx: first arg must be an array or address.",
+                    )
+                    .with(v));
                 }
-                _ => return Err("putidx: first arg must be an array or address."),
             }
             Ok(Value::Nil)
         }
@@ -1365,25 +1537,27 @@ pub fn execute_special(
             match &target {
                 Value::Array(a) => {
                     let b = a.borrow();
-                    if offset + count > b.len() { return Err("readidx: range out of bounds."); }
+                    if offset + count > b.len() {
+                        return Err(Error::bounds("readidx: range out of bounds."));
+                    }
                     for i in (0..count).rev() {
-                        result = Value::cons(
-                            Value::Number(Number::Unsigned(b[offset + i])),
-                            result,
-                        );
+                        result =
+                            Value::cons(Value::Number(Number::Unsigned(b[offset + i])), result);
                     }
                 }
                 Value::Number(n) => {
                     let base = extract_addr(n, "readidx: first arg")?;
                     for i in (0..count).rev() {
                         let val = unsafe { *(base.wrapping_add(offset + i) as *const u32) };
-                        result = Value::cons(
-                            Value::Number(Number::Unsigned(val)),
-                            result,
-                        );
+                        result = Value::cons(Value::Number(Number::Unsigned(val)), result);
                     }
                 }
-                _ => return Err("readidx: first arg must be an array or address."),
+                v => {
+                    return Err(Error::type_error(
+                        "readidx: first arg must be an array or address.",
+                    )
+                    .with(v));
+                }
             }
             Ok(result)
         }
@@ -1405,12 +1579,18 @@ pub fn execute_special(
                             Value::Nil => break,
                             Value::Cons(head, tail) => {
                                 let val = extract_u32(head, "fillidx: list element")?;
-                                if offset + i >= b.len() { return Err("fillidx: write out of bounds."); }
+                                if offset + i >= b.len() {
+                                    return Err(Error::bounds("fillidx: write out of bounds."));
+                                }
                                 b[offset + i] = val;
                                 i += 1;
                                 cur = tail.as_ref().clone();
                             }
-                            _ => return Err("fillidx: third arg must be a list."),
+                            _ => {
+                                return Err(Error::type_error(
+                                    "fillidx: third arg must be a list.",
+                                ));
+                            }
                         }
                     }
                 }
@@ -1423,15 +1603,26 @@ pub fn execute_special(
                             Value::Nil => break,
                             Value::Cons(head, tail) => {
                                 let val = extract_u32(head, "fillidx: list element")?;
-                                unsafe { *(base.wrapping_add(offset + i) as *mut u32) = val; }
+                                unsafe {
+                                    *(base.wrapping_add(offset + i) as *mut u32) = val;
+                                }
                                 i += 1;
                                 cur = tail.as_ref().clone();
                             }
-                            _ => return Err("fillidx: third arg must be a list."),
+                            _ => {
+                                return Err(Error::type_error(
+                                    "fillidx: third arg must be a list.",
+                                ));
+                            }
                         }
                     }
                 }
-                _ => return Err("fillidx: first arg must be an array or address."),
+                v => {
+                    return Err(Error::type_error(
+                        "fillidx: first arg must be an array or address.",
+                    )
+                    .with(v));
+                }
             }
             Ok(Value::Nil)
         }
@@ -1449,7 +1640,9 @@ pub fn execute_special(
             match &target {
                 Value::Array(a) => {
                     let mut b = a.borrow_mut();
-                    if offset + count > b.len() { return Err("fullidx: range out of bounds."); }
+                    if offset + count > b.len() {
+                        return Err(Error::bounds("fullidx: range out of bounds."));
+                    }
                     b[offset..offset + count].fill(val);
                 }
                 Value::Number(n) => {
@@ -1459,7 +1652,12 @@ pub fn execute_special(
                     };
                     dst.fill(val);
                 }
-                _ => return Err("fullidx: first arg must be an array or address."),
+                v => {
+                    return Err(Error::type_error(
+                        "fullidx: first arg must be an array or address.",
+                    )
+                    .with(v));
+                }
             }
             Ok(Value::Nil)
         }
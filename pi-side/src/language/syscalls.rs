@@ -1,15 +1,20 @@
 //! LISP Syscall Infrastructure
 
+use alloc::format;
 use alloc::rc::Rc;
+use alloc::string::String;
 use proc_bitfield::{ConvRaw, bitfield};
 
 use core::alloc::Layout;
 
 use super::ast::Value;
 use super::environment::Image;
+use super::error::{Error, ErrorKind};
 use super::execute::evaluate;
+use super::parse::parse;
+use super::source::Source;
 
-use crate::comm::uart;
+use crate::comm::{host, uart};
 use crate::utils::memory::{dsb, get32, prefetch_flush, put32};
 
 // static BAD_APPLE: &[u8] = include_bytes!("apple.bin");
@@ -46,82 +51,58 @@ pub enum Syscall {
     /// `(hi . lo)` of two Unsigned values. Use this when you need to
     /// span longer than 71 minutes or want to avoid wraparound math.
     TimerUs64,
+    /// `(@host/read path)` — the contents of `path` in the directory the
+    /// unix side shares (`--host-dir`), as a string.
+    HostRead,
+    /// `(@host/load path)` — evaluate every form in a host file, in
+    /// order, returning the last value.
+    HostLoad,
+    /// `(@host/write path value)` — replace a host file with `value`
+    /// (a string, or anything else as it prints).
+    HostWrite,
+    /// `(@host/append path value)` — `@host/write`, adding to the end.
+    HostAppend,
 }
 
 impl Syscall {
+    /// Every syscall name, as written after the `@`.
+    pub const NAMES: &[(&str, Syscall)] = &[
+        // ("apple", Self::Apple),
+        ("get32", Self::Get32),
+        ("put32", Self::Put32),
+        ("dsb", Self::DSB),
+        ("prefetch_flush", Self::PrefetchFlush),
+        ("uart/init", Self::UartInit),
+        ("uart/put8", Self::UartPut8),
+        ("uart/get8", Self::UartGet8),
+        ("delay", Self::Delay),
+        ("alloc32", Self::Alloc32),
+        ("free32", Self::Free32),
+        ("read32", Self::Read32),
+        ("zero32", Self::Zero32),
+        ("fill32", Self::Fill32),
+        ("full32", Self::Full32),
+        ("ldr", Self::Ldr),
+        ("str", Self::Str),
+        ("unpack1to16", Self::Unpack1to16),
+        ("monitor/clear", Self::ClearSetMonitor),
+        ("monitor/get", Self::GetMonitor),
+        ("monitor/stop", Self::StopMonitor),
+        ("timer/us", Self::TimerUs),
+        ("timer/us64", Self::TimerUs64),
+        ("host/read", Self::HostRead),
+        ("host/load", Self::HostLoad),
+        ("host/write", Self::HostWrite),
+        ("host/append", Self::HostAppend),
+    ];
+
     /// Look up a syscall by name (case-insensitive).
     /// Returns None for unknown names.
     pub fn from_name(name: &str) -> Option<Self> {
-        // if name.eq_ignore_ascii_case("apple") {
-        //     return Some(Self::Apple);
-        // }
-        if name.eq_ignore_ascii_case("get32") {
-            return Some(Self::Get32);
-        }
-        if name.eq_ignore_ascii_case("put32") {
-            return Some(Self::Put32);
-        }
-        if name.eq_ignore_ascii_case("dsb") {
-            return Some(Self::DSB);
-        }
-        if name.eq_ignore_ascii_case("prefetch_flush") {
-            return Some(Self::PrefetchFlush);
-        }
-        if name.eq_ignore_ascii_case("uart/init") {
-            return Some(Self::UartInit);
-        }
-        if name.eq_ignore_ascii_case("uart/put8") {
-            return Some(Self::UartPut8);
-        }
-        if name.eq_ignore_ascii_case("uart/get8") {
-            return Some(Self::UartGet8);
-        }
-        if name.eq_ignore_ascii_case("delay") {
-            return Some(Self::Delay);
-        }
-        if name.eq_ignore_ascii_case("alloc32") {
-            return Some(Self::Alloc32);
-        }
-        if name.eq_ignore_ascii_case("free32") {
-            return Some(Self::Free32);
-        }
-        if name.eq_ignore_ascii_case("read32") {
-            return Some(Self::Read32);
-        }
-        if name.eq_ignore_ascii_case("zero32") {
-            return Some(Self::Zero32);
-        }
-        if name.eq_ignore_ascii_case("fill32") {
-            return Some(Self::Fill32);
-        }
-        if name.eq_ignore_ascii_case("full32") {
-            return Some(Self::Full32);
-        }
-        if name.eq_ignore_ascii_case("ldr") {
-            return Some(Self::Ldr);
-        }
-        if name.eq_ignore_ascii_case("str") {
-            return Some(Self::Str);
-        }
-        if name.eq_ignore_ascii_case("unpack1to16") {
-            return Some(Self::Unpack1to16);
-        }
-        if name.eq_ignore_ascii_case("monitor/clear") {
-            return Some(Self::ClearSetMonitor);
-        }
-        if name.eq_ignore_ascii_case("monitor/get") {
-            return Some(Self::GetMonitor);
-        }
-        if name.eq_ignore_ascii_case("monitor/stop") {
-            return Some(Self::StopMonitor);
-        }
-        if name.eq_ignore_ascii_case("timer/us") {
-            return Some(Self::TimerUs);
-        }
-        if name.eq_ignore_ascii_case("timer/us64") {
-            return Some(Self::TimerUs64);
-        }
-        None
+        Self::NAMES
+            .iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.clone())
     }
 }
 
@@ -163,11 +144,27 @@ bitfield! {
     }
 }
 
+/// Evaluate the path argument of a `@host/...` syscall.
+fn host_path(sexp: Rc<Value>, image: &mut Image, err: &'static str) -> Result<String, Error> {
+    match evaluate(sexp.nth(1), image)? {
+        Value::String(s) => Ok(s),
+        v => Err(Error::type_error(err).with(&v)),
+    }
+}
+
+/// True if `src` holds nothing but whitespace and `;` comments.
+fn only_comments(src: &str) -> bool {
+    src.lines().all(|l| {
+        let l = l.trim_start();
+        l.is_empty() || l.starts_with(';')
+    })
+}
+
 pub fn execute_syscall(
     syscall: Syscall,
     sexp: Rc<Value>,
     image: &mut Image,
-) -> Result<Value, &'static str> {
+) -> Result<Value, Error> {
     match syscall {
         Syscall::GetMonitor => {
             let clocks = unsafe {
@@ -251,6 +248,41 @@ pub fn execute_syscall(
                 Value::Number(super::number::Number::Unsigned(lo)),
             ))
         }
+        Syscall::HostRead => {
+            let path = host_path(sexp, image, "host/read: path must be a string.")?;
+            let bytes = host::read(&path).map_err(Error::host)?;
+            let text = String::from_utf8(bytes)
+                .map_err(|_| Error::host("host/read: file isn't UTF-8."))?;
+            Ok(Value::String(text))
+        }
+        Syscall::HostLoad => {
+            let path = host_path(sexp, image, "host/load: path must be a string.")?;
+            let bytes = host::read(&path).map_err(Error::host)?;
+            let text = String::from_utf8(bytes)
+                .map_err(|_| Error::host("host/load: file isn't UTF-8."))?;
+            let source = Source::new(Some(path), text);
+            let mut at = 0;
+            let mut result = Value::Nil;
+            // `parse` skips what precedes a form but not what follows the
+            // last one
+            while !only_comments(&source.text[at..]) {
+                let (form, end) = parse(&source, at, image.spans_mut())
+                    .map_err(|e| Error::new(ErrorKind::Syntax, format!("{}", e)))?;
+                result = evaluate(form.into(), image)?;
+                at = end;
+            }
+            Ok(result)
+        }
+        Syscall::HostWrite | Syscall::HostAppend => {
+            let append = syscall == Syscall::HostAppend;
+            let path = host_path(sexp.clone(), image, "host/write: path must be a string.")?;
+            let text = match evaluate(sexp.nth(2), image)? {
+                Value::String(s) => s,
+                v => format!("{}", v),
+            };
+            host::write(&path, text.as_bytes(), append).map_err(Error::host)?;
+            Ok(Value::Nil)
+        }
         // Syscall::Apple => {
         //     // Returns (addr nframes) pointing to the raw 1bpp Bad Apple data.
         //     // Each frame is 320x240 pixels at 1bpp = 9600 bytes.
@@ -268,12 +300,12 @@ pub fn execute_syscall(
         Syscall::Get32 => {
             let addr = evaluate(sexp.nth(1), image)?;
             if let Value::Number(n) = &addr {
-                let raw_addr = n
-                    .as_addr()
-                    .map_err(|_| "GET32: argument must be an address or non-negative integer.")?;
+                let raw_addr = n.as_addr().map_err(|_| {
+                    Error::type_error("GET32: argument must be an address or non-negative integer.")
+                })?;
                 if let super::number::Number::Addr(a) = raw_addr {
                     if a % 4 != 0 {
-                        return Err("GET32: address must be 4-byte aligned.");
+                        return Err(Error::memory("GET32: address must be 4-byte aligned."));
                     }
                     let val = unsafe { get32(a) };
                     Ok(Value::Number(super::number::Number::Unsigned(val)))
@@ -281,22 +313,24 @@ pub fn execute_syscall(
                     unreachable!()
                 }
             } else {
-                Err("GET32 requires a number argument.")
+                Err(Error::type_error("GET32 requires a number argument."))
             }
         }
         Syscall::Put32 => {
             let addr_val = evaluate(sexp.nth(1), image)?;
             let val_val = evaluate(sexp.nth(2), image)?;
             if let (Value::Number(n_addr), Value::Number(n_val)) = (&addr_val, &val_val) {
-                let raw_addr = n_addr.as_addr().map_err(
-                    |_| "PUT32: first argument must be an address or non-negative integer.",
-                )?;
-                let raw_val = n_val
-                    .as_u32()
-                    .map_err(|_| "PUT32: second argument must be an integer or unsigned.")?;
+                let raw_addr = n_addr.as_addr().map_err(|_| {
+                    Error::type_error(
+                        "PUT32: first argument must be an address or non-negative integer.",
+                    )
+                })?;
+                let raw_val = n_val.as_u32().map_err(|_| {
+                    Error::type_error("PUT32: second argument must be an integer or unsigned.")
+                })?;
                 if let super::number::Number::Addr(a) = raw_addr {
                     if a % 4 != 0 {
-                        return Err("PUT32: address must be 4-byte aligned.");
+                        return Err(Error::memory("PUT32: address must be 4-byte aligned."));
                     }
                     unsafe { put32(a, raw_val) };
                     prefetch_flush();
@@ -306,7 +340,7 @@ pub fn execute_syscall(
                     unreachable!()
                 }
             } else {
-                Err("PUT32 requires two number arguments.")
+                Err(Error::type_error("PUT32 requires two number arguments."))
             }
         }
         Syscall::DSB => {
@@ -326,11 +360,11 @@ pub fn execute_syscall(
             if let Value::Number(n) = &val {
                 let byte = n
                     .as_i32()
-                    .map_err(|_| "uart/put8: argument must be an integer.")?;
+                    .map_err(|_| Error::type_error("uart/put8: argument must be an integer."))?;
                 uart::put8(byte as u8);
                 Ok(Value::Nil)
             } else {
-                Err("uart/put8 requires a number argument.")
+                Err(Error::type_error("uart/put8 requires a number argument."))
             }
         }
         Syscall::UartGet8 => {
@@ -342,9 +376,9 @@ pub fn execute_syscall(
             if let Value::Number(n) = &val {
                 let count = n
                     .as_i32()
-                    .map_err(|_| "delay: argument must be an integer.")?;
+                    .map_err(|_| Error::type_error("delay: argument must be an integer."))?;
                 if count < 0 {
-                    return Err("delay: argument must be non-negative.");
+                    return Err(Error::type_error("delay: argument must be non-negative."));
                 }
                 for _ in 0..count {
                     unsafe {
@@ -353,34 +387,36 @@ pub fn execute_syscall(
                 }
                 Ok(Value::Nil)
             } else {
-                Err("delay requires a number argument.")
+                Err(Error::type_error("delay requires a number argument."))
             }
         }
         Syscall::Alloc32 => {
             let val = evaluate(sexp.nth(1), image)?;
             let count = if let Value::Number(n) = &val {
-                let c = n
-                    .as_i32()
-                    .map_err(|_| "alloc32: first argument must be an integer.")?;
+                let c = n.as_i32().map_err(|_| {
+                    Error::type_error("alloc32: first argument must be an integer.")
+                })?;
                 if c < 0 {
-                    return Err("alloc32: count must be non-negative.");
+                    return Err(Error::type_error("alloc32: count must be non-negative."));
                 }
                 c as usize
             } else {
-                return Err("alloc32 requires a number argument.");
+                return Err(Error::type_error("alloc32 requires a number argument."));
             };
             let align = if sexp.nth_exists(2) {
                 let a_val = evaluate(sexp.nth(2), image)?;
                 if let Value::Number(n) = &a_val {
                     let a = n
                         .as_i32()
-                        .map_err(|_| "alloc32: alignment must be an integer.")?;
+                        .map_err(|_| Error::type_error("alloc32: alignment must be an integer."))?;
                     if a < 4 || (a as u32) & (a as u32 - 1) != 0 {
-                        return Err("alloc32: alignment must be a power of 2 >= 4.");
+                        return Err(Error::memory(
+                            "alloc32: alignment must be a power of 2 >= 4.",
+                        ));
                     }
                     a as usize
                 } else {
-                    return Err("alloc32: alignment must be a number.");
+                    return Err(Error::type_error("alloc32: alignment must be a number."));
                 }
             } else {
                 4
@@ -389,11 +425,11 @@ pub fn execute_syscall(
             if size == 0 {
                 return Ok(Value::Number(super::number::Number::Addr(align)));
             }
-            let layout =
-                Layout::from_size_align(size, align).map_err(|_| "alloc32: invalid layout.")?;
+            let layout = Layout::from_size_align(size, align)
+                .map_err(|_| Error::memory("alloc32: invalid layout."))?;
             let ptr = unsafe { alloc::alloc::alloc_zeroed(layout) };
             if ptr.is_null() {
-                return Err("alloc32: allocation failed.");
+                return Err(Error::memory("alloc32: allocation failed."));
             }
             Ok(Value::Number(super::number::Number::Addr(ptr as usize)))
         }
@@ -406,26 +442,28 @@ pub fn execute_syscall(
             let base = if let Value::Number(n) = &addr_val {
                 let a = n
                     .as_addr()
-                    .map_err(|_| "read32: first arg must be an address.")?;
+                    .map_err(|_| Error::type_error("read32: first arg must be an address."))?;
                 if let super::number::Number::Addr(a) = a {
                     a
                 } else {
                     unreachable!()
                 }
             } else {
-                return Err("read32: first arg must be an address.");
+                return Err(Error::type_error("read32: first arg must be an address."));
             };
             let offset = if let Value::Number(n) = &off_val {
                 n.as_i32()
-                    .map_err(|_| "read32: offset must be an integer.")? as usize
+                    .map_err(|_| Error::type_error("read32: offset must be an integer."))?
+                    as usize
             } else {
-                return Err("read32: offset must be a number.");
+                return Err(Error::type_error("read32: offset must be a number."));
             };
             let count = if let Value::Number(n) = &n_val {
                 n.as_i32()
-                    .map_err(|_| "read32: count must be an integer.")? as usize
+                    .map_err(|_| Error::type_error("read32: count must be an integer."))?
+                    as usize
             } else {
-                return Err("read32: count must be a number.");
+                return Err(Error::type_error("read32: count must be a number."));
             };
             let mut result = Value::Nil;
             for i in (0..count).rev() {
@@ -444,26 +482,28 @@ pub fn execute_syscall(
             let base = if let Value::Number(n) = &addr_val {
                 let a = n
                     .as_addr()
-                    .map_err(|_| "zero32: first arg must be an address.")?;
+                    .map_err(|_| Error::type_error("zero32: first arg must be an address."))?;
                 if let super::number::Number::Addr(a) = a {
                     a
                 } else {
                     unreachable!()
                 }
             } else {
-                return Err("zero32: first arg must be an address.");
+                return Err(Error::type_error("zero32: first arg must be an address."));
             };
             let offset = if let Value::Number(n) = &off_val {
                 n.as_i32()
-                    .map_err(|_| "zero32: offset must be an integer.")? as usize
+                    .map_err(|_| Error::type_error("zero32: offset must be an integer."))?
+                    as usize
             } else {
-                return Err("zero32: offset must be a number.");
+                return Err(Error::type_error("zero32: offset must be a number."));
             };
             let count = if let Value::Number(n) = &n_val {
                 n.as_i32()
-                    .map_err(|_| "zero32: count must be an integer.")? as usize
+                    .map_err(|_| Error::type_error("zero32: count must be an integer."))?
+                    as usize
             } else {
-                return Err("zero32: count must be a number.");
+                return Err(Error::type_error("zero32: count must be a number."));
             };
             for i in 0..count {
                 let addr = base + (offset + i) * 4;
@@ -481,20 +521,21 @@ pub fn execute_syscall(
             let base = if let Value::Number(n) = &addr_val {
                 let a = n
                     .as_addr()
-                    .map_err(|_| "fill32: first arg must be an address.")?;
+                    .map_err(|_| Error::type_error("fill32: first arg must be an address."))?;
                 if let super::number::Number::Addr(a) = a {
                     a
                 } else {
                     unreachable!()
                 }
             } else {
-                return Err("fill32: first arg must be an address.");
+                return Err(Error::type_error("fill32: first arg must be an address."));
             };
             let offset = if let Value::Number(n) = &off_val {
                 n.as_i32()
-                    .map_err(|_| "fill32: offset must be an integer.")? as usize
+                    .map_err(|_| Error::type_error("fill32: offset must be an integer."))?
+                    as usize
             } else {
-                return Err("fill32: offset must be a number.");
+                return Err(Error::type_error("fill32: offset must be a number."));
             };
             let mut cur = list_val;
             let mut i = 0;
@@ -503,18 +544,20 @@ pub fn execute_syscall(
                     Value::Nil => break,
                     Value::Cons(head, tail) => {
                         if let Value::Number(n) = head.as_ref() {
-                            let val = n
-                                .as_u32()
-                                .map_err(|_| "fill32: list element must be a u32.")?;
+                            let val = n.as_u32().map_err(|_| {
+                                Error::type_error("fill32: list element must be a u32.")
+                            })?;
                             let addr = base + (offset + i) * 4;
                             unsafe { put32(addr, val) };
                             i += 1;
                             cur = tail.as_ref().clone();
                         } else {
-                            return Err("fill32: list elements must be numbers.");
+                            return Err(Error::type_error(
+                                "fill32: list elements must be numbers.",
+                            ));
                         }
                     }
-                    _ => return Err("fill32: third arg must be a list."),
+                    _ => return Err(Error::type_error("fill32: third arg must be a list.")),
                 }
             }
             Ok(Value::Nil)
@@ -526,38 +569,44 @@ pub fn execute_syscall(
             let raw_addr = if let Value::Number(n) = &ptr_val {
                 let a = n
                     .as_addr()
-                    .map_err(|_| "free32: first argument must be an address.")?;
+                    .map_err(|_| Error::type_error("free32: first argument must be an address."))?;
                 if let super::number::Number::Addr(a) = a {
                     a
                 } else {
                     unreachable!()
                 }
             } else {
-                return Err("free32: first argument must be an address.");
+                return Err(Error::type_error(
+                    "free32: first argument must be an address.",
+                ));
             };
             let count = if let Value::Number(n) = &len_val {
-                let c = n
-                    .as_i32()
-                    .map_err(|_| "free32: second argument must be an integer.")?;
+                let c = n.as_i32().map_err(|_| {
+                    Error::type_error("free32: second argument must be an integer.")
+                })?;
                 if c < 0 {
-                    return Err("free32: length must be non-negative.");
+                    return Err(Error::type_error("free32: length must be non-negative."));
                 }
                 c as usize
             } else {
-                return Err("free32 requires a number as second argument.");
+                return Err(Error::type_error(
+                    "free32 requires a number as second argument.",
+                ));
             };
             let align = if sexp.nth_exists(3) {
                 let a_val = evaluate(sexp.nth(3), image)?;
                 if let Value::Number(n) = &a_val {
                     let a = n
                         .as_i32()
-                        .map_err(|_| "free32: alignment must be an integer.")?;
+                        .map_err(|_| Error::type_error("free32: alignment must be an integer."))?;
                     if a < 4 || (a as u32) & (a as u32 - 1) != 0 {
-                        return Err("free32: alignment must be a power of 2 >= 4.");
+                        return Err(Error::memory(
+                            "free32: alignment must be a power of 2 >= 4.",
+                        ));
                     }
                     a as usize
                 } else {
-                    return Err("free32: alignment must be a number.");
+                    return Err(Error::type_error("free32: alignment must be a number."));
                 }
             } else {
                 4
@@ -566,8 +615,8 @@ pub fn execute_syscall(
             if size == 0 {
                 return Ok(Value::Nil);
             }
-            let layout =
-                Layout::from_size_align(size, align).map_err(|_| "free32: invalid layout.")?;
+            let layout = Layout::from_size_align(size, align)
+                .map_err(|_| Error::memory("free32: invalid layout."))?;
             unsafe { alloc::alloc::dealloc(raw_addr as *mut u8, layout) };
             Ok(Value::Nil)
         }
@@ -582,31 +631,34 @@ pub fn execute_syscall(
             let base = if let Value::Number(n) = &addr_val {
                 let a = n
                     .as_addr()
-                    .map_err(|_| "full32: first arg must be an address.")?;
+                    .map_err(|_| Error::type_error("full32: first arg must be an address."))?;
                 if let super::number::Number::Addr(a) = a {
                     a
                 } else {
                     unreachable!()
                 }
             } else {
-                return Err("full32: first arg must be an address.");
+                return Err(Error::type_error("full32: first arg must be an address."));
             };
             let offset = if let Value::Number(n) = &off_val {
                 n.as_i32()
-                    .map_err(|_| "full32: offset must be an integer.")? as usize
+                    .map_err(|_| Error::type_error("full32: offset must be an integer."))?
+                    as usize
             } else {
-                return Err("full32: offset must be a number.");
+                return Err(Error::type_error("full32: offset must be a number."));
             };
             let count = if let Value::Number(n) = &n_val {
                 n.as_i32()
-                    .map_err(|_| "full32: count must be an integer.")? as usize
+                    .map_err(|_| Error::type_error("full32: count must be an integer."))?
+                    as usize
             } else {
-                return Err("full32: count must be a number.");
+                return Err(Error::type_error("full32: count must be a number."));
             };
             let val = if let Value::Number(n) = &val_val {
-                n.as_u32().map_err(|_| "full32: value must be a u32.")?
+                n.as_u32()
+                    .map_err(|_| Error::type_error("full32: value must be a u32."))?
             } else {
-                return Err("full32: value must be a number.");
+                return Err(Error::type_error("full32: value must be a number."));
             };
             let dst = (base + offset * 4) as *mut u32;
             let slice = unsafe { core::slice::from_raw_parts_mut(dst, count) };
@@ -622,9 +674,9 @@ pub fn execute_syscall(
             let n_val = evaluate(sexp.nth(3), image)?;
             let base = match &addr_val {
                 Value::Number(n) => {
-                    let a = n
-                        .as_addr()
-                        .map_err(|_| "ldr: first arg must be an address or array.")?;
+                    let a = n.as_addr().map_err(|_| {
+                        Error::type_error("ldr: first arg must be an address or array.")
+                    })?;
                     if let super::number::Number::Addr(a) = a {
                         a
                     } else {
@@ -632,17 +684,25 @@ pub fn execute_syscall(
                     }
                 }
                 Value::Array(a) => a.borrow().as_ptr() as usize,
-                _ => return Err("ldr: first arg must be an address or array."),
+                v => {
+                    return Err(
+                        Error::type_error("ldr: first arg must be an address or array.").with(v),
+                    );
+                }
             };
             let offset = if let Value::Number(n) = &off_val {
-                n.as_i32().map_err(|_| "ldr: offset must be an integer.")? as usize
+                n.as_i32()
+                    .map_err(|_| Error::type_error("ldr: offset must be an integer."))?
+                    as usize
             } else {
-                return Err("ldr: offset must be a number.");
+                return Err(Error::type_error("ldr: offset must be a number."));
             };
             let count = if let Value::Number(n) = &n_val {
-                n.as_i32().map_err(|_| "ldr: count must be an integer.")? as usize
+                n.as_i32()
+                    .map_err(|_| Error::type_error("ldr: count must be an integer."))?
+                    as usize
             } else {
-                return Err("ldr: count must be a number.");
+                return Err(Error::type_error("ldr: count must be a number."));
             };
             let src = (base + offset * 4) as *const u8;
             let mut v = alloc::vec![0u32; count];
@@ -658,9 +718,9 @@ pub fn execute_syscall(
             let arr_val = evaluate(sexp.nth(3), image)?;
             let base = match &addr_val {
                 Value::Number(n) => {
-                    let a = n
-                        .as_addr()
-                        .map_err(|_| "str: first arg must be an address or array.")?;
+                    let a = n.as_addr().map_err(|_| {
+                        Error::type_error("str: first arg must be an address or array.")
+                    })?;
                     if let super::number::Number::Addr(a) = a {
                         a
                     } else {
@@ -668,12 +728,18 @@ pub fn execute_syscall(
                     }
                 }
                 Value::Array(a) => a.borrow().as_ptr() as usize,
-                _ => return Err("str: first arg must be an address or array."),
+                v => {
+                    return Err(
+                        Error::type_error("str: first arg must be an address or array.").with(v),
+                    );
+                }
             };
             let offset = if let Value::Number(n) = &off_val {
-                n.as_i32().map_err(|_| "str: offset must be an integer.")? as usize
+                n.as_i32()
+                    .map_err(|_| Error::type_error("str: offset must be an integer."))?
+                    as usize
             } else {
-                return Err("str: offset must be a number.");
+                return Err(Error::type_error("str: offset must be a number."));
             };
             if let Value::Array(a) = &arr_val {
                 let borrowed = a.borrow();
@@ -683,7 +749,7 @@ pub fn execute_syscall(
                 }
                 Ok(Value::Nil)
             } else {
-                Err("str: third arg must be an array.")
+                Err(Error::type_error("str: third arg must be an array."))
             }
         }
 
@@ -698,7 +764,7 @@ pub fn execute_syscall(
                 let src_b = src.borrow();
                 let mut dst_b = dst.borrow_mut();
                 if dst_b.len() < src_b.len() * 16 {
-                    return Err("unpack1to16: dst array too small.");
+                    return Err(Error::bounds("unpack1to16: dst array too small."));
                 }
                 // Reinterpret src as bytes to avoid endian issues
                 let src_bytes: &[u8] = unsafe {
@@ -720,7 +786,9 @@ pub fn execute_syscall(
                 }
                 Ok(Value::Nil)
             } else {
-                Err("unpack1to16: both arguments must be arrays.")
+                Err(Error::type_error(
+                    "unpack1to16: both arguments must be arrays.",
+                ))
             }
         }
     }
@@ -18,8 +18,125 @@ extern crate alloc;
 
 use alloc::format;
 use alloc::string::String;
-use comm::uart::PiUart;
-use shared::Framer;
+use comm::link;
+use comm::uart::{self, PiUart};
+use core::time::Duration;
+use shared::control::feature;
+use shared::{Control, Framer, Hello, Message, MessageKind};
+use utils::timer;
+
+/// What this kernel tells the unix side about itself at connect time.
+fn kernel_hello() -> Hello {
+    let mut features = feature::JIT | feature::COMPRESSION;
+    if cfg!(feature = "bench_jit") {
+        features |= feature::BENCH_JIT;
+    }
+    Hello {
+        version: shared::PROTOCOL_VERSION,
+        max_payload: shared::MAX_PAYLOAD_LEN,
+        baud_rates: uart::SUPPORTED_BAUD_RATES.to_vec(),
+        build_id: env!("LISPI_BUILD_ID").into(),
+        features,
+    }
+}
+
+/// Parse and evaluate one request, producing a result or error reply
+/// that echoes the request's id.
+fn eval_request(msg: &Message, img: &mut language::Image) -> Message {
+    let reply = |kind, text: String| Message {
+        kind,
+        id: msg.id,
+        body: text.into_bytes(),
+    };
+    let Some(src) = msg.text() else {
+        return reply(MessageKind::EvalError, "received non-UTF8 source".into());
+    };
+    comm::interrupt::clear();
+    let depth = img.depth();
+    let source = language::source::Source::new(None, src);
+    match language::parse(&source, 0, img.spans_mut()) {
+        Ok((expr, _)) => match language::evaluate(expr.into(), img) {
+            Ok(result) => reply(MessageKind::EvalResult, format!("{}", result)),
+            Err(e) => {
+                // errors propagate without popping the frames of the
+                // calls they unwind through
+                img.unwind(depth);
+                reply(MessageKind::EvalError, format!("{}", e))
+            }
+        },
+        Err(e) => reply(MessageKind::EvalError, format!("parse error: {}", e)),
+    }
+}
+
+/// Everything a symbol could name right now, for completion on the unix
+/// side.
+fn names(img: &language::Image) -> Control {
+    use language::special::Special;
+    use language::syscalls::Syscall;
+    Control::Names {
+        globals: img.global_names(),
+        specials: Special::NAMES.iter().map(|(n, _)| (*n).into()).collect(),
+        syscalls: Syscall::NAMES.iter().map(|(n, _)| (*n).into()).collect(),
+    }
+}
+
+/// Handle a `Control::SetBaud` request: confirm the rate we'll use at the
+/// current rate, switch, then wait for the unix side's `Control::Ping` at
+/// the new rate. Without one before `BAUD_VERIFY_TIMEOUT_MS` the link is
+/// presumed broken and we go back to the old rate.
+fn switch_baud(framer: &mut Framer<PiUart>, wanted: u32) {
+    let old = uart::current_baud();
+    let rate = if uart::SUPPORTED_BAUD_RATES.contains(&wanted) {
+        wanted
+    } else {
+        old
+    };
+    let confirm = Message::control(&Control::SetBaud(rate));
+    if framer.send_message(&confirm).is_err() || rate == old {
+        return;
+    }
+    uart::init_baud(rate);
+
+    let deadline = Duration::from_millis(shared::BAUD_VERIFY_TIMEOUT_MS);
+    let start = timer::now_us();
+    loop {
+        let elapsed = Duration::from_micros(timer::now_us().wrapping_sub(start) as u64);
+        let Some(left) = deadline.checked_sub(elapsed) else {
+            break;
+        };
+        // the frame-level ack the framer sends for the ping is what the
+        // unix side is waiting for; anything else here is line noise
+        if let Ok(msg) = framer.recv_message_timeout(left)
+            && msg.kind == MessageKind::Control
+            && Control::decode(&msg.body) == Some(Control::Ping)
+        {
+            return;
+        }
+    }
+    uart::init_baud(old);
+}
+
+/// How long each `Control::Boot` announcement waits for its ack. Short,
+/// since most rates will find nobody listening.
+const BOOT_ACK_TIMEOUT_MS: u64 = 50;
+
+/// Tell a unix side that may still be attached from before a reboot that
+/// the Image is gone (see `shared::control`). Tries every rate, fastest
+/// first, and stays at the first that gets acked; with no answer at all
+/// the link is left at `BAUD_RATE` for a fresh connection.
+fn announce_boot(framer: &mut Framer<PiUart>) {
+    let ack_timeout = framer.ack_timeout;
+    framer.ack_timeout = Duration::from_millis(BOOT_ACK_TIMEOUT_MS);
+    let boot = Message::control(&Control::Boot);
+    let acked = uart::SUPPORTED_BAUD_RATES
+        .iter()
+        .rev()
+        .any(|&rate| uart::init_baud(rate) && framer.send_message(&boot).is_ok());
+    if !acked {
+        uart::init_baud(shared::BAUD_RATE);
+    }
+    framer.ack_timeout = ack_timeout;
+}
 
 fn main() {
     comm::uart::init();
@@ -30,34 +147,67 @@ fn main() {
     comm::uart::flush();
 
     let mut img = language::Image::new();
-    let mut framer = Framer::pi_side(PiUart);
-
-    let _ = framer.recv();
-    framer.send("PI_READY".as_bytes());
+    link::with(|framer| {
+        // boot timing varies enough to tell this run's frames from the last
+        framer.seed_seq(timer::now_us());
+        announce_boot(framer);
+    });
+    let mut uploads = comm::transfer::Uploads::default();
 
     loop {
-        let payload_str = match String::from_utf8(framer.recv()) {
-            Ok(s) => s,
-            Err(_) => {
-                framer.send("pi-side: received non-UTF8 message\n".as_bytes());
-                continue;
-            }
+        // a corrupted or truncated frame has already been NACKed;
+        // just wait for the retransmission
+        let msg = match link::with(|framer| framer.recv_message()) {
+            Ok(m) => m,
+            Err(_) => continue,
         };
-        match language::parse(&payload_str) {
-            Ok(expr) => match language::evaluate(expr.into(), &mut img) {
-                Ok(result) => {
-                    let response = format!("{}", result);
-                    framer.send(response.as_bytes());
+        match msg.kind {
+            MessageKind::EvalRequest => {
+                let reply = eval_request(&msg, &mut img);
+                let _ = link::with(|framer| framer.send_message(&reply));
+            }
+            // the unix side (re)connected; the Image survives across
+            // unix-side restarts. Always answer, even if incompatible, so
+            // the unix side can explain the mismatch.
+            MessageKind::Control => link::with(|framer| match Control::decode(&msg.body) {
+                Some(Control::Hello(peer)) => {
+                    let ours = kernel_hello();
+                    let compatible = ours.check_compatible(&peer).is_ok();
+                    if compatible {
+                        framer.max_payload = ours.max_payload.min(peer.max_payload);
+                        framer.compress = peer.has_feature(feature::COMPRESSION);
+                    }
+                    let _ = framer.send_message(&Message::control(&Control::Hello(ours)));
+                    // from here on, raw output would be lost among the frames
+                    comm::print::set_framed(compatible);
                 }
-                Err(e) => {
-                    let response = format!("{}", e);
-                    framer.send(response.as_bytes());
+                Some(Control::SetBaud(rate)) => switch_baud(framer, rate),
+                // a ping that outlived its baud switch
+                Some(Control::Ping) => {}
+                Some(Control::Upload { addr, len }) => uploads.begin(framer, addr, len),
+                Some(Control::QueryTransfer) => uploads.query(framer),
+                Some(Control::Download { addr, len, offset }) => {
+                    comm::transfer::download(framer, addr, len, offset)
                 }
-            },
-            Err(e) => {
-                let response = format!("PARSE ERROR: {}", e);
-                framer.send(response.as_bytes());
-            }
+                Some(Control::QueryNames) => {
+                    let _ = framer.send_message(&Message::control(&names(&img)));
+                }
+                // an interrupt that arrived after its evaluation finished
+                Some(Control::Interrupt) => {}
+                // only the Pi sends these, or they answer a request of
+                // ours that has given up
+                Some(
+                    Control::Transfer(_)
+                    | Control::Boot
+                    | Control::Names { .. }
+                    | Control::HostRead { .. }
+                    | Control::HostWrite { .. }
+                    | Control::HostReply(_),
+                ) => {}
+                None => {}
+            }),
+            MessageKind::Blob => uploads.chunk(msg),
+            _ => {}
         }
     }
 }
@@ -27,10 +27,12 @@ struct Context {
     r10: u32,
     r11: u32,
     r12: u32,
-    sp: u32,   // user's sp; 13
-    lr: u32,   // user's lr; 14
-    pc: u32,   // exception's model's pc
+    sp: u32,        // user's sp; 13
+    lr: u32,        // user's lr; 14
+    pc: u32,        // exception's model's pc
     spsr: u32, // exception's model's cpsr, which we will restore into the user's cpsr when we rfe to the user
+    fpscr: u32, // the VFP's status and control register; the VFP is on from boot
+    vfp: [u32; 32], // d0-d15; words rather than u64s so the frame stays 4-aligned
 }
 impl Default for Context {
     fn default() -> Self {
@@ -52,6 +54,8 @@ impl Default for Context {
             lr: 0,
             pc: 0,
             spsr: PSR::from(0).with_mode(Mode::User).into(),
+            fpscr: 3 << 24, // RunFast, as boot sets it up
+            vfp: [0; 32],
         }
     }
 }
@@ -82,12 +86,17 @@ static THREADING_STACK_PTR: [u32; 1] = [0u32; 1]; //  pointer to stack of the sy
 
 /// ASSUMES: SUPER mode
 /// get a pointer into the regs array, and:
-/// 1. restore all registers up to lr
-/// 2. rfe to the pc and cpsr
+/// 1. restore the VFP registers and fpscr
+/// 2. restore all registers up to lr
+/// 3. rfe to the pc and cpsr
 #[unsafe(naked)]
 extern "C" fn thread_dispatch_asm(regs: &Context) {
     core::arch::naked_asm!(
         "mov lr, r0", // load the address of the regs array into lr, which we can trash since we are in execption
+        "ldr r1, [lr, #68]", // the thread's fpscr
+        "vmsr fpscr, r1",
+        "add r1, lr, #72", // and its d0-d15, which follow it
+        "vldmia r1, {{d0-d15}}",
         "ldm lr, {{r0-r14}}^", // restore all registers up to lr into user mode!
         "add lr, lr, #60", // set offset to the pc and cpsr, which are the last two elements of the regs array
         "rfe lr",          // and then rfe to the pc and cpsr, which will jump to the new thread
@@ -161,11 +170,15 @@ extern "C" fn thread_context_switch_do(current_context: &'static Context) {
 pub extern "C" fn thread_context_switch() {
     core::arch::naked_asm!(
         // this move without cleanup is ok because syscalls reset the stack
-        "sub sp, sp, #68", // make space for the context on the stack; 17 registers * 4 bytes each = 68 bytes
+        "sub sp, sp, #200", // make space for the context on the stack; 18 registers * 4 bytes + 16 doubles * 8 bytes = 200 bytes
         "stmia sp, {{r0-r14}}^",
         "str lr, [sp, #60]", // store the USER pc at the end of the context; which is the lr in exception mode
         "mrs r1, spsr", // store the user's cpsr at the end of the context
         "str r1, [sp, #64]", // store the USER cpsr at the end of the context
+        "vmrs r1, fpscr", // then the VFP state, which the user may have been in the middle of using
+        "str r1, [sp, #68]",
+        "add r1, sp, #72",
+        "vstmia r1, {{d0-d15}}",
         "mov r0, sp", // return value of sp
         "b {handler}", // call the context switch handler
         handler = sym thread_context_switch_do
@@ -186,6 +199,7 @@ pub extern "C" fn thread_done() {
             core::arch::asm!(
                 "ldr r0, ={stack}", // restore the sp of the system thread, which we saved in join
                 "ldr sp, [r0]", // restore the sp of the system thread, which we saved in join
+                "vpop {{d8-d15}}", // pop the callee saved VFP registers, pushed last in join
                 "pop {{r1, r4-r11, lr}}", // pop the callee saved registers, which are the registers of the system thread
                 "msr cpsr, r1", // restore the cpsr of the system thread
                 "bx lr", // restore the cpsr of the system thread
@@ -209,6 +223,7 @@ pub extern "C" fn thread_join() {
     core::arch::naked_asm!(
         "mrs r1, cpsr", // store the caller's cpsr in r1, which we will restore in the handler
         "push {{r1, r4-r11, lr}}", // push callee saved registers to the stack, which we will restore in the handler
+        "vpush {{d8-d15}}", // and the callee saved VFP registers, which the hard-float ABI has too
         "ldr r0, ={stack}", // save the system thread's sp to the global static stack, which we will restore in the handler
         "str sp, [r0]", // save the system thread's sp to the global static stack, which we will restore in the handler
         "b {handler}", // call the join handler, which will save the system thread's context and dispatch to the first thread
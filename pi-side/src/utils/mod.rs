@@ -5,4 +5,5 @@ pub mod memory;
 pub mod exceptions;
 pub mod panic;
 pub mod psr;
+pub mod timer;
 pub mod watchdog;
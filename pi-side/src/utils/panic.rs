@@ -1,7 +1,16 @@
+use core::sync::atomic::{AtomicBool, Ordering};
+
 use crate::println;
 
+static PANICKING: AtomicBool = AtomicBool::new(false);
+
 #[panic_handler]
 fn panic(info: &core::panic::PanicInfo) -> ! {
+    // framing the message allocates and sends; if that is what panicked,
+    // fall back to the bare UART
+    if PANICKING.swap(true, Ordering::Relaxed) {
+        crate::comm::print::set_framed(false);
+    }
     if let Some(loc) = info.location() {
         println!(
             "Panic occurred at file '{}' line {}:\n",
@@ -11,10 +20,7 @@ fn panic(info: &core::panic::PanicInfo) -> ! {
     } else {
         println!("Panic occurred at unknown location.\n");
     }
-    let msg = info.message();
-    use ::core::fmt::Write as _;
-    let _ = ::core::writeln!(crate::comm::print::UartProxy, "{}\n", msg);
-    crate::comm::uart::flush();
+    println!("{}\n", info.message());
     crate::utils::memory::dsb();
     crate::utils::watchdog::restart();
 }
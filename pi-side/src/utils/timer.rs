@@ -0,0 +1,12 @@
+//! BCM2835 free-running system timer.
+
+use crate::utils::memory::get32;
+
+const SYSTIMER_BASE: usize = 0x2000_3000;
+const SYSTIMER_CLO: usize = SYSTIMER_BASE + 0x04;
+
+/// Low 32 bits of the 1 MHz system timer: microseconds since boot,
+/// wrapping every ~71 minutes. Compare with `wrapping_sub`.
+pub fn now_us() -> u32 {
+    unsafe { get32(SYSTIMER_CLO) }
+}
@@ -13,5 +13,7 @@ bin_path="${target_dir}/${base_name/.elf/}.bin"
 
 arm-none-eabi-objcopy "$elf_path" -O binary "$bin_path"
 echo "Created $bin_path from $elf_path"
-# RUST_LOG=info okdude -l $load_addr "$bin_path"
-my-install --baud 230400 "$bin_path"
+# upload through the bootloader and stay connected; run from the
+# workspace root so unix-side is built for the host
+bin_path=$(realpath "$bin_path")
+cd "$(dirname "$0")/.." && exec cargo run -q -p unix-side -- boot "$bin_path" "${@:2}"
@@ -0,0 +1,421 @@
+//! Control messages: link management traffic carried in
+//! `MessageKind::Control` bodies.
+//!
+//! ```text
+//! [op: u32]             control operation (see `Control`)
+//! [args...]             op-specific, little-endian u32s and
+//!                       length-prefixed byte strings
+//! ```
+//!
+//! # Handshake
+//!
+//! The unix side opens every session with `Control::Hello` describing
+//! itself; the Pi answers with its own `Hello`. Each side then checks the
+//! other with `Hello::check_compatible` and the unix side refuses to talk
+//! to a kernel speaking a different protocol version. Both sides clamp
+//! their framer's `max_payload` to the smaller of the two advertised
+//! limits, and turn on `Framer::compress` if the peer advertised
+//! `feature::COMPRESSION`.
+//!
+//! # Baud-rate switch
+//!
+//! After the handshake the unix side may pick the fastest rate both
+//! `Hello`s list and send `Control::SetBaud(rate)`. The Pi echoes
+//! `SetBaud` with the rate it will actually use (its current rate if it
+//! can't do the requested one), then re-initializes its UART. The unix
+//! side waits for its transmitter to drain, switches its tty, and sends
+//! `Control::Ping` at the new rate. If the Pi doesn't receive that ping
+//! within `BAUD_VERIFY_TIMEOUT_MS` it reverts to the old rate; if the
+//! unix side doesn't get the ping acked it waits out that deadline and
+//! reverts too.
+//!
+//! # Reboots
+//!
+//! A kernel that has just booted sends `Control::Boot` before anything
+//! else, in case a unix side is still attached from before. It can't know
+//! what rate that unix side was at, so it tries each rate it supports,
+//! fastest first, with a short ack timeout, and stays at the first one
+//! that gets acked (or at `BAUD_RATE` if none does). The unix side
+//! answers a `Boot` with a fresh handshake; until then the kernel runs
+//! with the defaults.
+//!
+//! # Interrupts
+//!
+//! While an evaluation runs, the unix side may send `Control::Interrupt`
+//! (on Ctrl-C). Nothing else is sent to the Pi then, so the interpreter
+//! polls the UART as it goes and, once the interrupt arrives, abandons
+//! the evaluation: the request is answered with an `EvalError` reading
+//! `interrupted`, and the scopes it had open are dropped, leaving the
+//! global bindings (including any it had already changed). The frame is
+//! only acked once the interpreter looks, so a kernel stuck outside it
+//! never acks.
+//!
+//! # Completion
+//!
+//! Between requests the unix side may send `Control::QueryNames`; the Pi
+//! answers with `Control::Names`, listing everything a symbol could
+//! currently name: the global bindings, the special forms and the
+//! syscalls (without their `@`).
+//!
+//! # Host files
+//!
+//! An evaluation may send `Control::HostRead` or `Control::HostWrite` and
+//! wait for `Control::HostReply`; see `host`.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
+
+use crate::host::HostError;
+use crate::transfer::Progress;
+
+/// Bumped whenever the framing or message layout changes incompatibly.
+pub const PROTOCOL_VERSION: u32 = 1;
+
+/// Optional capabilities advertised in `Hello::features`.
+pub mod feature {
+    /// The kernel has the JIT compiler (`jit`, `jitexec`, `ir*` specials).
+    pub const JIT: u32 = 1 << 0;
+    /// The kernel was built with the `bench_jit` feature.
+    pub const BENCH_JIT: u32 = 1 << 1;
+    /// This side can receive compressed frames (see `Framer::compress`).
+    pub const COMPRESSION: u32 = 1 << 2;
+
+    /// Name/bit pairs for printing feature sets.
+    pub const NAMES: &[(u32, &str)] = &[
+        (JIT, "jit"),
+        (BENCH_JIT, "bench_jit"),
+        (COMPRESSION, "compression"),
+    ];
+}
+
+/// Capability advertisement exchanged at connect time.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Hello {
+    pub version: u32,
+    /// Largest frame payload this side will accept.
+    pub max_payload: u32,
+    /// Baud rates this side can switch to.
+    pub baud_rates: Vec<u32>,
+    /// Free-form build identifier (git revision, build time, ...).
+    pub build_id: String,
+    /// Bitset of `feature` flags.
+    pub features: u32,
+}
+
+/// Why a peer's `Hello` was rejected.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Incompatible {
+    Version { ours: u32, theirs: u32 },
+}
+
+impl fmt::Display for Incompatible {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Incompatible::Version { ours, theirs } => write!(
+                f,
+                "protocol version mismatch: we speak v{}, peer speaks v{}",
+                ours, theirs
+            ),
+        }
+    }
+}
+
+impl Hello {
+    /// Check that we can talk to `peer`.
+    pub fn check_compatible(&self, peer: &Hello) -> Result<(), Incompatible> {
+        if self.version != peer.version {
+            return Err(Incompatible::Version {
+                ours: self.version,
+                theirs: peer.version,
+            });
+        }
+        Ok(())
+    }
+
+    pub fn has_feature(&self, f: u32) -> bool {
+        self.features & f == f
+    }
+
+    /// Display adapter listing the enabled feature names.
+    pub fn feature_names(&self) -> FeatureNames {
+        FeatureNames(self.features)
+    }
+}
+
+pub struct FeatureNames(u32);
+
+impl fmt::Display for FeatureNames {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut first = true;
+        for &(bit, name) in feature::NAMES {
+            if self.0 & bit != 0 {
+                if !first {
+                    write!(f, ", ")?;
+                }
+                write!(f, "{}", name)?;
+                first = false;
+            }
+        }
+        if first {
+            write!(f, "none")?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Control {
+    Hello(Hello),
+    /// Request (unix -> pi) or confirm (pi -> unix) a baud-rate switch.
+    SetBaud(u32),
+    /// No-op used to verify the link after a baud-rate switch; the
+    /// frame-level ack is the answer.
+    Ping,
+    /// Start (or resume) an upload of `len` bytes to `addr`; `addr == 0`
+    /// asks the Pi to allocate. See `transfer`.
+    Upload {
+        addr: u32,
+        len: u32,
+    },
+    /// Ask the Pi to stream `addr + offset .. addr + len`.
+    Download {
+        addr: u32,
+        len: u32,
+        offset: u32,
+    },
+    /// Ask the Pi how far the current upload got.
+    QueryTransfer,
+    /// The Pi's view of the current transfer.
+    Transfer(Progress),
+    /// The kernel (pi -> unix) has just booted with an empty Image.
+    Boot,
+    /// Stop (unix -> pi) the evaluation in progress.
+    Interrupt,
+    /// Ask the Pi which names are defined.
+    QueryNames,
+    /// The names (pi -> unix) a symbol can currently refer to.
+    Names {
+        globals: Vec<String>,
+        specials: Vec<String>,
+        syscalls: Vec<String>,
+    },
+    /// Ask (pi -> unix) for the contents of a host file.
+    HostRead {
+        path: String,
+    },
+    /// Replace, or with `append` extend, (pi -> unix) a host file.
+    HostWrite {
+        path: String,
+        data: Vec<u8>,
+        append: bool,
+    },
+    /// The host's answer (unix -> pi) to a `HostRead` (the file) or
+    /// `HostWrite` (empty).
+    HostReply(Result<Vec<u8>, HostError>),
+}
+
+const OP_HELLO: u32 = 1;
+const OP_SET_BAUD: u32 = 2;
+const OP_PING: u32 = 3;
+const OP_UPLOAD: u32 = 4;
+const OP_DOWNLOAD: u32 = 5;
+const OP_QUERY_TRANSFER: u32 = 6;
+const OP_TRANSFER: u32 = 7;
+const OP_BOOT: u32 = 8;
+const OP_INTERRUPT: u32 = 9;
+const OP_QUERY_NAMES: u32 = 10;
+const OP_NAMES: u32 = 11;
+const OP_HOST_READ: u32 = 12;
+const OP_HOST_WRITE: u32 = 13;
+const OP_HOST_REPLY: u32 = 14;
+
+impl Control {
+    pub fn encode(&self) -> Vec<u8> {
+        let mut w = Writer(Vec::new());
+        match self {
+            Control::Hello(h) => {
+                w.u32(OP_HELLO);
+                w.u32(h.version);
+                w.u32(h.max_payload);
+                w.u32(h.baud_rates.len() as u32);
+                for &b in &h.baud_rates {
+                    w.u32(b);
+                }
+                w.bytes(h.build_id.as_bytes());
+                w.u32(h.features);
+            }
+            Control::SetBaud(rate) => {
+                w.u32(OP_SET_BAUD);
+                w.u32(*rate);
+            }
+            Control::Ping => w.u32(OP_PING),
+            Control::Upload { addr, len } => {
+                w.u32(OP_UPLOAD);
+                w.u32(*addr);
+                w.u32(*len);
+            }
+            Control::Download { addr, len, offset } => {
+                w.u32(OP_DOWNLOAD);
+                w.u32(*addr);
+                w.u32(*len);
+                w.u32(*offset);
+            }
+            Control::QueryTransfer => w.u32(OP_QUERY_TRANSFER),
+            Control::Transfer(p) => {
+                w.u32(OP_TRANSFER);
+                w.u32(p.addr);
+                w.u32(p.len);
+                w.u32(p.offset);
+            }
+            Control::Boot => w.u32(OP_BOOT),
+            Control::Interrupt => w.u32(OP_INTERRUPT),
+            Control::QueryNames => w.u32(OP_QUERY_NAMES),
+            Control::Names {
+                globals,
+                specials,
+                syscalls,
+            } => {
+                w.u32(OP_NAMES);
+                w.strings(globals);
+                w.strings(specials);
+                w.strings(syscalls);
+            }
+            Control::HostRead { path } => {
+                w.u32(OP_HOST_READ);
+                w.bytes(path.as_bytes());
+            }
+            Control::HostWrite { path, data, append } => {
+                w.u32(OP_HOST_WRITE);
+                w.bytes(path.as_bytes());
+                w.u32(*append as u32);
+                w.bytes(data);
+            }
+            Control::HostReply(reply) => {
+                w.u32(OP_HOST_REPLY);
+                match reply {
+                    Ok(data) => {
+                        w.u32(0);
+                        w.bytes(data);
+                    }
+                    Err(e) => w.u32(*e as u32),
+                }
+            }
+        }
+        w.0
+    }
+
+    /// Parse a control body. Returns None if it is truncated or names an
+    /// unknown op.
+    pub fn decode(body: &[u8]) -> Option<Self> {
+        let mut r = Reader(body);
+        match r.u32()? {
+            OP_HELLO => {
+                let version = r.u32()?;
+                let max_payload = r.u32()?;
+                let n = r.u32()? as usize;
+                let mut baud_rates = Vec::new();
+                for _ in 0..n {
+                    baud_rates.push(r.u32()?);
+                }
+                let build_id = String::from_utf8(r.bytes()?.to_vec()).ok()?;
+                let features = r.u32()?;
+                Some(Control::Hello(Hello {
+                    version,
+                    max_payload,
+                    baud_rates,
+                    build_id,
+                    features,
+                }))
+            }
+            OP_SET_BAUD => Some(Control::SetBaud(r.u32()?)),
+            OP_PING => Some(Control::Ping),
+            OP_UPLOAD => Some(Control::Upload {
+                addr: r.u32()?,
+                len: r.u32()?,
+            }),
+            OP_DOWNLOAD => Some(Control::Download {
+                addr: r.u32()?,
+                len: r.u32()?,
+                offset: r.u32()?,
+            }),
+            OP_QUERY_TRANSFER => Some(Control::QueryTransfer),
+            OP_TRANSFER => Some(Control::Transfer(Progress {
+                addr: r.u32()?,
+                len: r.u32()?,
+                offset: r.u32()?,
+            })),
+            OP_BOOT => Some(Control::Boot),
+            OP_INTERRUPT => Some(Control::Interrupt),
+            OP_QUERY_NAMES => Some(Control::QueryNames),
+            OP_NAMES => Some(Control::Names {
+                globals: r.strings()?,
+                specials: r.strings()?,
+                syscalls: r.strings()?,
+            }),
+            OP_HOST_READ => Some(Control::HostRead {
+                path: String::from_utf8(r.bytes()?.to_vec()).ok()?,
+            }),
+            OP_HOST_WRITE => Some(Control::HostWrite {
+                path: String::from_utf8(r.bytes()?.to_vec()).ok()?,
+                append: r.u32()? != 0,
+                data: r.bytes()?.to_vec(),
+            }),
+            OP_HOST_REPLY => Some(Control::HostReply(match r.u32()? {
+                0 => Ok(r.bytes()?.to_vec()),
+                e => Err(HostError::from_u32(e)?),
+            })),
+            _ => None,
+        }
+    }
+}
+
+struct Writer(Vec<u8>);
+
+impl Writer {
+    fn u32(&mut self, v: u32) {
+        self.0.extend_from_slice(&v.to_le_bytes());
+    }
+
+    fn bytes(&mut self, b: &[u8]) {
+        self.u32(b.len() as u32);
+        self.0.extend_from_slice(b);
+    }
+
+    fn strings(&mut self, v: &[String]) {
+        self.u32(v.len() as u32);
+        for s in v {
+            self.bytes(s.as_bytes());
+        }
+    }
+}
+
+struct Reader<'a>(&'a [u8]);
+
+impl<'a> Reader<'a> {
+    fn u32(&mut self) -> Option<u32> {
+        let (head, rest) = self.0.split_at_checked(4)?;
+        self.0 = rest;
+        Some(u32::from_le_bytes(head.try_into().unwrap()))
+    }
+
+    fn bytes(&mut self) -> Option<&'a [u8]> {
+        let n = self.u32()? as usize;
+        let (head, rest) = self.0.split_at_checked(n)?;
+        self.0 = rest;
+        Some(head)
+    }
+
+    fn strings(&mut self) -> Option<Vec<String>> {
+        let n = self.u32()? as usize;
+        // every string takes at least its length word
+        if n > self.0.len() / 4 {
+            return None;
+        }
+        let mut v = Vec::with_capacity(n);
+        for _ in 0..n {
+            v.push(String::from_utf8(self.bytes()?.to_vec()).ok()?);
+        }
+        Some(v)
+    }
+}
@@ -0,0 +1,61 @@
+//! Files on the host, for the kernel (the `@host/...` syscalls).
+//!
+//! The Pi has no storage of its own, so the unix side lends it a
+//! directory. While an evaluation runs, the kernel may send
+//!
+//! - `Control::HostRead { path }` for a file's contents, or
+//! - `Control::HostWrite { path, data, append }` to replace or extend one,
+//!
+//! and waits for `Control::HostReply`: the contents (nothing, for a
+//! write) or a `HostError`. Paths are relative to that directory and may
+//! not leave it. A file travels in a single frame, so neither direction
+//! handles more than `max_file_len` bytes.
+
+use crate::message::MESSAGE_HEADER_LEN;
+
+/// Why the host didn't do what was asked.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HostError {
+    /// The unix side wasn't given a directory to serve.
+    NoDirectory = 1,
+    /// The path is absolute or climbs out of the directory.
+    Outside = 2,
+    NotFound = 3,
+    Denied = 4,
+    TooLarge = 5,
+    /// Any other failure.
+    Io = 6,
+}
+
+impl HostError {
+    pub fn from_u32(v: u32) -> Option<Self> {
+        match v {
+            1 => Some(Self::NoDirectory),
+            2 => Some(Self::Outside),
+            3 => Some(Self::NotFound),
+            4 => Some(Self::Denied),
+            5 => Some(Self::TooLarge),
+            6 => Some(Self::Io),
+            _ => None,
+        }
+    }
+
+    /// What the syscall fails with.
+    pub fn message(self) -> &'static str {
+        match self {
+            Self::NoDirectory => "host: no directory is shared (start unix-side with --host-dir).",
+            Self::Outside => "host: path is outside the shared directory.",
+            Self::NotFound => "host: no such file.",
+            Self::Denied => "host: permission denied.",
+            Self::TooLarge => "host: file is too large to send.",
+            Self::Io => "host: I/O error.",
+        }
+    }
+}
+
+/// Largest file that fits, with its control header, in a frame of
+/// `max_payload` bytes. A `HostWrite` also has to fit its path.
+pub fn max_file_len(max_payload: u32) -> usize {
+    // op, path length, append flag, data length
+    (max_payload as usize).saturating_sub(MESSAGE_HEADER_LEN + 16)
+}
@@ -12,8 +12,11 @@
 //! [DEADBEEF]*n          sync barrier (repeated; discarded)
 //! [00000000 00000000    frame header: four u32 zeros
 //!  00000000 00000000]
+//! [kind: u32]           frame kind (data / ack / nack) | flags (see below)
+//! [seq: u32]            sequence number of this data frame (or the one being acked)
 //! [n: u32]              payload length in bytes
 //! [payload: u8 * n]     payload data
+//! [crc: u32]            CRC-32 over kind, seq, n and payload
 //! [00000000 00000000    frame footer: four u32 zeros
 //!  00000000 00000000]
 //! [FACEFEED]*n          footer padding (repeated; other side should also discard)
@@ -26,17 +29,77 @@
 //! ```text
 //! [FACEFEED]*n          sync barrier
 //! [00000000 * 4]        frame header
+//! [kind: u32]           frame kind
+//! [seq: u32]            sequence number
 //! [n: u32]              payload length
 //! [payload: u8 * n]     payload data
+//! [crc: u32]            CRC-32
 //! [00000000 * 4]        frame footer
 //! [DEADBEEF]*n          footer padding
 //! ```
+//!
+//! Receivers locate a frame by scanning byte-wise for at least sixteen
+//! zero bytes followed by a non-zero byte (the low byte of `kind`), so a
+//! dropped or duplicated byte on the line never leaves them misaligned.
+//!
+//! ## Compression
+//!
+//! The low byte of the kind word is the `FrameKind`; the bits above it
+//! are flags. `FLAG_COMPRESSED` marks a data frame whose payload is an
+//! `lz` block preceded by the uncompressed length (`[n: u32][block]`);
+//! the CRC and length word cover the compressed bytes as sent. A framer
+//! always accepts compressed frames but only sends them once
+//! `Framer::compress` is set, which both sides do after the handshake if
+//! the peer advertised `feature::COMPRESSION`. Payloads shorter than
+//! `COMPRESS_THRESHOLD`, or that don't shrink, go out as they are.
+//!
+//! ## Acknowledgement
+//!
+//! Every data frame is answered by the receiver with an empty `Ack` frame
+//! carrying the same sequence number, or with a `Nack` frame if the CRC
+//! did not match. The sender retransmits on `Nack` (or on a corrupted
+//! reply) up to `MAX_RETRIES` times before giving up. Retransmissions
+//! reuse the sequence number, so a receiver that already accepted a frame
+//! re-acks the duplicate and drops it.
+//!
+//! ## Timeouts
+//!
+//! Waiting for the *start* of a frame may block indefinitely (the Pi sits
+//! idle between evaluations), but once a header has been seen every
+//! further byte must arrive within `Framer::timeout`. A peer that resets
+//! mid-frame therefore surfaces as `FrameError::Timeout` instead of a hang,
+//! and the next `recv` resynchronizes on the following sync barrier.
+//!
+//! ## Messages
+//!
+//! Data frame payloads carry a typed message (see `message`): a kind, a
+//! request id and a body. `send_message`/`recv_message` wrap the raw
+//! `send`/`recv`. Link management (the connect-time handshake and
+//! friends) travels as `MessageKind::Control` messages (see `control`).
+//! Bulk memory uploads and downloads are split into checksummed,
+//! resumable `MessageKind::Blob` chunks (see `transfer`). The kernel
+//! reads and writes files on the host through `host`.
 
 #![no_std]
 
 extern crate alloc;
+use alloc::collections::VecDeque;
 use alloc::vec::Vec;
+use core::time::Duration;
+
+pub mod control;
+pub mod crc;
+pub mod host;
+pub mod lz;
+pub mod message;
+pub mod transfer;
 
+pub use control::{Control, Hello, PROTOCOL_VERSION};
+use crc::Crc32;
+pub use message::{Message, MessageKind};
+
+/// Rate both sides start every session at. Faster rates are negotiated
+/// afterwards (see `control`).
 pub const BAUD_RATE: u32 = 230400;
 
 /// Number of sync words to send before a frame.
@@ -48,6 +111,30 @@ pub const FOOTER_COUNT: u32 = 4;
 /// Number of zero u32s in the header/footer delimiter.
 pub const ZERO_DELIMITER_COUNT: u32 = 4;
 
+/// How many times a data frame is retransmitted before `send` gives up.
+pub const MAX_RETRIES: u32 = 5;
+
+/// Largest payload `recv` will accept. Anything bigger is treated as a
+/// corrupted length word rather than an allocation request.
+pub const MAX_PAYLOAD_LEN: u32 = 1 << 20;
+
+/// Default inter-byte timeout once a frame has started, in milliseconds.
+pub const FRAME_TIMEOUT_MS: u64 = 500;
+
+/// Default time `send` waits for an ack before retransmitting, in
+/// milliseconds.
+pub const ACK_TIMEOUT_MS: u64 = 1000;
+
+/// Kind-word flag: the payload is compressed (see `lz`).
+pub const FLAG_COMPRESSED: u32 = 1 << 8;
+
+/// Payloads shorter than this are never worth compressing.
+pub const COMPRESS_THRESHOLD: usize = 64;
+
+/// After switching baud rates, how long the Pi waits for the unix side's
+/// `Control::Ping` at the new rate before reverting, in milliseconds.
+pub const BAUD_VERIFY_TIMEOUT_MS: u64 = 2000;
+
 // Pi-side framing constants
 pub const PI_SYNC_WORD: u32 = 0xDEAD_BEEF;
 pub const PI_FOOTER_WORD: u32 = 0xFACE_FEED;
@@ -56,10 +143,133 @@ pub const PI_FOOTER_WORD: u32 = 0xFACE_FEED;
 pub const UNIX_SYNC_WORD: u32 = 0xFACE_FEED;
 pub const UNIX_FOOTER_WORD: u32 = 0xDEAD_BEEF;
 
+/// What a frame carries. Kinds are nonzero so a kind word can never be
+/// mistaken for part of the zero delimiter.
+#[repr(u32)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FrameKind {
+    Data = 1,
+    Ack = 2,
+    Nack = 3,
+}
+
+impl FrameKind {
+    pub fn from_u32(v: u32) -> Option<Self> {
+        match v {
+            1 => Some(Self::Data),
+            2 => Some(Self::Ack),
+            3 => Some(Self::Nack),
+            _ => None,
+        }
+    }
+}
+
+/// Errors a transport can report from a read.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TransportError {
+    /// No byte arrived within the requested timeout.
+    Timeout,
+    /// The underlying device went away (EOF, hangup, I/O error).
+    Closed,
+}
+
+/// Errors reported by the framer.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FrameError {
+    /// The peer went quiet in the middle of a frame (or never acked).
+    Timeout,
+    /// The length word exceeded the framer's maximum payload size.
+    Oversized(u32),
+    /// The zero delimiter or padding after the payload was wrong.
+    BadFooter,
+    /// The frame's CRC (or kind word) didn't match its contents.
+    BadChecksum,
+    /// The peer never acknowledged a data frame within `MAX_RETRIES`
+    /// retransmissions.
+    RetriesExhausted,
+    /// The transport was closed underneath us.
+    TransportClosed,
+    /// A data frame arrived intact but didn't hold a valid message.
+    BadMessage,
+    /// A compressed data frame arrived intact but didn't decompress.
+    BadCompression,
+    /// The peer's `Hello` showed it can't be talked to.
+    Incompatible(control::Incompatible),
+}
+
+impl FrameError {
+    /// True if the link is gone and retrying is pointless.
+    pub fn is_fatal(&self) -> bool {
+        matches!(
+            self,
+            FrameError::TransportClosed | FrameError::Incompatible(_)
+        )
+    }
+
+    /// True if a frame arrived but was damaged, so the peer should resend.
+    /// That includes a payload that doesn't decompress: the NACK keeps the
+    /// sender from waiting out every ack timeout on it.
+    pub fn is_corrupt(&self) -> bool {
+        matches!(
+            self,
+            FrameError::BadChecksum
+                | FrameError::BadFooter
+                | FrameError::Oversized(_)
+                | FrameError::BadCompression
+        )
+    }
+}
+
+impl From<TransportError> for FrameError {
+    fn from(e: TransportError) -> Self {
+        match e {
+            TransportError::Timeout => FrameError::Timeout,
+            TransportError::Closed => FrameError::TransportClosed,
+        }
+    }
+}
+
+impl core::fmt::Display for FrameError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            FrameError::Timeout => write!(f, "timed out waiting for the rest of a frame"),
+            FrameError::Oversized(n) => {
+                write!(f, "frame length {} exceeds maximum payload size", n)
+            }
+            FrameError::BadFooter => write!(f, "malformed frame footer"),
+            FrameError::BadChecksum => write!(f, "frame checksum mismatch"),
+            FrameError::RetriesExhausted => {
+                write!(
+                    f,
+                    "peer did not acknowledge frame after {} retries",
+                    MAX_RETRIES
+                )
+            }
+            FrameError::TransportClosed => write!(f, "transport closed"),
+            FrameError::BadMessage => write!(f, "malformed message header"),
+            FrameError::BadCompression => write!(f, "malformed compressed payload"),
+            FrameError::Incompatible(e) => write!(
+                f,
+                "incompatible peer ({}); rebuild unix-side and pi-side from the same revision",
+                e
+            ),
+        }
+    }
+}
+
+/// A frame as it came off the wire, after its CRC was verified.
+struct RawFrame {
+    kind: FrameKind,
+    seq: u32,
+    payload: Vec<u8>,
+}
+
 /// Transport trait — anything that can send/receive bytes and u32s.
 pub trait Transport {
     fn put8(&mut self, b: u8);
-    fn get8(&mut self) -> u8;
+
+    /// Read one byte, waiting at most `timeout` (forever if `None`).
+    fn get8(&mut self, timeout: Option<Duration>) -> Result<u8, TransportError>;
 
     fn put32(&mut self, v: u32) {
         for &b in &v.to_le_bytes() {
@@ -67,15 +277,20 @@ pub trait Transport {
         }
     }
 
-    fn get32(&mut self) -> u32 {
+    /// Read a little-endian u32; `timeout` applies to each byte.
+    fn get32(&mut self, timeout: Option<Duration>) -> Result<u32, TransportError> {
         let mut bytes = [0u8; 4];
         for b in &mut bytes {
-            *b = self.get8();
+            *b = self.get8(timeout)?;
         }
-        u32::from_le_bytes(bytes)
+        Ok(u32::from_le_bytes(bytes))
     }
 
     fn flush(&mut self) {}
+
+    /// Go back to the line settings both ends start with (`BAUD_RATE`),
+    /// which is where the Pi is if it rebooted while we weren't looking.
+    fn reset(&mut self) {}
 }
 
 /// A framer wraps a transport and handles send/recv with a specific
@@ -86,31 +301,250 @@ pub struct Framer<T: Transport> {
     pub footer_word: u32,
     /// The sync word we expect to *receive* from the other side.
     pub peer_sync_word: u32,
+    /// The footer padding word we expect to receive from the other side.
+    pub peer_footer_word: u32,
+    /// Inter-byte timeout once a frame has started.
+    pub timeout: Duration,
+    /// How long `send` waits for an ack before retransmitting.
+    pub ack_timeout: Duration,
+    /// Largest payload length `recv` accepts.
+    pub max_payload: u32,
+    /// Whether `send` may compress payloads. Only set this once the peer
+    /// has said it can decompress them.
+    pub compress: bool,
+    /// Sequence number of the last data frame we sent.
+    tx_seq: u32,
+    /// Sequence number of the last data frame we accepted, for dropping
+    /// retransmitted duplicates.
+    rx_seq: Option<u32>,
+    /// Data frames that arrived while `send` was waiting for an ack.
+    pending: VecDeque<Vec<u8>>,
 }
 
 impl<T: Transport> Framer<T> {
-    /// Create a pi-side framer (sends DEADBEEF, receives FACEFEED).
-    pub fn pi_side(transport: T) -> Self {
+    fn new(transport: T, sync_word: u32, footer_word: u32, peer_sync_word: u32) -> Self {
         Self {
             transport,
-            sync_word: PI_SYNC_WORD,
-            footer_word: PI_FOOTER_WORD,
-            peer_sync_word: UNIX_SYNC_WORD,
+            sync_word,
+            footer_word,
+            peer_sync_word,
+            // the peer's footer is our sync word, and vice versa
+            peer_footer_word: sync_word,
+            timeout: Duration::from_millis(FRAME_TIMEOUT_MS),
+            ack_timeout: Duration::from_millis(ACK_TIMEOUT_MS),
+            max_payload: MAX_PAYLOAD_LEN,
+            compress: false,
+            tx_seq: 0,
+            rx_seq: None,
+            pending: VecDeque::new(),
         }
     }
 
+    /// Number data frames from `seed + 1` on. A side that starts afresh
+    /// should seed with something that differs between runs: the peer
+    /// drops a frame whose sequence number matches the last one it
+    /// accepted, as a retransmission, and it may remember one from our
+    /// previous life.
+    pub fn seed_seq(&mut self, seed: u32) {
+        self.tx_seq = seed;
+    }
+
+    /// Create a pi-side framer (sends DEADBEEF, receives FACEFEED).
+    pub fn pi_side(transport: T) -> Self {
+        Self::new(transport, PI_SYNC_WORD, PI_FOOTER_WORD, UNIX_SYNC_WORD)
+    }
+
     /// Create a unix-side framer (sends FACEFEED, receives DEADBEEF).
     pub fn unix_side(transport: T) -> Self {
-        Self {
-            transport,
-            sync_word: UNIX_SYNC_WORD,
-            footer_word: UNIX_FOOTER_WORD,
-            peer_sync_word: PI_SYNC_WORD,
+        Self::new(transport, UNIX_SYNC_WORD, UNIX_FOOTER_WORD, PI_SYNC_WORD)
+    }
+
+    /// Send a framed message and wait for the peer to acknowledge it,
+    /// retransmitting on NACK, a corrupted reply, or an ack timeout.
+    pub fn send(&mut self, payload: &[u8]) -> Result<(), FrameError> {
+        self.tx_seq = self.tx_seq.wrapping_add(1);
+        let seq = self.tx_seq;
+        let compressed = self.compress_payload(payload);
+        let (flags, wire) = match &compressed {
+            Some(c) => (FLAG_COMPRESSED, c.as_slice()),
+            None => (0, payload),
+        };
+
+        for _ in 0..=MAX_RETRIES {
+            self.send_raw(FrameKind::Data, flags, seq, wire);
+            loop {
+                match self.recv_raw(Some(self.ack_timeout)) {
+                    Ok(frame) => match frame.kind {
+                        FrameKind::Ack if frame.seq == seq => return Ok(()),
+                        // ack for an earlier transmission; keep waiting
+                        FrameKind::Ack => continue,
+                        FrameKind::Nack => break,
+                        // the peer started talking before our ack arrived;
+                        // hold on to its message for the next `recv`
+                        FrameKind::Data => {
+                            if let Some(p) = self.accept(frame) {
+                                self.pending.push_back(p);
+                            }
+                        }
+                    },
+                    Err(FrameError::TransportClosed) => return Err(FrameError::TransportClosed),
+                    // nothing came back; retransmit
+                    Err(FrameError::Timeout) => break,
+                    Err(_) => {
+                        // could have been our ack or a crossing data frame;
+                        // ask the peer to resend and retransmit ours
+                        self.send_raw(FrameKind::Nack, 0, 0, &[]);
+                        break;
+                    }
+                }
+            }
+        }
+        Err(FrameError::RetriesExhausted)
+    }
+
+    /// Send a framed message once, without waiting for an ack. For
+    /// traffic that may be lost (console output) but mustn't stall when
+    /// nobody is reading; the ack it gets back is skipped by the next
+    /// `send` or `recv` like any other stray one.
+    pub fn post(&mut self, payload: &[u8]) {
+        self.tx_seq = self.tx_seq.wrapping_add(1);
+        let seq = self.tx_seq;
+        match self.compress_payload(payload) {
+            Some(c) => self.send_raw(FrameKind::Data, FLAG_COMPRESSED, seq, &c),
+            None => self.send_raw(FrameKind::Data, 0, seq, payload),
+        }
+    }
+
+    /// Receive a framed message. Blocks until a frame starts arriving,
+    /// then returns its payload as an owned Vec.
+    ///
+    /// Corrupted frames are NACKed (so the peer retransmits) and then
+    /// reported; a timeout means the peer stopped mid-frame. Either way
+    /// the next call resynchronizes on the following sync barrier.
+    pub fn recv(&mut self) -> Result<Vec<u8>, FrameError> {
+        self.recv_within(None)
+    }
+
+    /// Like `recv`, but gives up with `FrameError::Timeout` if no frame
+    /// starts within `timeout`.
+    pub fn recv_timeout(&mut self, timeout: Duration) -> Result<Vec<u8>, FrameError> {
+        self.recv_within(Some(timeout))
+    }
+
+    fn recv_within(&mut self, start_timeout: Option<Duration>) -> Result<Vec<u8>, FrameError> {
+        if let Some(p) = self.pending.pop_front() {
+            return Ok(p);
+        }
+        loop {
+            match self.recv_raw(start_timeout) {
+                Ok(frame) if frame.kind == FrameKind::Data => {
+                    if let Some(p) = self.accept(frame) {
+                        return Ok(p);
+                    }
+                }
+                // stray ack/nack left over from an earlier exchange
+                Ok(_) => continue,
+                Err(e) => {
+                    if e.is_corrupt() {
+                        self.send_raw(FrameKind::Nack, 0, 0, &[]);
+                    }
+                    return Err(e);
+                }
+            }
+        }
+    }
+
+    /// Send a typed message (see `message`).
+    pub fn send_message(&mut self, msg: &Message) -> Result<(), FrameError> {
+        self.send(&msg.encode())
+    }
+
+    /// Post a typed message (see `post`).
+    pub fn post_message(&mut self, msg: &Message) {
+        self.post(&msg.encode())
+    }
+
+    /// Receive the next typed message.
+    pub fn recv_message(&mut self) -> Result<Message, FrameError> {
+        Message::decode(self.recv()?).ok_or(FrameError::BadMessage)
+    }
+
+    /// Receive the next typed message, waiting at most `timeout` for it
+    /// to start.
+    pub fn recv_message_timeout(&mut self, timeout: Duration) -> Result<Message, FrameError> {
+        Message::decode(self.recv_timeout(timeout)?).ok_or(FrameError::BadMessage)
+    }
+
+    /// Read the next data frame off the transport, if one starts within
+    /// `timeout`, and queue it for `recv` behind any already waiting.
+    /// With `take_queued`, lets a caller pick out one kind of message
+    /// without losing or reordering the rest.
+    pub fn poll(&mut self, timeout: Duration) -> Result<(), FrameError> {
+        loop {
+            match self.recv_raw(Some(timeout)) {
+                Ok(frame) if frame.kind == FrameKind::Data => {
+                    if let Some(p) = self.accept(frame) {
+                        self.pending.push_back(p);
+                    }
+                    return Ok(());
+                }
+                Ok(_) => continue,
+                Err(e) => {
+                    if e.is_corrupt() {
+                        self.send_raw(FrameKind::Nack, 0, 0, &[]);
+                    }
+                    return Err(e);
+                }
+            }
         }
     }
 
-    /// Send a framed message.
-    pub fn send(&mut self, payload: &[u8]) {
+    /// Remove and return the first message waiting for `recv` that
+    /// `wanted` picks.
+    pub fn take_queued(&mut self, wanted: impl Fn(&Message) -> bool) -> Option<Message> {
+        let i = self
+            .pending
+            .iter()
+            .position(|p| Message::decode(p.clone()).is_some_and(|m| wanted(&m)))?;
+        self.pending.remove(i).and_then(Message::decode)
+    }
+
+    /// Ack a verified data frame; returns its payload unless it is a
+    /// retransmission of the frame we accepted last.
+    fn accept(&mut self, frame: RawFrame) -> Option<Vec<u8>> {
+        self.send_raw(FrameKind::Ack, 0, frame.seq, &[]);
+        if self.rx_seq == Some(frame.seq) {
+            return None;
+        }
+        self.rx_seq = Some(frame.seq);
+        Some(frame.payload)
+    }
+
+    /// The on-wire form of a compressed payload, if compression is on and
+    /// actually makes it smaller.
+    fn compress_payload(&self, payload: &[u8]) -> Option<Vec<u8>> {
+        if !self.compress || payload.len() < COMPRESS_THRESHOLD {
+            return None;
+        }
+        let block = lz::compress(payload);
+        if block.len() + 4 >= payload.len() {
+            return None;
+        }
+        let mut wire = Vec::with_capacity(block.len() + 4);
+        wire.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+        wire.extend_from_slice(&block);
+        Some(wire)
+    }
+
+    /// Put a single frame on the wire.
+    fn send_raw(&mut self, kind: FrameKind, flags: u32, seq: u32, payload: &[u8]) {
+        let kind = kind as u32 | flags;
+        let mut crc = Crc32::new();
+        crc.update32(kind);
+        crc.update32(seq);
+        crc.update32(payload.len() as u32);
+        crc.update(payload);
+
         // sync barrier
         for _ in 0..SYNC_COUNT {
             self.transport.put32(self.sync_word);
@@ -119,12 +553,15 @@ impl<T: Transport> Framer<T> {
         for _ in 0..ZERO_DELIMITER_COUNT {
             self.transport.put32(0);
         }
+        self.transport.put32(kind);
+        self.transport.put32(seq);
         // payload length
         self.transport.put32(payload.len() as u32);
         // payload bytes
         for &b in payload {
             self.transport.put8(b);
         }
+        self.transport.put32(crc.finish());
         // footer: four zero u32s
         for _ in 0..ZERO_DELIMITER_COUNT {
             self.transport.put32(0);
@@ -136,41 +573,91 @@ impl<T: Transport> Framer<T> {
         self.transport.flush();
     }
 
-    /// Receive a framed message. Blocks until a complete frame arrives.
-    /// Returns the payload as an owned Vec.
-    pub fn recv(&mut self) -> Vec<u8> {
-        // scan for header: skip sync words, find four consecutive zero u32s
+    /// Read a single frame off the wire and verify its CRC.
+    /// `start_timeout` bounds the wait for the header; once inside the
+    /// frame, every byte must arrive within `self.timeout`.
+    fn recv_raw(&mut self, start_timeout: Option<Duration>) -> Result<RawFrame, FrameError> {
+        // scan for header: skip sync words until a run of at least four
+        // u32s worth of zero bytes. The run ends at the low byte of the
+        // kind word, which is never zero. Scanning bytes rather than words
+        // lets us realign after the line drops or duplicates a byte.
+        let header_len = ZERO_DELIMITER_COUNT * 4;
         let mut zero_count: u32 = 0;
-        loop {
-            let word = self.transport.get32();
-            if word == 0 {
+        let kind_lo = loop {
+            let b = self.transport.get8(start_timeout)?;
+            if b == 0 {
                 zero_count += 1;
-                if zero_count >= ZERO_DELIMITER_COUNT {
-                    break;
-                }
+            } else if zero_count >= header_len {
+                break b;
             } else {
                 zero_count = 0;
             }
-        }
+        };
 
+        let timeout = Some(self.timeout);
+        let mut kind = kind_lo as u32;
+        for shift in [8, 16, 24] {
+            kind |= (self.transport.get8(timeout)? as u32) << shift;
+        }
+        let seq = self.transport.get32(timeout)?;
         // payload length
-        let len = self.transport.get32() as usize;
+        let len = self.transport.get32(timeout)?;
+        if len > self.max_payload {
+            return Err(FrameError::Oversized(len));
+        }
+
+        let mut crc = Crc32::new();
+        crc.update32(kind);
+        crc.update32(seq);
+        crc.update32(len);
 
         // payload
-        let mut buf = Vec::with_capacity(len);
+        let mut buf = Vec::with_capacity(len as usize);
         for _ in 0..len {
-            buf.push(self.transport.get8());
+            buf.push(self.transport.get8(timeout)?);
         }
+        crc.update(&buf);
+        let expected = self.transport.get32(timeout)?;
 
         // consume footer zeros
+        let mut footer_ok = true;
         for _ in 0..ZERO_DELIMITER_COUNT {
-            self.transport.get32();
+            footer_ok &= self.transport.get32(timeout)? == 0;
         }
         // consume footer words
         for _ in 0..FOOTER_COUNT {
-            self.transport.get32();
+            footer_ok &= self.transport.get32(timeout)? == self.peer_footer_word;
+        }
+
+        if crc.finish() != expected {
+            return Err(FrameError::BadChecksum);
+        }
+        let flags = kind & !0xFF;
+        let kind = FrameKind::from_u32(kind & 0xFF).ok_or(FrameError::BadChecksum)?;
+        if flags & !FLAG_COMPRESSED != 0 {
+            return Err(FrameError::BadChecksum);
+        }
+        if !footer_ok {
+            return Err(FrameError::BadFooter);
+        }
+        if flags & FLAG_COMPRESSED != 0 {
+            buf = self.decompress_payload(&buf)?;
         }
 
-        buf
+        Ok(RawFrame {
+            kind,
+            seq,
+            payload: buf,
+        })
+    }
+
+    /// Undo `compress_payload`.
+    fn decompress_payload(&self, wire: &[u8]) -> Result<Vec<u8>, FrameError> {
+        let (len, block) = wire.split_at_checked(4).ok_or(FrameError::BadCompression)?;
+        let len = u32::from_le_bytes(len.try_into().unwrap());
+        if len > self.max_payload {
+            return Err(FrameError::Oversized(len));
+        }
+        lz::decompress(block, len as usize).ok_or(FrameError::BadCompression)
     }
 }
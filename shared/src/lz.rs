@@ -0,0 +1,147 @@
+//! A small LZ77 compressor for frame payloads, using a variant of the
+//! LZ4 block format. No allocation beyond the output and a 16 KiB hash
+//! table, so it runs on the Pi as happily as on the host.
+//!
+//! A block is a series of sequences:
+//!
+//! ```text
+//! [token: u8]           high nibble: literal count, low nibble: match length - 4
+//!                       (15 in either means "more length bytes follow")
+//! [lit len: u8 * k]     only if the literal nibble was 15: bytes added to it,
+//!                       255 meaning "keep going"
+//! [literals: u8 * n]
+//! [offset: u16]         how far back the match starts (absent if the
+//!                       block ends after the literals)
+//! [match len: u8 * k]   only if the match nibble was 15, encoded as above
+//! ```
+
+use alloc::vec;
+use alloc::vec::Vec;
+
+const MIN_MATCH: usize = 4;
+const MAX_OFFSET: usize = u16::MAX as usize;
+const HASH_BITS: u32 = 12;
+
+fn hash(word: u32) -> usize {
+    (word.wrapping_mul(2_654_435_761) >> (32 - HASH_BITS)) as usize
+}
+
+fn read_u32(input: &[u8], i: usize) -> u32 {
+    u32::from_le_bytes(input[i..i + 4].try_into().unwrap())
+}
+
+fn push_len(out: &mut Vec<u8>, mut n: usize) {
+    while n >= 255 {
+        out.push(255);
+        n -= 255;
+    }
+    out.push(n as u8);
+}
+
+fn push_sequence(out: &mut Vec<u8>, literals: &[u8], matched: Option<(usize, usize)>) {
+    let lit = literals.len();
+    let match_extra = matched.map_or(0, |(_, len)| len - MIN_MATCH);
+    out.push(((lit.min(15) as u8) << 4) | match_extra.min(15) as u8);
+    if lit >= 15 {
+        push_len(out, lit - 15);
+    }
+    out.extend_from_slice(literals);
+    if let Some((offset, _)) = matched {
+        out.extend_from_slice(&(offset as u16).to_le_bytes());
+        if match_extra >= 15 {
+            push_len(out, match_extra - 15);
+        }
+    }
+}
+
+/// Compress `input` into a block.
+pub fn compress(input: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(input.len() / 2 + 16);
+    let mut table = vec![u32::MAX; 1 << HASH_BITS];
+    let mut anchor = 0;
+    let mut i = 0;
+
+    while i + MIN_MATCH <= input.len() {
+        let h = hash(read_u32(input, i));
+        let candidate = table[h] as usize;
+        table[h] = i as u32;
+
+        if candidate < i
+            && i - candidate <= MAX_OFFSET
+            && input[candidate..candidate + MIN_MATCH] == input[i..i + MIN_MATCH]
+        {
+            let mut len = MIN_MATCH;
+            while i + len < input.len() && input[candidate + len] == input[i + len] {
+                len += 1;
+            }
+            push_sequence(&mut out, &input[anchor..i], Some((i - candidate, len)));
+            i += len;
+            anchor = i;
+        } else {
+            i += 1;
+        }
+    }
+    // trailing literals, if any (an empty input still gets a token)
+    if anchor < input.len() || out.is_empty() {
+        push_sequence(&mut out, &input[anchor..], None);
+    }
+    out
+}
+
+/// Decompress a block produced by `compress`. Returns None if the block
+/// is malformed or doesn't decompress to exactly `len` bytes.
+pub fn decompress(block: &[u8], len: usize) -> Option<Vec<u8>> {
+    let mut out = Vec::with_capacity(len);
+    let mut i = 0;
+
+    let read_len = |i: &mut usize, mut n: usize| -> Option<usize> {
+        loop {
+            let b = *block.get(*i)?;
+            *i += 1;
+            n += b as usize;
+            if b != 255 {
+                return Some(n);
+            }
+        }
+    };
+
+    while i < block.len() {
+        let token = block[i];
+        i += 1;
+
+        let mut lit = (token >> 4) as usize;
+        if lit == 15 {
+            lit = read_len(&mut i, lit)?;
+        }
+        let literals = block.get(i..i.checked_add(lit)?)?;
+        if out.len() + lit > len {
+            return None;
+        }
+        out.extend_from_slice(literals);
+        i += lit;
+
+        // a trailing literal-only sequence ends the block
+        if i == block.len() {
+            break;
+        }
+
+        let offset = u16::from_le_bytes(block.get(i..i + 2)?.try_into().unwrap()) as usize;
+        i += 2;
+        let mut matched = (token & 0x0F) as usize;
+        if matched == 15 {
+            matched = read_len(&mut i, matched)?;
+        }
+        matched += MIN_MATCH;
+
+        if offset == 0 || offset > out.len() || out.len() + matched > len {
+            return None;
+        }
+        // byte by byte: the match may overlap what it is producing
+        let start = out.len() - offset;
+        for k in 0..matched {
+            out.push(out[start + k]);
+        }
+    }
+
+    (out.len() == len).then_some(out)
+}
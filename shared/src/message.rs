@@ -0,0 +1,107 @@
+//! Typed messages carried inside data frames.
+//!
+//! Every data frame payload starts with a small message header so either
+//! side can tell an evaluation result from an error or from incidental
+//! console output without sniffing the text:
+//!
+//! ```text
+//! [kind: u32]           message kind (see `MessageKind`)
+//! [id: u32]             request id; replies echo the id of their request
+//! [body: u8 * rest]     kind-specific body (UTF-8 text for most kinds)
+//! ```
+//!
+//! Messages that aren't tied to a request (console output, unsolicited
+//! control traffic) use id `0`.
+
+use alloc::vec::Vec;
+
+/// Length in bytes of the message header.
+pub const MESSAGE_HEADER_LEN: usize = 8;
+
+#[repr(u32)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MessageKind {
+    /// unix -> pi: source text to parse and evaluate.
+    EvalRequest = 1,
+    /// pi -> unix: printed value of a successful evaluation.
+    EvalResult = 2,
+    /// pi -> unix: parse or runtime error for a request.
+    EvalError = 3,
+    /// pi -> unix: console output (`println!` etc.), not a reply.
+    Output = 4,
+    /// Raw binary data, either direction.
+    Blob = 5,
+    /// Link management (handshake and friends), either direction.
+    Control = 6,
+}
+
+impl MessageKind {
+    pub fn from_u32(v: u32) -> Option<Self> {
+        match v {
+            1 => Some(Self::EvalRequest),
+            2 => Some(Self::EvalResult),
+            3 => Some(Self::EvalError),
+            4 => Some(Self::Output),
+            5 => Some(Self::Blob),
+            6 => Some(Self::Control),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Message {
+    pub kind: MessageKind,
+    pub id: u32,
+    pub body: Vec<u8>,
+}
+
+impl Message {
+    pub fn new(kind: MessageKind, id: u32, body: &[u8]) -> Self {
+        Self {
+            kind,
+            id,
+            body: body.to_vec(),
+        }
+    }
+
+    /// Wrap a control operation (id 0, not tied to a request).
+    pub fn control(ctl: &crate::Control) -> Self {
+        Self {
+            kind: MessageKind::Control,
+            id: 0,
+            body: ctl.encode(),
+        }
+    }
+
+    /// Serialize header + body into a frame payload.
+    pub fn encode(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(MESSAGE_HEADER_LEN + self.body.len());
+        out.extend_from_slice(&(self.kind as u32).to_le_bytes());
+        out.extend_from_slice(&self.id.to_le_bytes());
+        out.extend_from_slice(&self.body);
+        out
+    }
+
+    /// Parse a frame payload. Returns None if the header is truncated or
+    /// names an unknown kind.
+    pub fn decode(mut payload: Vec<u8>) -> Option<Self> {
+        if payload.len() < MESSAGE_HEADER_LEN {
+            return None;
+        }
+        let word = |i: usize| u32::from_le_bytes(payload[i..i + 4].try_into().unwrap());
+        let kind = MessageKind::from_u32(word(0))?;
+        let id = word(4);
+        payload.drain(..MESSAGE_HEADER_LEN);
+        Some(Self {
+            kind,
+            id,
+            body: payload,
+        })
+    }
+
+    /// The body as UTF-8 text, or None if it isn't valid UTF-8.
+    pub fn text(&self) -> Option<&str> {
+        core::str::from_utf8(&self.body).ok()
+    }
+}
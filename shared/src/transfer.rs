@@ -0,0 +1,116 @@
+//! Chunked bulk transfer of raw memory between the host and the Pi.
+//!
+//! A transfer moves `len` bytes to or from Pi address `addr` as a series
+//! of `MessageKind::Blob` messages, each carrying one `Chunk`:
+//!
+//! ```text
+//! [offset: u32]         byte offset of this chunk within the transfer
+//! [crc: u32]            CRC-32 of data
+//! [data: u8 * rest]     chunk contents
+//! ```
+//!
+//! The frame CRC already protects the bytes on the wire; the chunk CRC is
+//! checked again against the receiver's buffer so a chunk that landed at
+//! the wrong offset, or was copied badly, is caught too. Chunks that fail
+//! either check are dropped rather than answered, so a transfer never
+//! waits on a per-chunk reply.
+//!
+//! # Upload (unix -> pi)
+//!
+//! 1. unix sends `Control::Upload { addr, len }`. `addr == 0` asks the Pi
+//!    to allocate `len` bytes (rounded up to whole words, like `alloc32`).
+//! 2. The Pi answers `Control::Transfer(Progress)` with the destination
+//!    address and the offset to start from: 0 for a new transfer, or how
+//!    far it got if this repeats an unfinished upload to the same
+//!    `addr`/`len`. A progress `addr` of 0 means the Pi refused.
+//! 3. unix streams chunks in order starting at that offset.
+//! 4. unix sends `Control::QueryTransfer`; the Pi answers with its
+//!    progress. If `offset < len` some chunks were lost and unix resumes
+//!    from step 3 at the reported offset.
+//!
+//! # Download (pi -> unix)
+//!
+//! unix sends `Control::Download { addr, len, offset }`; the Pi streams
+//! chunks from `offset` to `len` and finishes with
+//! `Control::Transfer(Progress)`. If unix saw a gap it requests the rest
+//! with a new `Download` starting at the first missing byte.
+
+use alloc::vec::Vec;
+
+use crate::crc::crc32;
+use crate::message::MESSAGE_HEADER_LEN;
+
+/// Length in bytes of the chunk header.
+pub const CHUNK_HEADER_LEN: usize = 8;
+
+/// Default amount of data per chunk. Senders use less if the negotiated
+/// `max_payload` is smaller (see `chunk_len`).
+pub const CHUNK_LEN: usize = 4096;
+
+/// Largest chunk data length that fits in a frame of `max_payload` bytes.
+pub fn chunk_len(max_payload: u32) -> usize {
+    let room = (max_payload as usize).saturating_sub(MESSAGE_HEADER_LEN + CHUNK_HEADER_LEN);
+    CHUNK_LEN.min(room).max(1)
+}
+
+/// Where a transfer stands. Sent by the Pi in `Control::Transfer`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Progress {
+    pub addr: u32,
+    pub len: u32,
+    /// Bytes `[0, offset)` have been transferred and verified.
+    pub offset: u32,
+}
+
+impl Progress {
+    pub fn is_done(&self) -> bool {
+        self.offset >= self.len
+    }
+}
+
+/// One piece of a transfer, carried in a `MessageKind::Blob` body.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Chunk {
+    pub offset: u32,
+    pub crc: u32,
+    pub data: Vec<u8>,
+}
+
+impl Chunk {
+    pub fn new(offset: u32, data: &[u8]) -> Self {
+        Self {
+            offset,
+            crc: crc32(data),
+            data: data.to_vec(),
+        }
+    }
+
+    /// True if `data` still matches the checksum it was sent with.
+    pub fn is_intact(&self) -> bool {
+        crc32(&self.data) == self.crc
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(CHUNK_HEADER_LEN + self.data.len());
+        out.extend_from_slice(&self.offset.to_le_bytes());
+        out.extend_from_slice(&self.crc.to_le_bytes());
+        out.extend_from_slice(&self.data);
+        out
+    }
+
+    /// Parse a blob body. Returns None if the header is truncated.
+    pub fn decode(mut body: Vec<u8>) -> Option<Self> {
+        if body.len() < CHUNK_HEADER_LEN {
+            return None;
+        }
+        let word = |i: usize| u32::from_le_bytes(body[i..i + 4].try_into().unwrap());
+        let offset = word(0);
+        let crc = word(4);
+        body.drain(..CHUNK_HEADER_LEN);
+        Some(Self {
+            offset,
+            crc,
+            data: body,
+        })
+    }
+}
@@ -0,0 +1,99 @@
+//! Non-interactive evaluation for `--script` and `--eval`.
+
+use std::fmt;
+use std::fs;
+use std::io::{self, Write};
+
+use shared::{FrameError, Framer, MessageKind, Transport};
+
+use crate::link;
+use crate::reader::{self, ReadError};
+
+#[derive(Debug)]
+pub enum BatchError {
+    Io(String, io::Error),
+    Read(String, ReadError),
+    /// The source ended in the middle of a form.
+    Unterminated(String),
+    Link(FrameError),
+    /// The kernel reported an error evaluating `form`, which starts on
+    /// `line` of the source `name`. Positions in `error` count from the
+    /// start of the form, which is all the kernel was sent.
+    Eval {
+        name: String,
+        line: usize,
+        form: String,
+        error: String,
+    },
+}
+
+impl fmt::Display for BatchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BatchError::Io(path, e) => write!(f, "{}: {}", path, e),
+            BatchError::Read(name, e) => write!(f, "{}: {}", name, e),
+            BatchError::Unterminated(name) => {
+                write!(f, "{}: unterminated form at end of input", name)
+            }
+            BatchError::Link(e) => write!(f, "link error: {}", e),
+            BatchError::Eval {
+                name,
+                line,
+                form,
+                error,
+            } => write!(f, "{}:{}: error in {}: {}", name, line, form, error),
+        }
+    }
+}
+
+/// Evaluate every top-level form in `src` in order, writing each result
+/// to `out`. `name` identifies the source in errors. Stops at the first
+/// form the kernel reports an error for.
+pub fn eval_source<T: Transport, W: Write>(
+    framer: &mut Framer<T>,
+    next_id: &mut u32,
+    name: &str,
+    src: &str,
+    out: &mut W,
+) -> Result<(), BatchError> {
+    let split = reader::split_forms(src).map_err(|e| BatchError::Read(name.into(), e))?;
+    if !split.rest.is_empty() {
+        return Err(BatchError::Unterminated(name.into()));
+    }
+    for form in split.forms {
+        let id = *next_id;
+        *next_id = next_id.wrapping_add(1).max(1);
+        let reply = link::eval(framer, id, form).map_err(BatchError::Link)?;
+        let text = String::from_utf8_lossy(&reply.body).into_owned();
+        if reply.kind == MessageKind::EvalError {
+            // `form` is a slice of `src`
+            let start = form.as_ptr() as usize - src.as_ptr() as usize;
+            return Err(BatchError::Eval {
+                name: name.into(),
+                line: src[..start].matches('\n').count() + 1,
+                form: form.into(),
+                error: text,
+            });
+        }
+        let _ = writeln!(out, "{}", text);
+    }
+    Ok(())
+}
+
+/// Run `script` (if any) and then each of `exprs`.
+pub fn run<T: Transport, W: Write>(
+    framer: &mut Framer<T>,
+    script: Option<&str>,
+    exprs: &[String],
+    out: &mut W,
+) -> Result<(), BatchError> {
+    let mut next_id = 1;
+    if let Some(path) = script {
+        let src = fs::read_to_string(path).map_err(|e| BatchError::Io(path.into(), e))?;
+        eval_source(framer, &mut next_id, path, &src, out)?;
+    }
+    for expr in exprs {
+        eval_source(framer, &mut next_id, "--eval", expr, out)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,208 @@
+//! `unix-side boot FILE`: our half of the Pi bootloader's upload
+//! protocol (the one `my-install` speaks), so the kernel goes over the
+//! same open tty the session then runs on.
+//!
+//! Every word is a little-endian u32. The bootloader keeps asking for
+//! the program until we answer, then:
+//!
+//! ```text
+//! pi:   GET_PROG_INFO                      (repeated)
+//! unix: PUT_PROG_INFO addr nbytes crc
+//! pi:   GET_CODE crc                       (crc echoed back)
+//! unix: PUT_CODE <nbytes bytes>
+//! pi:   BOOT_SUCCESS                       (then jumps to addr)
+//! ```
+//!
+//! In place of any of its words the bootloader may send `PRINT_STRING
+//! nbytes <bytes>`, which we echo, or an error code, which ends the
+//! upload. The CRC is the same CRC-32 the framer uses.
+
+use std::fmt;
+use std::time::{Duration, Instant};
+
+use shared::crc::crc32;
+use shared::{Transport, TransportError};
+
+pub const GET_PROG_INFO: u32 = 0x1111_2222;
+pub const PUT_PROG_INFO: u32 = 0x3333_4444;
+pub const GET_CODE: u32 = 0x5555_6666;
+pub const PUT_CODE: u32 = 0x7777_8888;
+pub const BOOT_SUCCESS: u32 = 0x9999_AAAA;
+pub const BOOT_ERROR: u32 = 0xBBBB_CCCC;
+pub const PRINT_STRING: u32 = 0xDDDD_EEEE;
+pub const BAD_CODE_ADDR: u32 = 0x6666_8888;
+pub const BAD_CODE_CKSUM: u32 = 0x7777_9999;
+
+/// Where the kernel is linked to run (`__BOOTLOADER_LOAD_ADDR` in
+/// `pi-side/memory.ld`).
+pub const LOAD_ADDR: u32 = 0x8000;
+
+/// How long to wait for the bootloader to speak up, which gives time to
+/// reset a board that is still running the last kernel.
+pub const BOOTLOADER_WAIT: Duration = Duration::from_secs(30);
+
+/// How long the bootloader gets to answer each step once it is talking.
+const STEP_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Longest `PRINT_STRING` we believe.
+const MAX_PRINT: u32 = 1024;
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum BootError {
+    /// Nothing that looked like a bootloader asked for a program.
+    NoBootloader,
+    /// The bootloader went quiet while we were waiting for `what`.
+    Timeout(&'static str),
+    /// The bootloader sent an error code instead of the next step.
+    Refused(u32),
+    /// The bootloader sent `got` where we expected `expected`.
+    Unexpected {
+        expected: u32,
+        got: u32,
+    },
+    /// The CRC the bootloader echoed isn't the one we sent.
+    ChecksumMismatch {
+        sent: u32,
+        echoed: u32,
+    },
+    /// The kernel image doesn't fit the protocol.
+    TooLarge(usize),
+    Closed,
+}
+
+impl fmt::Display for BootError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BootError::NoBootloader => {
+                write!(f, "no bootloader asked for a program; is the Pi reset?")
+            }
+            BootError::Timeout(what) => write!(f, "bootloader went quiet waiting for {}", what),
+            BootError::Refused(BAD_CODE_ADDR) => {
+                write!(f, "bootloader refused the load address")
+            }
+            BootError::Refused(BAD_CODE_CKSUM) => {
+                write!(f, "bootloader received the code with a bad checksum")
+            }
+            BootError::Refused(code) => write!(f, "bootloader reported error {:#010x}", code),
+            BootError::Unexpected { expected, got } => {
+                write!(
+                    f,
+                    "expected {:#010x} from the bootloader, got {:#010x}",
+                    expected, got
+                )
+            }
+            BootError::ChecksumMismatch { sent, echoed } => write!(
+                f,
+                "bootloader echoed checksum {:#010x}, we sent {:#010x}",
+                echoed, sent
+            ),
+            BootError::TooLarge(len) => write!(f, "kernel image of {} bytes is too large", len),
+            BootError::Closed => write!(f, "tty closed"),
+        }
+    }
+}
+
+impl std::error::Error for BootError {}
+
+fn get8<T: Transport>(t: &mut T, timeout: Duration, what: &'static str) -> Result<u8, BootError> {
+    t.get8(Some(timeout)).map_err(|e| match e {
+        TransportError::Timeout => BootError::Timeout(what),
+        TransportError::Closed => BootError::Closed,
+    })
+}
+
+fn get32<T: Transport>(t: &mut T, what: &'static str) -> Result<u32, BootError> {
+    let mut word = [0u8; 4];
+    for b in &mut word {
+        *b = get8(t, STEP_TIMEOUT, what)?;
+    }
+    Ok(u32::from_le_bytes(word))
+}
+
+fn put32<T: Transport>(t: &mut T, v: u32) {
+    for b in v.to_le_bytes() {
+        t.put8(b);
+    }
+}
+
+/// The next word from the bootloader that isn't part of a
+/// `PRINT_STRING`, echoing those to stderr.
+fn get_op<T: Transport>(t: &mut T, what: &'static str) -> Result<u32, BootError> {
+    loop {
+        let op = get32(t, what)?;
+        if op != PRINT_STRING {
+            return Ok(op);
+        }
+        let len = get32(t, "a string length")?;
+        if len > MAX_PRINT {
+            return Err(BootError::Unexpected {
+                expected: MAX_PRINT,
+                got: len,
+            });
+        }
+        let mut bytes = Vec::with_capacity(len as usize);
+        for _ in 0..len {
+            bytes.push(get8(t, STEP_TIMEOUT, "a string")?);
+        }
+        eprint!("pi: {}", String::from_utf8_lossy(&bytes));
+    }
+}
+
+/// `op` if it is `expected`, else the error it amounts to.
+fn expect(op: u32, expected: u32) -> Result<(), BootError> {
+    match op {
+        _ if op == expected => Ok(()),
+        BOOT_ERROR | BAD_CODE_ADDR | BAD_CODE_CKSUM => Err(BootError::Refused(op)),
+        got => Err(BootError::Unexpected { expected, got }),
+    }
+}
+
+/// Wait up to `wait` for the bootloader on `t`, send it `code` to run at
+/// `addr`, and return once it reports that it is jumping there.
+pub fn upload<T: Transport>(
+    t: &mut T,
+    addr: u32,
+    code: &[u8],
+    wait: Duration,
+) -> Result<(), BootError> {
+    let len = u32::try_from(code.len()).map_err(|_| BootError::TooLarge(code.len()))?;
+
+    // we may come in halfway through a word, or after output from
+    // whatever ran before the reset, so look for the request a byte at
+    // a time
+    let deadline = Instant::now() + wait;
+    let mut window = 0u32;
+    while window != GET_PROG_INFO {
+        let left = deadline.saturating_duration_since(Instant::now());
+        let b = match get8(t, left.max(Duration::from_millis(1)), "") {
+            Err(BootError::Timeout(_)) => return Err(BootError::NoBootloader),
+            r => r?,
+        };
+        window = (window >> 8) | (b as u32) << 24;
+    }
+
+    let crc = crc32(code);
+    for word in [PUT_PROG_INFO, addr, len, crc] {
+        put32(t, word);
+    }
+    t.flush();
+
+    // requests sent before ours arrived are now word-aligned
+    let mut op = get_op(t, "GET_CODE")?;
+    while op == GET_PROG_INFO {
+        op = get_op(t, "GET_CODE")?;
+    }
+    expect(op, GET_CODE)?;
+    let echoed = get32(t, "the checksum")?;
+    if echoed != crc {
+        return Err(BootError::ChecksumMismatch { sent: crc, echoed });
+    }
+
+    put32(t, PUT_CODE);
+    for &b in code {
+        t.put8(b);
+    }
+    t.flush();
+
+    expect(get_op(t, "BOOT_SUCCESS")?, BOOT_SUCCESS)
+}
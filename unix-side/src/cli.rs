@@ -0,0 +1,208 @@
+//! Command-line options.
+
+use crate::tty;
+
+pub const USAGE: &str = "\
+usage: unix-side [serve | connect | replay FILE | boot FILE | trace FILE] [options]
+
+commands:
+  (none)           talk to the Pi over the serial device
+  boot FILE        send the kernel image FILE to the Pi's bootloader, then
+                   carry on as with no command
+  serve            own the serial device and share the Pi with clients
+                   connecting to a local socket
+  connect          talk to the Pi through a running `unix-side serve`
+  replay FILE      re-send the forms in a transcript (see --record) and
+                   compare the replies; exits with status 1 if any differ
+  trace FILE       decode a capture (see --capture) into annotated frames
+
+options:
+  --device PATH    serial device to use (default: first /dev/ttyUSB* or similar)
+  --baud RATE      link rate to switch to after connecting
+                   (default: fastest rate both sides support)
+  --script FILE    evaluate FILE's top-level forms, then exit;
+                   stops with status 1 at the first error
+  --eval EXPR      evaluate EXPR, then exit (may be repeated; runs after --script)
+  --socket PATH    socket for serve and connect (default: $LISPI_SOCKET, else
+                   lispi.sock in $XDG_RUNTIME_DIR, else /tmp/lispi-<uid>.sock)
+  --json           read requests and write replies as JSON lines, for
+                   editor frontends (see `unix-side/src/json.rs`)
+  --record FILE    append every form sent and every reply to FILE
+  --capture FILE   write every byte crossing the serial device to FILE,
+                   for `unix-side trace`
+  --prelude FILE   re-send FILE's forms whenever the Pi turns out to have
+                   rebooted (may be repeated)
+  --host-dir DIR   let the kernel read and write files under DIR
+                   (@host/read, @host/load, @host/write, @host/append)
+  --stop-at-divergence
+                   (replay) stop at the first reply that differs
+  --quiet          don't print connection status messages
+  -h, --help       show this help
+";
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Command {
+    /// Own the serial device for this session.
+    #[default]
+    Direct,
+    /// Own the serial device and serve clients on a socket.
+    Serve,
+    /// Go through a server instead of the serial device.
+    Connect,
+    /// Re-send a transcript over the serial device.
+    Replay,
+    /// Upload a kernel, then continue as `Direct`.
+    Boot,
+    /// Decode a capture.
+    Trace,
+}
+
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Options {
+    pub command: Command,
+    pub device: Option<String>,
+    pub baud: Option<u32>,
+    pub script: Option<String>,
+    pub socket: Option<String>,
+    pub eval: Vec<String>,
+    pub json: bool,
+    /// Transcript to record to.
+    pub record: Option<String>,
+    /// Files to re-send after a reboot.
+    pub preludes: Vec<String>,
+    /// Directory the kernel's `@host/...` syscalls see.
+    pub host_dir: Option<String>,
+    /// Transcript to replay.
+    pub replay: Option<String>,
+    pub stop_at_divergence: bool,
+    /// Kernel image to upload.
+    pub kernel: Option<String>,
+    /// Where to record the tty's traffic.
+    pub capture: Option<String>,
+    /// Capture to decode.
+    pub trace: Option<String>,
+    pub quiet: bool,
+    pub help: bool,
+}
+
+impl Options {
+    /// Parse arguments (without the program name). Accepts both
+    /// `--flag value` and `--flag=value`; the command, if any, comes
+    /// first.
+    pub fn parse<I: IntoIterator<Item = String>>(args: I) -> Result<Self, String> {
+        let mut opts = Options::default();
+        let mut args = args.into_iter().peekable();
+        match args.peek().map(String::as_str) {
+            Some("serve") => opts.command = Command::Serve,
+            Some("connect") => opts.command = Command::Connect,
+            Some("replay") => opts.command = Command::Replay,
+            Some("boot") => opts.command = Command::Boot,
+            Some("trace") => opts.command = Command::Trace,
+            _ => {}
+        }
+        if opts.command != Command::Direct {
+            args.next();
+        }
+        if opts.command == Command::Replay {
+            opts.replay = Some(
+                args.next()
+                    .filter(|a| !a.starts_with('-'))
+                    .ok_or("replay needs a transcript file")?,
+            );
+        }
+        if opts.command == Command::Boot {
+            opts.kernel = Some(
+                args.next()
+                    .filter(|a| !a.starts_with('-'))
+                    .ok_or("boot needs a kernel image")?,
+            );
+        }
+        if opts.command == Command::Trace {
+            opts.trace = Some(
+                args.next()
+                    .filter(|a| !a.starts_with('-'))
+                    .ok_or("trace needs a capture file")?,
+            );
+        }
+        while let Some(arg) = args.next() {
+            let (flag, inline) = match arg.split_once('=') {
+                Some((f, v)) if f.starts_with("--") => (f.to_string(), Some(v.to_string())),
+                _ => (arg, None),
+            };
+            let mut value = || {
+                inline
+                    .clone()
+                    .or_else(|| args.next())
+                    .ok_or_else(|| format!("{} needs a value", flag))
+            };
+            match flag.as_str() {
+                "--device" => opts.device = Some(value()?),
+                "--baud" => {
+                    let v = value()?;
+                    let rate = v
+                        .parse()
+                        .ok()
+                        .filter(|r| tty::SUPPORTED_BAUD_RATES.contains(r))
+                        .ok_or_else(|| {
+                            format!(
+                                "unsupported baud rate {} (supported: {:?})",
+                                v,
+                                tty::SUPPORTED_BAUD_RATES
+                            )
+                        })?;
+                    opts.baud = Some(rate);
+                }
+                "--script" => opts.script = Some(value()?),
+                "--socket" => opts.socket = Some(value()?),
+                "--eval" => opts.eval.push(value()?),
+                "--json" => opts.json = true,
+                "--record" => opts.record = Some(value()?),
+                "--capture" => opts.capture = Some(value()?),
+                "--prelude" => opts.preludes.push(value()?),
+                "--host-dir" => opts.host_dir = Some(value()?),
+                "--stop-at-divergence" => opts.stop_at_divergence = true,
+                "--quiet" => opts.quiet = true,
+                "-h" | "--help" => opts.help = true,
+                _ => return Err(format!("unknown argument: {}", flag)),
+            }
+        }
+        if opts.command == Command::Serve && opts.is_batch() {
+            return Err("--script and --eval can't be used with serve".into());
+        }
+        if opts.json
+            && (matches!(opts.command, Command::Serve | Command::Replay) || opts.is_batch())
+        {
+            return Err("--json only applies to interactive sessions".into());
+        }
+        if opts.command == Command::Replay && opts.is_batch() {
+            return Err("--script and --eval can't be used with replay".into());
+        }
+        if opts.command == Command::Serve && opts.record.is_some() {
+            return Err("--record can't be used with serve; record on the clients".into());
+        }
+        if opts.command == Command::Connect && !opts.preludes.is_empty() {
+            return Err("--prelude can't be used with connect; give it to serve".into());
+        }
+        if opts.command == Command::Connect && opts.host_dir.is_some() {
+            return Err("--host-dir can't be used with connect; give it to serve".into());
+        }
+        if opts.command == Command::Connect && opts.capture.is_some() {
+            return Err("--capture can't be used with connect; give it to serve".into());
+        }
+        if opts.command == Command::Trace
+            && (opts.is_batch() || opts.json || opts.capture.is_some())
+        {
+            return Err("trace only decodes a capture".into());
+        }
+        if opts.stop_at_divergence && opts.command != Command::Replay {
+            return Err("--stop-at-divergence only applies to replay".into());
+        }
+        Ok(opts)
+    }
+
+    /// True if we evaluate the given sources and exit instead of
+    /// starting the REPL.
+    pub fn is_batch(&self) -> bool {
+        self.script.is_some() || !self.eval.is_empty()
+    }
+}
@@ -0,0 +1,109 @@
+//! Tab completion at the prompt. The kernel is asked what names exist
+//! (`Control::QueryNames`) the first time Tab is pressed after an
+//! evaluation, since any evaluation may have defined new ones.
+//!
+//! What a word completes to depends on where it is:
+//!
+//! - after `@`, the syscalls;
+//! - right after `(`, the special forms and the global bindings;
+//! - anywhere else, the global bindings.
+
+use std::time::Duration;
+
+use shared::{Control, FrameError, Framer, Message, MessageKind, Transport};
+
+use crate::editor::Completion;
+use crate::link;
+
+/// How long the kernel gets to list its names. It answers straight away
+/// between requests, so this only runs out if the link is in trouble.
+const NAMES_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// What the kernel says a symbol can name.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Names {
+    pub globals: Vec<String>,
+    pub specials: Vec<String>,
+    /// Without their `@`.
+    pub syscalls: Vec<String>,
+}
+
+/// Ask the kernel for its `Names`, echoing console output meanwhile.
+pub fn query_names<T: Transport>(framer: &mut Framer<T>) -> Result<Names, FrameError> {
+    framer.send_message(&Message::control(&Control::QueryNames))?;
+    loop {
+        let msg = match framer.recv_message_timeout(NAMES_TIMEOUT) {
+            Ok(m) => m,
+            Err(e) if e.is_corrupt() => continue,
+            Err(e) => return Err(e),
+        };
+        match msg.kind {
+            MessageKind::Control => {
+                if let Some(Control::Names {
+                    globals,
+                    specials,
+                    syscalls,
+                }) = Control::decode(&msg.body)
+                {
+                    return Ok(Names {
+                        globals,
+                        specials,
+                        syscalls,
+                    });
+                }
+            }
+            MessageKind::Output => link::print_output(&msg.body),
+            _ => {}
+        }
+    }
+}
+
+/// True for the characters that end a symbol (see `reader`).
+fn is_delimiter(c: char) -> bool {
+    c.is_whitespace() || matches!(c, '(' | ')' | '\'' | '`' | ',' | '"' | ';')
+}
+
+/// Complete the word `line` ends with, `line` being what precedes the
+/// cursor. Candidates are sorted and free of duplicates.
+pub fn complete(names: &Names, line: &str) -> Completion {
+    let mut start = line
+        .char_indices()
+        .rev()
+        .take_while(|&(_, c)| !is_delimiter(c))
+        .last()
+        .map_or(line.len(), |(i, _)| i);
+    let before = line[..start].trim_end().chars().next_back();
+    // `,@` splices the form after it
+    let splice = line[..start].ends_with(',');
+    let word = &line[start..];
+
+    let mut candidates: Vec<String> = match word.strip_prefix('@') {
+        Some(name) if !splice => names
+            .syscalls
+            .iter()
+            .filter(|s| s.starts_with(name))
+            .map(|s| format!("@{}", s))
+            .collect(),
+        Some(name) => {
+            start += 1;
+            prefixed(&names.globals, name)
+        }
+        None if before == Some('(') => {
+            let mut c = prefixed(&names.specials, word);
+            c.extend(prefixed(&names.globals, word));
+            c
+        }
+        None => prefixed(&names.globals, word),
+    };
+    candidates.sort();
+    candidates.dedup();
+    Completion { start, candidates }
+}
+
+fn prefixed(names: &[String], prefix: &str) -> Vec<String> {
+    names
+        .iter()
+        .filter(|n| n.starts_with(prefix))
+        .cloned()
+        .collect()
+}
@@ -0,0 +1,460 @@
+//! Where the REPL gets its lines from: a minimal line editor with
+//! persistent history when stdin is a terminal, or plain buffered reads
+//! otherwise (pipes, Emacs, tests).
+//!
+//! The editor understands the usual readline keys: arrows, Home/End,
+//! Backspace/Delete, Ctrl-A/E/B/F (movement), Ctrl-K/U/W (kill to end,
+//! to start, previous word), Ctrl-P/N (history), Ctrl-C (discard input)
+//! and Ctrl-D (end of input on an empty line). Tab completes the word
+//! before the cursor as far as it is unambiguous; pressed again, it lists
+//! the candidates.
+
+use std::fs::{self, OpenOptions};
+use std::io::{self, BufRead, Read, Write};
+use std::path::PathBuf;
+
+/// Which prompt to show before a line.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Prompt {
+    /// Start of a new form.
+    Primary,
+    /// More lines of an unfinished form.
+    Continuation,
+}
+
+pub const PRIMARY_PROMPT: &str = "> ";
+pub const CONTINUATION_PROMPT: &str = ".. ";
+
+/// What the word before the cursor could be completed to.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Completion {
+    /// Byte offset of the start of the word.
+    pub start: usize,
+    pub candidates: Vec<String>,
+}
+
+/// A source of input lines for the REPL.
+pub trait LineSource {
+    /// Read one line (without its newline), showing `prompt` on `out`.
+    /// Returns `Ok(None)` at end of input; an `Interrupted` error means
+    /// the user asked to throw away what they have typed so far.
+    /// `complete` is given the line up to the cursor when the user asks
+    /// for completion.
+    fn read_line(
+        &mut self,
+        prompt: Prompt,
+        out: &mut dyn Write,
+        complete: &mut dyn FnMut(&str) -> Completion,
+    ) -> io::Result<Option<String>>;
+
+    /// The lines read since the last call made up `entry` (a whole form
+    /// or command, its lines joined with newlines), for the history.
+    fn entered(&mut self, _entry: &str) {}
+}
+
+/// Plain line reads. Only the primary prompt is printed, so programs
+/// driving the REPL over a pipe can keep waiting for `"\n> "`.
+impl<R: BufRead> LineSource for R {
+    fn read_line(
+        &mut self,
+        prompt: Prompt,
+        out: &mut dyn Write,
+        _complete: &mut dyn FnMut(&str) -> Completion,
+    ) -> io::Result<Option<String>> {
+        if prompt == Prompt::Primary {
+            write!(out, "{}", PRIMARY_PROMPT)?;
+            out.flush()?;
+        }
+        let mut line = String::new();
+        if BufRead::read_line(self, &mut line)? == 0 {
+            return Ok(None);
+        }
+        let len = line.trim_end_matches(['\n', '\r']).len();
+        line.truncate(len);
+        Ok(Some(line))
+    }
+}
+
+/// Most entries kept in the history file.
+const HISTORY_LEN: usize = 1000;
+
+/// Previously entered forms, oldest first, mirrored to a file. An entry
+/// can span lines; in the file, the lines after its first start with a
+/// tab, which the editor never puts in a line (Tab completes).
+pub struct History {
+    entries: Vec<String>,
+    path: Option<PathBuf>,
+}
+
+impl History {
+    /// `$LISPI_HISTORY`, or `~/.lispi_history`.
+    pub fn default_path() -> Option<PathBuf> {
+        if let Some(p) = std::env::var_os("LISPI_HISTORY") {
+            return Some(p.into());
+        }
+        std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".lispi_history"))
+    }
+
+    /// Load history from `path` (a missing file is an empty history).
+    /// With no path, history lasts for this session only.
+    pub fn load(path: Option<PathBuf>) -> Self {
+        let mut entries: Vec<String> = Vec::new();
+        let text = path.as_ref().and_then(|p| fs::read_to_string(p).ok());
+        for line in text.iter().flat_map(|s| s.lines()) {
+            match (line.strip_prefix('\t'), entries.last_mut()) {
+                (Some(more), Some(entry)) => {
+                    entry.push('\n');
+                    entry.push_str(more);
+                }
+                _ => entries.push(line.to_string()),
+            }
+        }
+        if entries.len() > HISTORY_LEN {
+            entries.drain(..entries.len() - HISTORY_LEN);
+            if let Some(p) = &path {
+                let lines: String = entries.iter().map(|e| Self::encode(e) + "\n").collect();
+                let _ = fs::write(p, lines);
+            }
+        }
+        Self { entries, path }
+    }
+
+    pub fn entries(&self) -> &[String] {
+        &self.entries
+    }
+
+    /// Record `entry`, skipping blanks and immediate repeats.
+    pub fn add(&mut self, entry: &str) {
+        if entry.trim().is_empty() || self.entries.last().is_some_and(|e| e == entry) {
+            return;
+        }
+        self.entries.push(entry.to_string());
+        if let Some(p) = &self.path
+            && let Ok(mut f) = OpenOptions::new().create(true).append(true).open(p)
+        {
+            let _ = writeln!(f, "{}", Self::encode(entry));
+        }
+    }
+
+    /// `entry` as it goes in the file.
+    fn encode(entry: &str) -> String {
+        entry.replace('\n', "\n\t")
+    }
+}
+
+/// Puts the terminal on stdin in raw mode until dropped.
+struct RawMode {
+    saved: libc::termios,
+}
+
+impl RawMode {
+    fn enable() -> io::Result<Self> {
+        unsafe {
+            let mut saved: libc::termios = std::mem::zeroed();
+            if libc::tcgetattr(libc::STDIN_FILENO, &mut saved) != 0 {
+                return Err(io::Error::last_os_error());
+            }
+            let mut raw = saved;
+            raw.c_iflag &= !(libc::ICRNL | libc::IXON | libc::BRKINT | libc::ISTRIP);
+            raw.c_lflag &= !(libc::ECHO | libc::ICANON | libc::ISIG | libc::IEXTEN);
+            raw.c_cc[libc::VMIN] = 1;
+            raw.c_cc[libc::VTIME] = 0;
+            if libc::tcsetattr(libc::STDIN_FILENO, libc::TCSAFLUSH, &raw) != 0 {
+                return Err(io::Error::last_os_error());
+            }
+            Ok(Self { saved })
+        }
+    }
+}
+
+impl Drop for RawMode {
+    fn drop(&mut self) {
+        unsafe {
+            libc::tcsetattr(libc::STDIN_FILENO, libc::TCSAFLUSH, &self.saved);
+        }
+    }
+}
+
+/// True if stdin is a terminal the editor can drive.
+pub fn stdin_is_tty() -> bool {
+    unsafe { libc::isatty(libc::STDIN_FILENO) == 1 }
+}
+
+/// Interactive line editor on the terminal attached to stdin.
+pub struct Editor {
+    history: History,
+}
+
+enum Key {
+    Char(char),
+    Enter,
+    Backspace,
+    Delete,
+    Left,
+    Right,
+    Home,
+    End,
+    Up,
+    Down,
+    KillToEnd,
+    KillToStart,
+    KillWord,
+    Tab,
+    Interrupt,
+    Eof,
+    Ignored,
+}
+
+impl Editor {
+    pub fn new(history: History) -> Self {
+        Self { history }
+    }
+
+    fn read_byte(input: &mut impl Read) -> io::Result<Option<u8>> {
+        let mut b = [0u8];
+        loop {
+            match input.read(&mut b) {
+                Ok(0) => return Ok(None),
+                Ok(_) => return Ok(Some(b[0])),
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    fn read_key(input: &mut impl Read) -> io::Result<Key> {
+        let Some(b) = Self::read_byte(input)? else {
+            return Ok(Key::Eof);
+        };
+        Ok(match b {
+            b'\r' | b'\n' => Key::Enter,
+            0x7F | 0x08 => Key::Backspace,
+            0x01 => Key::Home,
+            0x02 => Key::Left,
+            0x03 => Key::Interrupt,
+            0x04 => Key::Eof,
+            0x05 => Key::End,
+            0x06 => Key::Right,
+            b'\t' => Key::Tab,
+            0x0B => Key::KillToEnd,
+            0x0E => Key::Down,
+            0x10 => Key::Up,
+            0x15 => Key::KillToStart,
+            0x17 => Key::KillWord,
+            0x1B => Self::read_escape(input)?,
+            b if b < 0x20 => Key::Ignored,
+            b => {
+                // collect the rest of a UTF-8 sequence
+                let len = match b {
+                    0xC0..=0xDF => 2,
+                    0xE0..=0xEF => 3,
+                    0xF0..=0xF7 => 4,
+                    _ => 1,
+                };
+                let mut bytes = vec![b];
+                for _ in 1..len {
+                    match Self::read_byte(input)? {
+                        Some(c) => bytes.push(c),
+                        None => break,
+                    }
+                }
+                match std::str::from_utf8(&bytes)
+                    .ok()
+                    .and_then(|s| s.chars().next())
+                {
+                    Some(c) => Key::Char(c),
+                    None => Key::Ignored,
+                }
+            }
+        })
+    }
+
+    /// Decode the rest of an `ESC [ ...` or `ESC O ...` sequence.
+    fn read_escape(input: &mut impl Read) -> io::Result<Key> {
+        let Some(intro) = Self::read_byte(input)? else {
+            return Ok(Key::Ignored);
+        };
+        if intro != b'[' && intro != b'O' {
+            return Ok(Key::Ignored);
+        }
+        let mut param = 0u32;
+        loop {
+            let Some(b) = Self::read_byte(input)? else {
+                return Ok(Key::Ignored);
+            };
+            return Ok(match b {
+                b'0'..=b'9' => {
+                    param = param * 10 + (b - b'0') as u32;
+                    continue;
+                }
+                b';' => continue,
+                b'A' => Key::Up,
+                b'B' => Key::Down,
+                b'C' => Key::Right,
+                b'D' => Key::Left,
+                b'H' => Key::Home,
+                b'F' => Key::End,
+                b'~' => match param {
+                    1 | 7 => Key::Home,
+                    3 => Key::Delete,
+                    4 | 8 => Key::End,
+                    _ => Key::Ignored,
+                },
+                _ => Key::Ignored,
+            });
+        }
+    }
+
+    fn redraw(out: &mut dyn Write, prompt: &str, buf: &[char], cursor: usize) -> io::Result<()> {
+        // a recalled entry's line breaks are edited as one line
+        let line: String = buf
+            .iter()
+            .map(|&c| if c == '\n' { ' ' } else { c })
+            .collect();
+        write!(out, "\r{}{}\x1b[K", prompt, line)?;
+        let col = prompt.chars().count() + cursor;
+        write!(out, "\r")?;
+        if col > 0 {
+            write!(out, "\x1b[{}C", col)?;
+        }
+        out.flush()
+    }
+
+    /// Complete the word before `cursor`: extend it by what all the
+    /// candidates share, followed by a space if there is only one. With
+    /// nothing to add, `list` prints the candidates (else we beep).
+    fn complete(
+        out: &mut dyn Write,
+        buf: &mut Vec<char>,
+        cursor: &mut usize,
+        complete: &mut dyn FnMut(&str) -> Completion,
+        list: bool,
+    ) -> io::Result<()> {
+        let line: String = buf[..*cursor].iter().collect();
+        let Completion { start, candidates } = complete(&line);
+        let start = line[..start].chars().count();
+        let Some(first) = candidates.first() else {
+            return write!(out, "\x07");
+        };
+        let mut common: Vec<char> = first.chars().collect();
+        for c in &candidates[1..] {
+            let shared = common.iter().zip(c.chars()).take_while(|(a, b)| **a == *b);
+            common.truncate(shared.count());
+        }
+        if candidates.len() == 1 {
+            common.push(' ');
+        }
+        if common.len() > *cursor - start {
+            buf.splice(start..*cursor, common.iter().copied());
+            *cursor = start + common.len();
+        } else if list {
+            write!(out, "\r\n{}\r\n", candidates.join("  "))?;
+        } else {
+            write!(out, "\x07")?;
+        }
+        Ok(())
+    }
+}
+
+impl LineSource for Editor {
+    fn read_line(
+        &mut self,
+        prompt: Prompt,
+        out: &mut dyn Write,
+        complete: &mut dyn FnMut(&str) -> Completion,
+    ) -> io::Result<Option<String>> {
+        let prompt = match prompt {
+            Prompt::Primary => PRIMARY_PROMPT,
+            Prompt::Continuation => CONTINUATION_PROMPT,
+        };
+        let _raw = RawMode::enable()?;
+        let mut input = io::stdin().lock();
+
+        let mut buf: Vec<char> = Vec::new();
+        let mut cursor = 0;
+        // index into history while browsing; `entries().len()` is the
+        // line being edited, saved in `draft`
+        let mut browsing = self.history.entries().len();
+        let mut draft: Vec<char> = Vec::new();
+        // whether the last key was a Tab
+        let mut tabbed = false;
+
+        Self::redraw(out, prompt, &buf, cursor)?;
+        loop {
+            let key = Self::read_key(&mut input)?;
+            let repeat = std::mem::replace(&mut tabbed, matches!(key, Key::Tab));
+            match key {
+                Key::Enter => {
+                    write!(out, "\r\n")?;
+                    out.flush()?;
+                    return Ok(Some(buf.into_iter().collect()));
+                }
+                Key::Eof if buf.is_empty() => {
+                    write!(out, "\r\n")?;
+                    return Ok(None);
+                }
+                Key::Eof | Key::Delete => {
+                    if cursor < buf.len() {
+                        buf.remove(cursor);
+                    }
+                }
+                Key::Interrupt => {
+                    write!(out, "^C\r\n")?;
+                    out.flush()?;
+                    return Err(io::ErrorKind::Interrupted.into());
+                }
+                Key::Char(c) => {
+                    buf.insert(cursor, c);
+                    cursor += 1;
+                }
+                Key::Backspace if cursor > 0 => {
+                    cursor -= 1;
+                    buf.remove(cursor);
+                }
+                Key::Left if cursor > 0 => cursor -= 1,
+                Key::Right if cursor < buf.len() => cursor += 1,
+                Key::Home => cursor = 0,
+                Key::End => cursor = buf.len(),
+                Key::KillToEnd => buf.truncate(cursor),
+                Key::KillToStart => {
+                    buf.drain(..cursor);
+                    cursor = 0;
+                }
+                Key::KillWord => {
+                    let mut start = cursor;
+                    while start > 0 && buf[start - 1].is_whitespace() {
+                        start -= 1;
+                    }
+                    while start > 0 && !buf[start - 1].is_whitespace() {
+                        start -= 1;
+                    }
+                    buf.drain(start..cursor);
+                    cursor = start;
+                }
+                Key::Tab => Self::complete(out, &mut buf, &mut cursor, complete, repeat)?,
+                Key::Up if browsing > 0 => {
+                    if browsing == self.history.entries().len() {
+                        draft = buf.clone();
+                    }
+                    browsing -= 1;
+                    buf = self.history.entries()[browsing].chars().collect();
+                    cursor = buf.len();
+                }
+                Key::Down if browsing < self.history.entries().len() => {
+                    browsing += 1;
+                    buf = match self.history.entries().get(browsing) {
+                        Some(l) => l.chars().collect(),
+                        None => draft.clone(),
+                    };
+                    cursor = buf.len();
+                }
+                _ => {}
+            }
+            Self::redraw(out, prompt, &buf, cursor)?;
+        }
+    }
+
+    fn entered(&mut self, entry: &str) {
+        self.history.add(entry);
+    }
+}
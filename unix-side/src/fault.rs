@@ -0,0 +1,88 @@
+//! A `Transport` wrapper that damages outgoing bytes, for exercising the
+//! framer's retransmission and resynchronization paths.
+//!
+//! Faults are drawn from a seeded xorshift generator, so a failing test
+//! replays exactly.
+
+use std::thread;
+use std::time::Duration;
+
+use shared::{Transport, TransportError};
+
+/// Per-byte fault probabilities, each in `0.0..=1.0`.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Faults {
+    /// Byte is not sent at all.
+    pub drop: f64,
+    /// Byte is sent twice.
+    pub duplicate: f64,
+    /// Byte is sent with one random bit flipped.
+    pub corrupt: f64,
+    /// Sending the byte is preceded by a pause of `delay`.
+    pub delay_rate: f64,
+    pub delay: Duration,
+}
+
+pub struct Faulty<T: Transport> {
+    pub inner: T,
+    /// Faults applied to bytes written from now on. Reads pass through
+    /// untouched; wrap the other end to damage the reverse direction.
+    pub faults: Faults,
+    rng: u64,
+}
+
+impl<T: Transport> Faulty<T> {
+    pub fn new(inner: T, faults: Faults, seed: u64) -> Self {
+        Self {
+            inner,
+            faults,
+            // xorshift gets stuck at zero
+            rng: seed | 1,
+        }
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        let mut x = self.rng;
+        x ^= x << 13;
+        x ^= x >> 7;
+        x ^= x << 17;
+        self.rng = x;
+        x
+    }
+
+    /// True with probability `p`.
+    fn roll(&mut self, p: f64) -> bool {
+        // top 53 bits as a uniform float in [0, 1)
+        let x = (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64;
+        p > 0.0 && x < p
+    }
+}
+
+impl<T: Transport> Transport for Faulty<T> {
+    fn put8(&mut self, b: u8) {
+        let f = self.faults;
+        if self.roll(f.delay_rate) {
+            thread::sleep(f.delay);
+        }
+        if self.roll(f.drop) {
+            return;
+        }
+        let b = if self.roll(f.corrupt) {
+            b ^ (1 << (self.next_u64() % 8))
+        } else {
+            b
+        };
+        self.inner.put8(b);
+        if self.roll(f.duplicate) {
+            self.inner.put8(b);
+        }
+    }
+
+    fn get8(&mut self, timeout: Option<Duration>) -> Result<u8, TransportError> {
+        self.inner.get8(timeout)
+    }
+
+    fn flush(&mut self) {
+        self.inner.flush();
+    }
+}
@@ -0,0 +1,103 @@
+//! The directory `--host-dir` lends the kernel (see `shared::host`).
+//! `link::await_reply_with` answers the `@host/...` syscalls' requests
+//! from here while an evaluation runs.
+//!
+//! Paths from the Pi are relative to the directory. Absolute paths and
+//! `..` are refused, and so is anything that reaches outside through a
+//! symlink. Writing a file creates the directories above it.
+
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Component, Path, PathBuf};
+use std::sync::Mutex;
+
+use shared::Control;
+use shared::host::{HostError, max_file_len};
+
+/// The shared directory, canonicalized.
+static DIR: Mutex<Option<PathBuf>> = Mutex::new(None);
+
+/// Serve files from `dir` from now on.
+pub fn share(dir: &Path) -> io::Result<()> {
+    let dir = dir.canonicalize()?;
+    if !dir.is_dir() {
+        return Err(io::Error::new(
+            io::ErrorKind::NotADirectory,
+            format!("{} is not a directory", dir.display()),
+        ));
+    }
+    *DIR.lock().unwrap() = Some(dir);
+    Ok(())
+}
+
+/// The answer to `request` if it is one of the kernel's host requests,
+/// for a link whose frames hold up to `max_payload` bytes.
+pub fn answer(request: &Control, max_payload: u32) -> Option<Control> {
+    let dir = DIR.lock().unwrap().clone();
+    let reply = match request {
+        Control::HostRead { path } => dir
+            .ok_or(HostError::NoDirectory)
+            .and_then(|dir| read(&dir, path, max_file_len(max_payload))),
+        Control::HostWrite { path, data, append } => dir
+            .ok_or(HostError::NoDirectory)
+            .and_then(|dir| write(&dir, path, data, *append))
+            .map(|()| Vec::new()),
+        _ => return None,
+    };
+    Some(Control::HostReply(reply))
+}
+
+/// Where `path` is under `dir`, which must be canonical.
+pub fn resolve(dir: &Path, path: &str) -> Result<PathBuf, HostError> {
+    let relative = Path::new(path);
+    if path.is_empty()
+        || !relative
+            .components()
+            .all(|c| matches!(c, Component::Normal(_) | Component::CurDir))
+    {
+        return Err(HostError::Outside);
+    }
+    let full = dir.join(relative);
+    // the part that already exists must stay inside once symlinks are
+    // followed
+    let existing = full
+        .ancestors()
+        .find(|p| p.symlink_metadata().is_ok())
+        .unwrap_or(dir);
+    let real = existing.canonicalize().map_err(from_io)?;
+    if !real.starts_with(dir) {
+        return Err(HostError::Outside);
+    }
+    Ok(full)
+}
+
+fn read(dir: &Path, path: &str, max: usize) -> Result<Vec<u8>, HostError> {
+    let full = resolve(dir, path)?;
+    if fs::metadata(&full).map_err(from_io)?.len() > max as u64 {
+        return Err(HostError::TooLarge);
+    }
+    fs::read(full).map_err(from_io)
+}
+
+fn write(dir: &Path, path: &str, data: &[u8], append: bool) -> Result<(), HostError> {
+    let full = resolve(dir, path)?;
+    if let Some(parent) = full.parent() {
+        fs::create_dir_all(parent).map_err(from_io)?;
+    }
+    OpenOptions::new()
+        .create(true)
+        .write(true)
+        .append(append)
+        .truncate(!append)
+        .open(full)
+        .and_then(|mut f| f.write_all(data))
+        .map_err(from_io)
+}
+
+fn from_io(e: io::Error) -> HostError {
+    match e.kind() {
+        io::ErrorKind::NotFound => HostError::NotFound,
+        io::ErrorKind::PermissionDenied => HostError::Denied,
+        _ => HostError::Io,
+    }
+}
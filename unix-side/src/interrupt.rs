@@ -0,0 +1,68 @@
+//! Ctrl-C while the Pi is evaluating. Instead of killing unix-side, the
+//! first Ctrl-C asks the kernel to abandon the evaluation
+//! (`Control::Interrupt`), which then fails with `interrupted`. A second
+//! Ctrl-C before the reply arrives, or one while nothing is running,
+//! kills us as usual, for when the kernel is wedged somewhere it never
+//! looks for interrupts.
+//!
+//! `link::await_reply_with` does the sending; this module only tracks
+//! whether an evaluation is running and whether it should be stopped.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static EVALUATING: AtomicBool = AtomicBool::new(false);
+static REQUESTED: AtomicBool = AtomicBool::new(false);
+
+extern "C" fn on_sigint(_: libc::c_int) {
+    if !request() {
+        // only async-signal-safe calls in here
+        unsafe {
+            libc::signal(libc::SIGINT, libc::SIG_DFL);
+            libc::raise(libc::SIGINT);
+        }
+    }
+}
+
+/// Route Ctrl-C (SIGINT) through `request`.
+pub fn install() {
+    let handler: extern "C" fn(libc::c_int) = on_sigint;
+    unsafe {
+        libc::signal(libc::SIGINT, handler as libc::sighandler_t);
+    }
+}
+
+/// Ask for the running evaluation to be stopped. Returns false if none
+/// is running or it has already been asked.
+pub fn request() -> bool {
+    EVALUATING.load(Ordering::SeqCst) && !REQUESTED.swap(true, Ordering::SeqCst)
+}
+
+/// True if the running evaluation should be stopped.
+pub(crate) fn requested() -> bool {
+    REQUESTED.load(Ordering::SeqCst)
+}
+
+/// Marks an evaluation as running until dropped. Nests, for the
+/// preludes re-sent in the middle of one.
+pub(crate) struct Evaluating {
+    outer: bool,
+}
+
+impl Evaluating {
+    pub(crate) fn start() -> Self {
+        let outer = EVALUATING.swap(true, Ordering::SeqCst);
+        if !outer {
+            REQUESTED.store(false, Ordering::SeqCst);
+        }
+        Evaluating { outer }
+    }
+}
+
+impl Drop for Evaluating {
+    fn drop(&mut self) {
+        if !self.outer {
+            EVALUATING.store(false, Ordering::SeqCst);
+            REQUESTED.store(false, Ordering::SeqCst);
+        }
+    }
+}
@@ -0,0 +1,388 @@
+//! `--json` mode: a line-oriented protocol for editor frontends, in place
+//! of the prompt-and-text REPL.
+//!
+//! Each input line is a JSON object giving the source to evaluate and,
+//! optionally, an id (any number or string) to pair the answer with:
+//!
+//! ```text
+//! {"id": 7, "code": "(+ 1 2)"}
+//! ```
+//!
+//! Each output line is a JSON object carrying the request's id (or the
+//! sequence number we gave it, if it had none), a `kind` of `value`,
+//! `error` or `output` (console output printed while the request ran),
+//! the `text`, and for values and errors the round-trip time in
+//! `elapsed_ms`:
+//!
+//! ```text
+//! {"id":7,"kind":"output","text":"hello\n"}
+//! {"id":7,"kind":"value","text":"3","elapsed_ms":4.21}
+//! ```
+//!
+//! One `{"id":null,"kind":"ready","text":""}` line is written before the
+//! first request is read. Lines that aren't valid requests are answered
+//! with an `error`, whose id is `null` if none could be read.
+
+use std::fmt;
+use std::io::{BufRead, Write};
+use std::time::{Duration, Instant};
+
+use shared::{Framer, MessageKind, Transport};
+
+use crate::link;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Value>),
+    Object(Vec<(String, Value)>),
+}
+
+impl Value {
+    /// Parse a complete JSON document.
+    pub fn parse(src: &str) -> Result<Value, String> {
+        let mut p = Parser {
+            src,
+            i: 0,
+            depth: 0,
+        };
+        let v = p.value()?;
+        p.skip_ws();
+        if p.i != src.len() {
+            return Err(format!("trailing characters at offset {}", p.i));
+        }
+        Ok(v)
+    }
+
+    /// The value of `key`, if this is an object that has it.
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        match self {
+            Value::Object(fields) => fields.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+}
+
+fn write_str(f: &mut fmt::Formatter<'_>, s: &str) -> fmt::Result {
+    f.write_str("\"")?;
+    for c in s.chars() {
+        match c {
+            '"' => f.write_str("\\\"")?,
+            '\\' => f.write_str("\\\\")?,
+            '\n' => f.write_str("\\n")?,
+            '\r' => f.write_str("\\r")?,
+            '\t' => f.write_str("\\t")?,
+            c if (c as u32) < 0x20 => write!(f, "\\u{:04x}", c as u32)?,
+            c => write!(f, "{}", c)?,
+        }
+    }
+    f.write_str("\"")
+}
+
+/// Compact JSON, with no newlines.
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Value::Null => f.write_str("null"),
+            Value::Bool(b) => write!(f, "{}", b),
+            Value::Number(n) if !n.is_finite() => f.write_str("null"),
+            Value::Number(n) => write!(f, "{}", n),
+            Value::String(s) => write_str(f, s),
+            Value::Array(items) => {
+                f.write_str("[")?;
+                for (i, v) in items.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(",")?;
+                    }
+                    write!(f, "{}", v)?;
+                }
+                f.write_str("]")
+            }
+            Value::Object(fields) => {
+                f.write_str("{")?;
+                for (i, (k, v)) in fields.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(",")?;
+                    }
+                    write_str(f, k)?;
+                    write!(f, ":{}", v)?;
+                }
+                f.write_str("}")
+            }
+        }
+    }
+}
+
+/// How deep arrays and objects may nest; the parser recurses, and a
+/// line of brackets mustn't run it out of stack.
+const MAX_DEPTH: usize = 128;
+
+struct Parser<'a> {
+    src: &'a str,
+    i: usize,
+    /// Arrays and objects open around the current value.
+    depth: usize,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<u8> {
+        self.src.as_bytes().get(self.i).copied()
+    }
+
+    fn skip_ws(&mut self) {
+        while matches!(self.peek(), Some(b' ' | b'\t' | b'\n' | b'\r')) {
+            self.i += 1;
+        }
+    }
+
+    fn expect(&mut self, c: u8) -> Result<(), String> {
+        self.skip_ws();
+        if self.peek() != Some(c) {
+            return Err(format!("expected '{}' at offset {}", c as char, self.i));
+        }
+        self.i += 1;
+        Ok(())
+    }
+
+    fn keyword(&mut self, word: &str, v: Value) -> Result<Value, String> {
+        if !self.src[self.i..].starts_with(word) {
+            return Err(format!("unexpected character at offset {}", self.i));
+        }
+        self.i += word.len();
+        Ok(v)
+    }
+
+    fn value(&mut self) -> Result<Value, String> {
+        self.skip_ws();
+        match self.peek() {
+            None => Err("unexpected end of input".into()),
+            Some(b'n') => self.keyword("null", Value::Null),
+            Some(b't') => self.keyword("true", Value::Bool(true)),
+            Some(b'f') => self.keyword("false", Value::Bool(false)),
+            Some(b'"') => self.string().map(Value::String),
+            Some(b'[') => self.nested(Self::array),
+            Some(b'{') => self.nested(Self::object),
+            Some(_) => self.number(),
+        }
+    }
+
+    /// Parse an array or object with `f`, one level further in.
+    fn nested(&mut self, f: fn(&mut Self) -> Result<Value, String>) -> Result<Value, String> {
+        if self.depth == MAX_DEPTH {
+            return Err("nesting too deep".into());
+        }
+        self.depth += 1;
+        let v = f(self);
+        self.depth -= 1;
+        v
+    }
+
+    fn array(&mut self) -> Result<Value, String> {
+        self.i += 1;
+        let mut items = Vec::new();
+        self.skip_ws();
+        if self.peek() == Some(b']') {
+            self.i += 1;
+            return Ok(Value::Array(items));
+        }
+        loop {
+            items.push(self.value()?);
+            self.skip_ws();
+            match self.peek() {
+                Some(b',') => self.i += 1,
+                _ => {
+                    self.expect(b']')?;
+                    return Ok(Value::Array(items));
+                }
+            }
+        }
+    }
+
+    fn object(&mut self) -> Result<Value, String> {
+        self.i += 1;
+        let mut fields = Vec::new();
+        self.skip_ws();
+        if self.peek() == Some(b'}') {
+            self.i += 1;
+            return Ok(Value::Object(fields));
+        }
+        loop {
+            self.skip_ws();
+            if self.peek() != Some(b'"') {
+                return Err(format!("expected a key at offset {}", self.i));
+            }
+            let key = self.string()?;
+            self.expect(b':')?;
+            fields.push((key, self.value()?));
+            self.skip_ws();
+            match self.peek() {
+                Some(b',') => self.i += 1,
+                _ => {
+                    self.expect(b'}')?;
+                    return Ok(Value::Object(fields));
+                }
+            }
+        }
+    }
+
+    fn number(&mut self) -> Result<Value, String> {
+        let start = self.i;
+        while matches!(
+            self.peek(),
+            Some(b'0'..=b'9' | b'-' | b'+' | b'.' | b'e' | b'E')
+        ) {
+            self.i += 1;
+        }
+        if self.i == start {
+            return Err(format!("unexpected character at offset {}", start));
+        }
+        self.src[start..self.i]
+            .parse()
+            .map(Value::Number)
+            .map_err(|_| format!("bad number at offset {}", start))
+    }
+
+    fn hex4(&mut self) -> Result<u32, String> {
+        let digits = self
+            .src
+            .get(self.i..self.i + 4)
+            .ok_or("truncated \\u escape")?;
+        let v = u32::from_str_radix(digits, 16).map_err(|_| "bad \\u escape")?;
+        self.i += 4;
+        Ok(v)
+    }
+
+    /// A string literal; `self.i` is at its opening quote.
+    fn string(&mut self) -> Result<String, String> {
+        self.i += 1;
+        let mut s = String::new();
+        loop {
+            // copy everything up to the next quote or escape as is
+            let run = self.src[self.i..]
+                .find(['"', '\\'])
+                .ok_or("unterminated string")?;
+            s.push_str(&self.src[self.i..self.i + run]);
+            self.i += run;
+            if self.peek() == Some(b'"') {
+                self.i += 1;
+                return Ok(s);
+            }
+            self.i += 1;
+            let esc = self.peek().ok_or("unterminated string")?;
+            self.i += 1;
+            match esc {
+                b'"' => s.push('"'),
+                b'\\' => s.push('\\'),
+                b'/' => s.push('/'),
+                b'b' => s.push('\u{8}'),
+                b'f' => s.push('\u{c}'),
+                b'n' => s.push('\n'),
+                b'r' => s.push('\r'),
+                b't' => s.push('\t'),
+                b'u' => {
+                    let mut c = self.hex4()?;
+                    // a high surrogate must be followed by its low half
+                    if (0xD800..0xDC00).contains(&c) && self.src[self.i..].starts_with("\\u") {
+                        self.i += 2;
+                        let low = self.hex4()?;
+                        c = match low {
+                            0xDC00..0xE000 => 0x10000 + ((c - 0xD800) << 10) + (low - 0xDC00),
+                            _ => 0xFFFD,
+                        };
+                    }
+                    s.push(char::from_u32(c).unwrap_or(char::REPLACEMENT_CHARACTER));
+                }
+                _ => return Err(format!("bad escape at offset {}", self.i - 1)),
+            }
+        }
+    }
+}
+
+/// Read a request line: its id, if it has one, and the source.
+/// Failures come with whatever id could be found.
+fn parse_request(line: &str) -> Result<(Option<Value>, String), (Value, String)> {
+    let req = Value::parse(line).map_err(|e| (Value::Null, e))?;
+    let id = match req.get("id") {
+        None | Some(Value::Null) => None,
+        Some(id @ (Value::Number(_) | Value::String(_))) => Some(id.clone()),
+        Some(_) => return Err((Value::Null, "id must be a number or a string".into())),
+    };
+    match req.get("code") {
+        Some(Value::String(code)) => Ok((id, code.clone())),
+        _ => Err((
+            id.unwrap_or(Value::Null),
+            "expected an object with a \"code\" string".into(),
+        )),
+    }
+}
+
+/// Write one event line.
+fn emit<W: Write>(out: &mut W, id: &Value, kind: &str, text: &str, elapsed: Option<Duration>) {
+    let mut fields = vec![
+        ("id".to_string(), id.clone()),
+        ("kind".to_string(), Value::String(kind.into())),
+        ("text".to_string(), Value::String(text.into())),
+    ];
+    if let Some(t) = elapsed {
+        // microsecond resolution is plenty
+        let ms = (t.as_secs_f64() * 1e6).round() / 1e3;
+        fields.push(("elapsed_ms".to_string(), Value::Number(ms)));
+    }
+    let _ = writeln!(out, "{}", Value::Object(fields));
+    let _ = out.flush();
+}
+
+/// Answer request lines from `input` with event lines on `out` until
+/// input runs out or the link is lost.
+pub fn run<T: Transport, R: BufRead, W: Write>(framer: &mut Framer<T>, input: R, out: &mut W) {
+    let mut next_id: u32 = 1;
+    emit(out, &Value::Null, "ready", "", None);
+
+    for line in input.lines() {
+        let line = match line {
+            Ok(l) => l,
+            Err(e) => {
+                eprintln!("read error: {}", e);
+                break;
+            }
+        };
+        if line.trim().is_empty() {
+            continue;
+        }
+        let (id, code) = match parse_request(&line) {
+            Ok(req) => req,
+            Err((id, e)) => {
+                emit(out, &id, "error", &format!("bad request: {}", e), None);
+                continue;
+            }
+        };
+
+        let wire_id = next_id;
+        next_id = next_id.wrapping_add(1).max(1);
+        let id = id.unwrap_or(Value::Number(wire_id as f64));
+
+        let start = Instant::now();
+        let reply = link::eval_with(framer, wire_id, &code, &mut |bytes| {
+            emit(out, &id, "output", &String::from_utf8_lossy(bytes), None)
+        });
+        let elapsed = start.elapsed();
+        match reply {
+            Ok(reply) => {
+                let kind = match reply.kind {
+                    MessageKind::EvalError => "error",
+                    _ => "value",
+                };
+                let text = String::from_utf8_lossy(&reply.body);
+                emit(out, &id, kind, &text, Some(elapsed));
+            }
+            Err(e) if e.is_fatal() => {
+                emit(out, &id, "error", &format!("link lost: {}", e), None);
+                break;
+            }
+            Err(e) => emit(out, &id, "error", &format!("send failed: {}", e), None),
+        }
+    }
+}
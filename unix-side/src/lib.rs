@@ -0,0 +1,26 @@
+//! Host side of lispi: the tty link to the Pi, the session and transfer
+//! logic on top of `shared::Framer`, the REPL, and the socket server that
+//! lets several clients share one Pi. `main.rs` is a thin wrapper; the
+//! in-process transports (`loopback`, `pty`, `fault`) exist so all of
+//! this can be exercised without a Pi attached.
+
+pub mod batch;
+pub mod bootloader;
+pub mod cli;
+pub mod complete;
+pub mod editor;
+pub mod fault;
+pub mod host;
+pub mod interrupt;
+pub mod json;
+pub mod link;
+pub mod loopback;
+pub mod pty;
+pub mod reader;
+pub mod repl;
+pub mod server;
+pub mod socket;
+pub mod trace;
+pub mod transcript;
+pub mod transfer;
+pub mod tty;
@@ -0,0 +1,391 @@
+//! Session management on top of the framer: receiving replies, the
+//! connect-time handshake and the baud-rate switch that follows it, and
+//! reconnecting after the Pi reboots.
+//!
+//! A kernel announces every boot with a `Control::Boot`. If one turns up
+//! while we wait for a reply, the Image our request was evaluated in (if
+//! it was at all) is gone: we redo the handshake, re-send the `--prelude`
+//! files, and answer the request with an error unless the new kernel
+//! replied to it. A Pi that rebooted while we weren't listening has gone
+//! back to `BAUD_RATE`, so our next send isn't acked; `eval_with` then
+//! drops back to that rate and tries the handshake before giving up.
+//!
+//! The kernel may also ask for host files while it evaluates (see
+//! `host`); those requests are answered here too.
+
+use std::fs;
+use std::io::{self, Write};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use shared::control::feature;
+use shared::{Control, FrameError, Framer, Hello, Message, MessageKind, Transport};
+
+use crate::batch::{self, BatchError};
+use crate::transcript;
+use crate::tty::{self, Tty};
+use crate::{host, interrupt};
+
+/// Wait for the next message from the Pi. Corrupted or truncated frames
+/// are reported and skipped (the framer resyncs on the next sync barrier
+/// and the Pi retransmits anything we didn't ack); a timeout means the Pi
+/// went quiet mid-frame, either because the line lost bytes or because
+/// it reset.
+pub fn recv_message<T: Transport>(framer: &mut Framer<T>) -> Result<Message, FrameError> {
+    loop {
+        match framer.recv_message() {
+            Ok(m) => return Ok(m),
+            Err(e) if e.is_fatal() => return Err(e),
+            Err(e @ FrameError::Timeout) => {
+                eprintln!("pi stopped mid-frame ({}); did it reboot? resyncing", e)
+            }
+            Err(e) => eprintln!(
+                "dropped corrupted frame ({}); waiting for retransmission",
+                e
+            ),
+        }
+    }
+}
+
+/// Echo console output from the kernel to stdout, as it arrives: an
+/// evaluation may print a partial line long before it finishes.
+pub fn print_output(bytes: &[u8]) {
+    let mut out = io::stdout().lock();
+    let _ = write!(out, "{}", String::from_utf8_lossy(bytes));
+    let _ = out.flush();
+}
+
+/// Wait for the reply to request `id`, echoing any console output that
+/// arrives in the meantime. Replies to other requests are discarded.
+pub fn await_reply<T: Transport>(framer: &mut Framer<T>, id: u32) -> Result<Message, FrameError> {
+    await_reply_with(framer, id, &mut print_output)
+}
+
+/// How often `await_reply_with` looks up to see whether it should
+/// interrupt the evaluation.
+const INTERRUPT_POLL: Duration = Duration::from_millis(100);
+
+/// `await_reply`, passing console output to `on_output` instead. An
+/// `interrupt::request` meanwhile is passed on to the kernel, and its
+/// host file requests are answered.
+pub fn await_reply_with<T: Transport>(
+    framer: &mut Framer<T>,
+    id: u32,
+    on_output: &mut dyn FnMut(&[u8]),
+) -> Result<Message, FrameError> {
+    let _evaluating = interrupt::Evaluating::start();
+    let mut interrupted = false;
+    loop {
+        if !interrupted && interrupt::requested() {
+            interrupted = true;
+            match framer.send_message(&Message::control(&Control::Interrupt)) {
+                Err(e) if e.is_fatal() => return Err(e),
+                Err(e) => eprintln!(
+                    "the Pi didn't take the interrupt ({}); Ctrl-C again to give up on it",
+                    e
+                ),
+                Ok(()) => {}
+            }
+        }
+        let msg = match framer.recv_message_timeout(INTERRUPT_POLL) {
+            Ok(msg) => msg,
+            // nothing yet, or the Pi stopped mid-frame and will resend
+            Err(FrameError::Timeout) => continue,
+            Err(e) if e.is_fatal() => return Err(e),
+            Err(e) => {
+                eprintln!(
+                    "dropped corrupted frame ({}); waiting for retransmission",
+                    e
+                );
+                continue;
+            }
+        };
+        match msg.kind {
+            MessageKind::EvalResult | MessageKind::EvalError if msg.id == id => return Ok(msg),
+            MessageKind::Output => on_output(&msg.body),
+            MessageKind::Control if is_boot(&msg) => return reconnect(framer, id, on_output),
+            MessageKind::Control => {
+                let Some(reply) = Control::decode(&msg.body)
+                    .and_then(|request| host::answer(&request, framer.max_payload))
+                else {
+                    continue;
+                };
+                match framer.send_message(&Message::control(&reply)) {
+                    Err(e) if e.is_fatal() => return Err(e),
+                    // the kernel gives up waiting and fails the syscall
+                    Err(e) => eprintln!("couldn't answer the Pi's file request ({})", e),
+                    Ok(()) => {}
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+fn is_boot(msg: &Message) -> bool {
+    msg.kind == MessageKind::Control && Control::decode(&msg.body) == Some(Control::Boot)
+}
+
+/// The Pi rebooted while request `id` was outstanding. Redo the handshake
+/// and the preludes, and return the reply to `id` if the new kernel sent
+/// one (the request reached it after the boot), else an error.
+fn reconnect<T: Transport>(
+    framer: &mut Framer<T>,
+    id: u32,
+    on_output: &mut dyn FnMut(&[u8]),
+) -> Result<Message, FrameError> {
+    eprintln!("pi rebooted; reconnecting");
+    let mut survivor = None;
+    let kernel = exchange_hellos(framer, &mut |msg| match msg.kind {
+        MessageKind::EvalResult | MessageKind::EvalError if msg.id == id => survivor = Some(msg),
+        MessageKind::Output => on_output(&msg.body),
+        _ => {}
+    })?;
+    eprintln!("reconnected to kernel {}", kernel.build_id);
+    transcript::record_reboot();
+    prime(framer);
+    Ok(survivor.unwrap_or_else(|| {
+        Message::new(
+            MessageKind::EvalError,
+            id,
+            b"pi rebooted before replying; its Image was reset",
+        )
+    }))
+}
+
+/// Files whose forms are re-sent after a reboot (`--prelude`).
+static PRELUDES: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+/// Set while the preludes are being re-sent, so that a prelude which
+/// crashes the Pi doesn't send us round in circles.
+static PRIMING: AtomicBool = AtomicBool::new(false);
+
+/// Request ids for prelude forms, well clear of everyone else's.
+const PRELUDE_IDS: u32 = 0x8000_0000;
+
+/// Re-send the forms of each of `paths`, in order, whenever the Pi turns
+/// out to have rebooted. The files are read at that point, so edits
+/// made during the session are picked up.
+pub fn set_preludes(paths: Vec<String>) {
+    *PRELUDES.lock().unwrap() = paths;
+}
+
+/// Evaluate the preludes on a freshly booted kernel. Results are
+/// discarded; failures are reported and skip the rest of that file.
+fn prime<T: Transport>(framer: &mut Framer<T>) {
+    let paths = PRELUDES.lock().unwrap().clone();
+    if paths.is_empty() {
+        return;
+    }
+    if PRIMING.swap(true, Ordering::SeqCst) {
+        eprintln!("pi rebooted while re-sending the preludes; not trying again");
+        return;
+    }
+    let mut next_id = PRELUDE_IDS;
+    for path in &paths {
+        let result = fs::read_to_string(path)
+            .map_err(|e| BatchError::Io(path.clone(), e))
+            .and_then(|src| batch::eval_source(framer, &mut next_id, path, &src, &mut io::sink()));
+        match result {
+            Ok(()) => eprintln!("re-sent {}", path),
+            Err(e) => eprintln!("prelude failed: {}", e),
+        }
+    }
+    PRIMING.store(false, Ordering::SeqCst);
+}
+
+/// Send `src` for evaluation as request `id` and wait for the reply,
+/// echoing console output to stdout.
+pub fn eval<T: Transport>(
+    framer: &mut Framer<T>,
+    id: u32,
+    src: &str,
+) -> Result<Message, FrameError> {
+    eval_with(framer, id, src, &mut print_output)
+}
+
+/// `eval`, passing console output to `on_output` instead. The exchange
+/// is added to the transcript, if one is being recorded.
+pub fn eval_with<T: Transport>(
+    framer: &mut Framer<T>,
+    id: u32,
+    src: &str,
+    on_output: &mut dyn FnMut(&[u8]),
+) -> Result<Message, FrameError> {
+    let recorded = transcript::record_form(src);
+    let start = Instant::now();
+    let mut output = Vec::new();
+    let request = Message::new(MessageKind::EvalRequest, id, src.as_bytes());
+    match framer.send_message(&request) {
+        Err(FrameError::RetriesExhausted) => {
+            resume_after_silence(framer)?;
+            framer.send_message(&request)?;
+        }
+        sent => sent?,
+    }
+    let reply = await_reply_with(framer, id, &mut |bytes| {
+        if recorded.is_some() {
+            output.extend_from_slice(bytes);
+        }
+        on_output(bytes)
+    })?;
+    if let Some(n) = recorded {
+        transcript::record_reply(n, &reply, &output, start.elapsed());
+    }
+    Ok(reply)
+}
+
+/// What we tell the kernel about ourselves at connect time.
+fn unix_hello() -> Hello {
+    Hello {
+        version: shared::PROTOCOL_VERSION,
+        max_payload: shared::MAX_PAYLOAD_LEN,
+        baud_rates: tty::SUPPORTED_BAUD_RATES.to_vec(),
+        build_id: env!("CARGO_PKG_VERSION").into(),
+        features: feature::COMPRESSION,
+    }
+}
+
+/// Our send went unacknowledged. If the Pi rebooted behind our back and
+/// nobody was reading when it announced itself, it is listening at
+/// `BAUD_RATE` again: try the handshake there, and if the Pi answers,
+/// treat it as freshly booted (an idle kernel always acks). Returns the
+/// original error if it doesn't answer at all.
+fn resume_after_silence<T: Transport>(framer: &mut Framer<T>) -> Result<(), FrameError> {
+    eprintln!(
+        "pi isn't answering; trying {} baud in case it rebooted",
+        shared::BAUD_RATE
+    );
+    framer.transport.reset();
+    let kernel = exchange_hellos(framer, &mut |_| {}).map_err(|e| match e {
+        FrameError::Incompatible(_) => e,
+        _ => FrameError::RetriesExhausted,
+    })?;
+    eprintln!(
+        "reconnected to kernel {} at {} baud",
+        kernel.build_id,
+        shared::BAUD_RATE
+    );
+    transcript::record_reboot();
+    prime(framer);
+    Ok(())
+}
+
+/// Exchange hellos with the kernel and return its advertisement.
+pub fn handshake<T: Transport>(framer: &mut Framer<T>) -> Result<Hello, FrameError> {
+    // start somewhere a previous run didn't leave off, so the kernel
+    // can't take our first frame for a retransmission of its last one
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    framer.seed_seq(now.subsec_nanos() ^ now.as_secs() as u32);
+    exchange_hellos(framer, &mut |_| {})
+}
+
+/// Send our hello and wait for the kernel's, passing anything else that
+/// arrives meanwhile to `other`. Fails with `FrameError::Incompatible`
+/// if we can't talk to the kernel.
+fn exchange_hellos<T: Transport>(
+    framer: &mut Framer<T>,
+    other: &mut dyn FnMut(Message),
+) -> Result<Hello, FrameError> {
+    let ours = unix_hello();
+    framer.send_message(&Message::control(&Control::Hello(ours.clone())))?;
+    loop {
+        let msg = recv_message(framer)?;
+        let Some(Control::Hello(kernel)) =
+            Control::decode(&msg.body).filter(|_| msg.kind == MessageKind::Control)
+        else {
+            other(msg);
+            continue;
+        };
+        ours.check_compatible(&kernel)
+            .map_err(FrameError::Incompatible)?;
+        framer.max_payload = ours.max_payload.min(kernel.max_payload);
+        framer.compress = kernel.has_feature(feature::COMPRESSION);
+        return Ok(kernel);
+    }
+}
+
+/// How long to give the Pi to re-initialize its UART before we talk to
+/// it at the new rate.
+const SWITCH_SETTLE: Duration = Duration::from_millis(50);
+
+/// Ack timeout for the verification ping. Short enough that every
+/// retransmission fits inside the Pi's `BAUD_VERIFY_TIMEOUT_MS` window.
+const PING_ACK_TIMEOUT: Duration = Duration::from_millis(250);
+
+/// Switch the link to `wanted`, or if that's None to the fastest rate
+/// both sides support, falling back to the current rate if the kernel
+/// declines or the new rate doesn't work. Returns the rate the link ends
+/// up running at.
+pub fn negotiate_baud(framer: &mut Framer<Tty>, kernel: &Hello, wanted: Option<u32>) -> u32 {
+    let current = framer.transport.speed();
+    let common = |r: &u32| tty::SUPPORTED_BAUD_RATES.contains(r) && kernel.baud_rates.contains(r);
+    let target = match wanted {
+        Some(rate) if !common(&rate) => {
+            eprintln!("kernel can't run at {} baud; staying at {}", rate, current);
+            None
+        }
+        Some(rate) => Some(rate),
+        None => kernel.baud_rates.iter().copied().filter(common).max(),
+    };
+    match target {
+        Some(rate) if rate != current => match switch_baud(framer, rate) {
+            Ok(r) => r,
+            Err(e) => {
+                eprintln!(
+                    "couldn't switch to {} baud ({}); staying at {}",
+                    rate, e, current
+                );
+                current
+            }
+        },
+        _ => current,
+    }
+}
+
+/// Run the `Control::SetBaud` exchange for `rate` (see `shared::control`).
+/// On error the tty is back at its old rate, as is the Pi once its
+/// verification window has passed.
+fn switch_baud(framer: &mut Framer<Tty>, rate: u32) -> Result<u32, FrameError> {
+    let old = framer.transport.speed();
+    framer.send_message(&Message::control(&Control::SetBaud(rate)))?;
+
+    let confirmed = loop {
+        match framer.recv_message_timeout(framer.ack_timeout) {
+            Ok(msg) if msg.kind == MessageKind::Control => {
+                if let Some(Control::SetBaud(r)) = Control::decode(&msg.body) {
+                    break r;
+                }
+            }
+            Ok(_) => {}
+            Err(e) if e.is_corrupt() => {}
+            Err(e) => return Err(e),
+        }
+    };
+    if confirmed != rate {
+        return Ok(old);
+    }
+
+    // our ack for the confirmation must leave at the old rate before
+    // either side switches
+    framer.transport.flush();
+    thread::sleep(SWITCH_SETTLE);
+    framer.transport.set_speed(rate);
+
+    let ack_timeout = framer.ack_timeout;
+    framer.ack_timeout = PING_ACK_TIMEOUT;
+    let verified = framer.send_message(&Message::control(&Control::Ping));
+    framer.ack_timeout = ack_timeout;
+
+    if let Err(e) = verified {
+        // make sure the Pi has given up on the new rate too
+        thread::sleep(Duration::from_millis(shared::BAUD_VERIFY_TIMEOUT_MS));
+        framer.transport.set_speed(old);
+        return Err(e);
+    }
+    Ok(rate)
+}
@@ -0,0 +1,42 @@
+//! In-process byte pipe: two connected `Transport`s, one for each end of
+//! the link. Bytes written to one end are read from the other, in order.
+//! Dropping one end makes reads on the other fail with
+//! `TransportError::Closed` once the buffered bytes run out.
+
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::time::Duration;
+
+use shared::{Transport, TransportError};
+
+pub struct Loopback {
+    tx: Sender<u8>,
+    rx: Receiver<u8>,
+}
+
+/// Create a connected pair of loopback transports.
+pub fn pair() -> (Loopback, Loopback) {
+    let (a_tx, b_rx) = mpsc::channel();
+    let (b_tx, a_rx) = mpsc::channel();
+    (
+        Loopback { tx: a_tx, rx: a_rx },
+        Loopback { tx: b_tx, rx: b_rx },
+    )
+}
+
+impl Transport for Loopback {
+    fn put8(&mut self, b: u8) {
+        // like a UART with nobody listening, bytes to a dropped peer
+        // just vanish
+        let _ = self.tx.send(b);
+    }
+
+    fn get8(&mut self, timeout: Option<Duration>) -> Result<u8, TransportError> {
+        match timeout {
+            None => self.rx.recv().map_err(|_| TransportError::Closed),
+            Some(t) => self.rx.recv_timeout(t).map_err(|e| match e {
+                RecvTimeoutError::Timeout => TransportError::Timeout,
+                RecvTimeoutError::Disconnected => TransportError::Closed,
+            }),
+        }
+    }
+}
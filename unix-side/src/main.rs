@@ -1,43 +1,227 @@
-mod tty;
-
-use shared::{BAUD_RATE, Framer};
-use std::io::{self, BufRead, Write};
+use shared::{BAUD_RATE, Framer, Transport};
+use std::io;
+use std::os::unix::net::UnixStream;
+use unix_side::cli::{self, Command, Options};
+use unix_side::editor::{self, Editor, History};
+use unix_side::socket::Socket;
+use unix_side::{
+    batch, bootloader, host, interrupt, json, link, repl, server, trace, transcript, tty,
+};
 
 fn main() {
-    let uart = tty::Tty::open(None, BAUD_RATE);
-    let mut framer = Framer::unix_side(uart);
+    let opts = match Options::parse(std::env::args().skip(1)) {
+        Ok(opts) => opts,
+        Err(e) => {
+            eprint!("{}\n\n{}", e, cli::USAGE);
+            std::process::exit(2);
+        }
+    };
+    if opts.help {
+        print!("{}", cli::USAGE);
+        return;
+    }
+    if let Some(path) = &opts.trace {
+        return trace(path);
+    }
+    if opts.command != Command::Serve {
+        interrupt::install();
+    }
+    let socket = opts
+        .socket
+        .clone()
+        .map(Into::into)
+        .unwrap_or_else(server::default_socket_path);
 
-    framer.send("UNIX_READY".as_bytes());
-    let _ = framer.recv();
+    if opts.command == Command::Connect {
+        let stream = match UnixStream::connect(&socket).and_then(Socket::new) {
+            Ok(s) => s,
+            Err(e) => {
+                eprintln!("couldn't connect to {}: {}", socket.display(), e);
+                std::process::exit(1);
+            }
+        };
+        let mut framer = Framer::unix_side(stream);
+        let kernel = handshake(&mut framer);
+        if !opts.quiet {
+            eprintln!(
+                "connected to kernel {} via {}",
+                kernel.build_id,
+                socket.display()
+            );
+        }
+        record(&opts, &kernel);
+        return session(&mut framer, &opts);
+    }
 
-    let stdin = io::stdin();
-    let mut reader = stdin.lock();
+    let mut uart = tty::Tty::open(opts.device.as_deref(), BAUD_RATE);
+    if !opts.quiet {
+        eprintln!("opened tty port <{}>", uart.device());
+    }
+    if let Some(path) = &opts.capture {
+        match trace::Capture::create(path.as_ref()) {
+            Ok(capture) => uart.capture(capture),
+            Err(e) => {
+                eprintln!("couldn't capture to {}: {}", path, e);
+                std::process::exit(1);
+            }
+        }
+    }
+    if let Some(path) = &opts.kernel {
+        boot(&mut uart, path);
+    }
+    let mut framer = Framer::unix_side(uart);
+    link::set_preludes(opts.preludes.clone());
+    if let Some(dir) = &opts.host_dir
+        && let Err(e) = host::share(dir.as_ref())
+    {
+        eprintln!("couldn't share {}: {}", dir, e);
+        std::process::exit(1);
+    }
 
-    loop {
-        print!("> ");
-        io::stdout().flush().unwrap();
+    let kernel = handshake(&mut framer);
+    let rate = link::negotiate_baud(&mut framer, &kernel, opts.baud);
+    if !opts.quiet {
+        eprintln!(
+            "connected to kernel {} (protocol v{}, features: {})",
+            kernel.build_id,
+            kernel.version,
+            kernel.feature_names()
+        );
+        eprintln!("link running at {} baud", rate);
+    }
 
-        let mut line = String::new();
-        match reader.read_line(&mut line) {
-            Ok(0) => break, // EOF
-            Ok(_) => {}
+    if opts.command == Command::Serve {
+        let listener = match server::bind(&socket) {
+            Ok(l) => l,
             Err(e) => {
-                eprintln!("read error: {}", e);
-                break;
+                eprintln!("couldn't listen on {}: {}", socket.display(), e);
+                std::process::exit(1);
             }
+        };
+        if !opts.quiet {
+            eprintln!("serving on {}", socket.display());
         }
+        let e = server::serve(&mut framer, &kernel, listener);
+        let _ = std::fs::remove_file(&socket);
+        eprintln!("link lost: {}", e);
+        std::process::exit(1);
+    }
+    record(&opts, &kernel);
+    if let Some(path) = &opts.replay {
+        return replay(&mut framer, path, opts.stop_at_divergence);
+    }
+    session(&mut framer, &opts);
+}
 
-        let line = line.trim();
-        if line.is_empty() {
-            continue;
+/// Send the kernel image at `path` to the bootloader, exiting if that
+/// fails.
+fn boot(uart: &mut tty::Tty, path: &str) {
+    let code = match std::fs::read(path) {
+        Ok(code) => code,
+        Err(e) => {
+            eprintln!("couldn't read {}: {}", path, e);
+            std::process::exit(1);
         }
+    };
+    if code.starts_with(b"\x7fELF") {
+        eprintln!(
+            "{} is an ELF file; the bootloader wants a raw image (arm-none-eabi-objcopy -O binary)",
+            path
+        );
+        std::process::exit(1);
+    }
+    eprintln!("waiting for the bootloader (reset the Pi if it is running a kernel)");
+    if let Err(e) = bootloader::upload(
+        uart,
+        bootloader::LOAD_ADDR,
+        &code,
+        bootloader::BOOTLOADER_WAIT,
+    ) {
+        eprintln!("boot failed: {}", e);
+        std::process::exit(1);
+    }
+    eprintln!("sent {} ({} bytes); kernel starting", path, code.len());
+}
 
-        framer.send(line.as_bytes());
-        let response = framer.recv();
+/// Print the decoded capture at `path`.
+fn trace(path: &str) {
+    let records = match trace::load(path.as_ref()) {
+        Ok(records) => records,
+        Err(e) => {
+            eprintln!("couldn't read capture {}: {}", path, e);
+            std::process::exit(1);
+        }
+    };
+    if let Err(e) = trace::print(&records, &mut io::stdout().lock())
+        && e.kind() != io::ErrorKind::BrokenPipe
+    {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    }
+}
 
-        match std::str::from_utf8(&response) {
-            Ok(s) => println!("{}", s),
-            Err(_) => println!("Received non-UTF8 response: {:?}", response),
+/// Start the `--record` transcript, if asked for.
+fn record(opts: &Options, kernel: &shared::Hello) {
+    if let Some(path) = &opts.record
+        && let Err(e) = transcript::start(path, &kernel.build_id)
+    {
+        eprintln!("couldn't record to {}: {}", path, e);
+        std::process::exit(1);
+    }
+}
+
+/// Re-send the transcript at `path`, exiting with status 1 if the
+/// replies don't match.
+fn replay<T: Transport>(framer: &mut Framer<T>, path: &str, stop_at_divergence: bool) {
+    let entries = match transcript::load(path) {
+        Ok(entries) => entries,
+        Err(e) => {
+            eprintln!("couldn't read transcript: {}", e);
+            std::process::exit(1);
+        }
+    };
+    match transcript::replay(framer, &entries, stop_at_divergence, &mut io::stdout()) {
+        Ok(diverged) if diverged.is_empty() => {
+            eprintln!("replayed {} forms, all replies matched", entries.len());
+        }
+        Ok(diverged) => {
+            let at: Vec<String> = diverged.iter().map(|d| d.index.to_string()).collect();
+            eprintln!("replies diverged at form(s) {}", at.join(", "));
+            std::process::exit(1);
         }
+        Err(e) => {
+            eprintln!("link lost during replay: {}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+fn handshake<T: Transport>(framer: &mut Framer<T>) -> shared::Hello {
+    match link::handshake(framer) {
+        Ok(kernel) => kernel,
+        Err(e) => {
+            eprintln!("handshake failed: {}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Evaluate the batch sources, or run the REPL (or its JSON version).
+fn session<T: Transport>(framer: &mut Framer<T>, opts: &Options) {
+    if opts.is_batch() {
+        let script = opts.script.as_deref();
+        if let Err(e) = batch::run(framer, script, &opts.eval, &mut io::stdout()) {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+    if opts.json {
+        json::run(framer, io::stdin().lock(), &mut io::stdout());
+    } else if editor::stdin_is_tty() {
+        let history = History::load(History::default_path());
+        repl::run(framer, Editor::new(history), &mut io::stdout());
+    } else {
+        repl::run(framer, io::stdin().lock(), &mut io::stdout());
     }
 }
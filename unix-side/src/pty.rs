@@ -0,0 +1,32 @@
+//! Pseudo-terminal pairs: a real kernel tty on one end, so the unix side
+//! can be run against a fake Pi with exactly the termios and `poll`
+//! behaviour it sees on a USB serial adapter.
+
+use std::fs::File;
+use std::io;
+use std::os::unix::io::FromRawFd;
+use std::ptr;
+
+use crate::tty::Tty;
+
+/// Open a pty pair. The first `Tty` is the slave end, configured like a
+/// serial port at `speed`; hand it to the code under test. The second is
+/// the master end, which plays the Pi.
+pub fn pair(speed: u32) -> io::Result<(Tty, Tty)> {
+    let mut master = -1;
+    let mut slave = -1;
+    let rc = unsafe {
+        libc::openpty(
+            &mut master,
+            &mut slave,
+            ptr::null_mut(),
+            ptr::null(),
+            ptr::null(),
+        )
+    };
+    if rc != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    let (master, slave) = unsafe { (File::from_raw_fd(master), File::from_raw_fd(slave)) };
+    Ok((Tty::from_file(slave, speed), Tty::from_file(master, speed)))
+}
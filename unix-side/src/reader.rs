@@ -0,0 +1,147 @@
+//! Splitting source text into top-level forms without parsing it.
+//!
+//! The kernel's `language::parse` reads exactly one expression per
+//! request, so scripts (and, at the prompt, input spanning several lines)
+//! have to be cut into top-level forms first. This only tracks what
+//! matters for finding form boundaries, following the same rules as the
+//! kernel's parser: parentheses, `"..."` strings with `\` escapes, `;`
+//! line comments, and the `'` `` ` `` `,` `,@` prefixes, which belong to
+//! the form after them.
+
+use std::fmt;
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum ReadError {
+    /// A `)` with no matching `(`, on the given (1-based) line.
+    UnexpectedClose { line: usize },
+}
+
+impl fmt::Display for ReadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReadError::UnexpectedClose { line } => write!(f, "line {}: unexpected ')'", line),
+        }
+    }
+}
+
+/// The complete forms at the start of some source text, and whatever
+/// follows them.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Split<'a> {
+    pub forms: Vec<&'a str>,
+    /// An unfinished form (unbalanced parentheses or an open string), or
+    /// empty if the text ended between forms.
+    pub rest: &'a str,
+}
+
+/// Cut `src` into top-level forms.
+pub fn split_forms(src: &str) -> Result<Split<'_>, ReadError> {
+    let b = src.as_bytes();
+    let mut forms = Vec::new();
+    let mut i = 0;
+    loop {
+        let start = skip_blank(b, i);
+        if start == b.len() {
+            return Ok(Split { forms, rest: "" });
+        }
+        match form_end(b, start) {
+            Ok(Some(end)) => {
+                forms.push(&src[start..end]);
+                i = end;
+            }
+            Ok(None) => {
+                return Ok(Split {
+                    forms,
+                    rest: &src[start..],
+                });
+            }
+            Err(at) => {
+                let line = src[..at].matches('\n').count() + 1;
+                return Err(ReadError::UnexpectedClose { line });
+            }
+        }
+    }
+}
+
+/// Skip whitespace and `;` comments.
+fn skip_blank(b: &[u8], mut i: usize) -> usize {
+    while i < b.len() {
+        match b[i] {
+            b';' => {
+                while i < b.len() && b[i] != b'\n' {
+                    i += 1;
+                }
+            }
+            c if c.is_ascii_whitespace() => i += 1,
+            _ => break,
+        }
+    }
+    i
+}
+
+/// End of the string literal whose opening quote is at `i`, or None if
+/// it isn't closed yet.
+fn string_end(b: &[u8], mut i: usize) -> Option<usize> {
+    i += 1;
+    while i < b.len() {
+        match b[i] {
+            b'\\' => i += 2,
+            b'"' => return Some(i + 1),
+            _ => i += 1,
+        }
+    }
+    None
+}
+
+/// End of the form starting at `i`: `Ok(None)` if the text runs out
+/// first, `Err(at)` for a stray `)` at byte `at`.
+fn form_end(b: &[u8], i: usize) -> Result<Option<usize>, usize> {
+    let i = skip_blank(b, i);
+    let Some(&c) = b.get(i) else {
+        return Ok(None);
+    };
+    match c {
+        b'\'' | b'`' => form_end(b, i + 1),
+        b',' if b.get(i + 1) == Some(&b'@') => form_end(b, i + 2),
+        b',' => form_end(b, i + 1),
+        b'"' => Ok(string_end(b, i)),
+        b')' => Err(i),
+        b'(' => {
+            let mut depth = 0;
+            let mut i = i;
+            while i < b.len() {
+                match b[i] {
+                    b'(' => depth += 1,
+                    b')' => {
+                        depth -= 1;
+                        if depth == 0 {
+                            return Ok(Some(i + 1));
+                        }
+                    }
+                    b'"' => match string_end(b, i) {
+                        Some(end) => {
+                            i = end;
+                            continue;
+                        }
+                        None => return Ok(None),
+                    },
+                    b';' => {
+                        i = skip_blank(b, i);
+                        continue;
+                    }
+                    _ => {}
+                }
+                i += 1;
+            }
+            Ok(None)
+        }
+        // an atom runs to the next delimiter
+        _ => {
+            let mut i = i;
+            while i < b.len() && !b[i].is_ascii_whitespace() && !b"()\";".contains(&b[i]) {
+                i += 1;
+            }
+            Ok(Some(i))
+        }
+    }
+}
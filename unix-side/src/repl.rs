@@ -0,0 +1,164 @@
+//! The interactive read-eval-print loop, generic over where lines come
+//! from, where results go and which transport reaches the Pi.
+
+use std::io::{self, Write};
+
+use shared::{Framer, MessageKind, Transport};
+
+use crate::complete::{self, Names};
+use crate::editor::{LineSource, Prompt};
+use crate::{link, reader, transfer};
+
+/// Parse a decimal or `0x`-prefixed hex number.
+fn parse_u32(s: &str) -> Option<u32> {
+    match s.strip_prefix("0x").or_else(|| s.strip_prefix("0X")) {
+        Some(hex) => u32::from_str_radix(hex, 16).ok(),
+        None => s.parse().ok(),
+    }
+}
+
+/// Run a `:command` typed at the prompt instead of lisp source.
+fn run_command<T: Transport, W: Write>(framer: &mut Framer<T>, line: &str, out: &mut W) {
+    let words: Vec<&str> = line.split_whitespace().collect();
+    match words.as_slice() {
+        [":upload", path] | [":upload", path, _] => {
+            let addr = match words.get(2) {
+                Some(a) => match parse_u32(a) {
+                    Some(a) => Some(a),
+                    None => return eprintln!("bad address: {}", a),
+                },
+                None => None,
+            };
+            match transfer::upload(framer, path, addr) {
+                Ok(p) => {
+                    let _ = writeln!(out, "uploaded {} bytes to {:#010x}", p.len, p.addr);
+                }
+                Err(e) => eprintln!("upload failed: {}", e),
+            }
+        }
+        [":download", addr, len, path] => {
+            let (Some(addr), Some(len)) = (parse_u32(addr), parse_u32(len)) else {
+                return eprintln!("usage: :download <addr> <len> <file>");
+            };
+            match transfer::download(framer, addr, len, path) {
+                Ok(()) => {
+                    let _ = writeln!(out, "downloaded {} bytes to {}", len, path);
+                }
+                Err(e) => eprintln!("download failed: {}", e),
+            }
+        }
+        _ => eprintln!("commands: :upload <file> [addr], :download <addr> <len> <file>"),
+    }
+}
+
+/// Read lines from `input` until EOF, send each complete top-level form
+/// to the Pi for evaluation and write the replies to `out`. A form may
+/// span several lines; input is accumulated until it balances. Returns
+/// when input runs out or the link is lost.
+pub fn run<T: Transport, L: LineSource, W: Write>(
+    framer: &mut Framer<T>,
+    mut input: L,
+    out: &mut W,
+) {
+    let mut next_id: u32 = 1;
+    // lines of an unfinished form
+    let mut pending = String::new();
+    // what was typed since the last history entry
+    let mut typed = String::new();
+    // what the kernel had defined when last asked; any evaluation or
+    // command may change it
+    let mut names: Option<Names> = None;
+
+    loop {
+        let prompt = if pending.is_empty() {
+            Prompt::Primary
+        } else {
+            Prompt::Continuation
+        };
+        let mut complete = |line: &str| {
+            if names.is_none() {
+                names = complete::query_names(framer).ok();
+            }
+            names
+                .as_ref()
+                .map(|n| complete::complete(n, line))
+                .unwrap_or_default()
+        };
+        let line = match input.read_line(prompt, out, &mut complete) {
+            Ok(Some(line)) => line,
+            Ok(None) => {
+                if !pending.is_empty() {
+                    eprintln!("discarding unfinished form at end of input");
+                }
+                break;
+            }
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {
+                pending.clear();
+                typed.clear();
+                continue;
+            }
+            Err(e) => {
+                eprintln!("read error: {}", e);
+                break;
+            }
+        };
+
+        names = None;
+        if pending.is_empty() && line.trim_start().starts_with(':') {
+            input.entered(&line);
+            run_command(framer, line.trim(), out);
+            continue;
+        }
+        if !typed.is_empty() {
+            typed.push('\n');
+        }
+        typed.push_str(&line);
+
+        pending.push_str(&line);
+        pending.push('\n');
+        let (forms, rest) = match reader::split_forms(&pending) {
+            Ok(split) => (
+                split
+                    .forms
+                    .iter()
+                    .map(|f| f.to_string())
+                    .collect::<Vec<_>>(),
+                split.rest.to_string(),
+            ),
+            Err(e) => {
+                eprintln!("{}", e);
+                pending.clear();
+                input.entered(&std::mem::take(&mut typed));
+                continue;
+            }
+        };
+        pending = rest;
+        // a form spanning lines is one entry, ready once it balances
+        if pending.is_empty() {
+            input.entered(&std::mem::take(&mut typed));
+        }
+
+        for form in forms {
+            let id = next_id;
+            next_id = next_id.wrapping_add(1).max(1);
+
+            let reply = match link::eval(framer, id, &form) {
+                Ok(r) => r,
+                Err(e) if e.is_fatal() => {
+                    eprintln!("link lost: {}", e);
+                    return;
+                }
+                Err(e) => {
+                    eprintln!("send failed: {}", e);
+                    continue;
+                }
+            };
+
+            let text = String::from_utf8_lossy(&reply.body);
+            let _ = match reply.kind {
+                MessageKind::EvalError => writeln!(out, "error: {}", text),
+                _ => writeln!(out, "{}", text),
+            };
+        }
+    }
+}
@@ -0,0 +1,228 @@
+//! `unix-side serve`: one process owns the tty and shares the Pi with
+//! any number of clients (`unix-side connect`, or Emacs running it) over
+//! a Unix-domain socket.
+//!
+//! Clients speak the ordinary framed protocol to the server as if it were
+//! the kernel: the server answers their `Hello` with the kernel's own
+//! advertisement, so `link::handshake`, the REPL and transfers run
+//! unchanged on top of a `Socket`. Everything else goes into a single
+//! queue, and requests are forwarded to the Pi one at a time. Each
+//! evaluation is renumbered on the way in and given back its client's id
+//! on the way out, so ids from different clients never collide; output
+//! the kernel prints meanwhile goes to the client whose request is
+//! running. A client's `Control::Interrupt` is acted on straight away,
+//! and only if its own request is the one running.
+
+use std::io;
+use std::os::unix::net::{UnixListener, UnixStream};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{self, Sender};
+use std::thread;
+use std::time::Duration;
+
+use shared::control::feature;
+use shared::{Control, FrameError, Framer, Hello, Message, MessageKind, Transport};
+
+use crate::socket::Socket;
+use crate::{interrupt, link};
+
+/// `$LISPI_SOCKET`, else `lispi.sock` in `$XDG_RUNTIME_DIR`, else a
+/// per-user socket in `/tmp`.
+pub fn default_socket_path() -> PathBuf {
+    if let Some(p) = std::env::var_os("LISPI_SOCKET") {
+        return p.into();
+    }
+    match std::env::var_os("XDG_RUNTIME_DIR") {
+        Some(dir) => PathBuf::from(dir).join("lispi.sock"),
+        None => PathBuf::from(format!("/tmp/lispi-{}.sock", unsafe { libc::getuid() })),
+    }
+}
+
+/// Bind the server socket at `path`, replacing a stale socket file left
+/// by a server that died, but not one that is still being served.
+pub fn bind(path: &Path) -> io::Result<UnixListener> {
+    if path.exists() {
+        if UnixStream::connect(path).is_ok() {
+            return Err(io::Error::new(
+                io::ErrorKind::AddrInUse,
+                format!("a server is already listening on {}", path.display()),
+            ));
+        }
+        std::fs::remove_file(path)?;
+    }
+    UnixListener::bind(path)
+}
+
+/// A message from a client, and where to send whatever the Pi says
+/// back.
+struct Request {
+    msg: Message,
+    /// Which client sent it, numbered from 1.
+    client: u64,
+    reply: Sender<Message>,
+}
+
+/// The client whose evaluation the Pi is running, or 0.
+static RUNNING: AtomicU64 = AtomicU64::new(0);
+
+/// How often a client thread looks up from its socket to pass on
+/// replies.
+const CLIENT_POLL: Duration = Duration::from_millis(10);
+
+/// Accept clients on `listener` and forward their requests to the Pi
+/// behind `pi`, which must have completed the handshake (`kernel` is its
+/// hello). Only returns once the link to the Pi is lost.
+pub fn serve<T: Transport>(
+    pi: &mut Framer<T>,
+    kernel: &Hello,
+    listener: UnixListener,
+) -> FrameError {
+    // what clients are told: the kernel as seen through this server.
+    // Compression doesn't pay on a local socket.
+    let hello = Hello {
+        max_payload: pi.max_payload,
+        features: kernel.features & !feature::COMPRESSION,
+        ..kernel.clone()
+    };
+
+    let (requests, queue) = mpsc::channel();
+    thread::spawn(move || {
+        for (client, stream) in (1..).zip(listener.incoming()) {
+            let stream = match stream.and_then(Socket::new) {
+                Ok(s) => s,
+                Err(e) => {
+                    eprintln!("accept failed: {}", e);
+                    continue;
+                }
+            };
+            let hello = hello.clone();
+            let requests = requests.clone();
+            thread::spawn(move || serve_client(Framer::pi_side(stream), client, &hello, requests));
+        }
+    });
+
+    let mut next_id: u32 = 1;
+    for req in queue {
+        if let Err(e) = forward(pi, &mut next_id, req) {
+            return e;
+        }
+    }
+    // the accept loop holds a sender for as long as the listener lives
+    unreachable!("request queue closed")
+}
+
+/// Pass one client request to the Pi and route what comes back. Returns
+/// an error only if the link to the Pi is lost.
+fn forward<T: Transport>(
+    pi: &mut Framer<T>,
+    next_id: &mut u32,
+    req: Request,
+) -> Result<(), FrameError> {
+    // a client that went away just stops getting replies
+    let route = |msg: Message| {
+        let _ = req.reply.send(msg);
+    };
+    match req.msg.kind {
+        MessageKind::EvalRequest => {
+            let id = *next_id;
+            *next_id = next_id.wrapping_add(1).max(1);
+            pi.send_message(&Message::new(MessageKind::EvalRequest, id, &req.msg.body))?;
+            // a reboot is dealt with here, so clients never see one
+            RUNNING.store(req.client, Ordering::SeqCst);
+            let reply = link::await_reply_with(pi, id, &mut |bytes| {
+                route(Message::new(MessageKind::Output, req.msg.id, bytes))
+            });
+            RUNNING.store(0, Ordering::SeqCst);
+            let mut reply = reply?;
+            reply.id = req.msg.id;
+            route(reply);
+            Ok(())
+        }
+        MessageKind::Control => {
+            pi.send_message(&req.msg)?;
+            // transfer requests are answered with a `Transfer` report,
+            // downloads with chunks first; name queries with `Names`
+            if !matches!(
+                Control::decode(&req.msg.body),
+                Some(
+                    Control::Upload { .. }
+                        | Control::Download { .. }
+                        | Control::QueryTransfer
+                        | Control::QueryNames
+                )
+            ) {
+                return Ok(());
+            }
+            loop {
+                let msg = link::recv_message(pi)?;
+                match msg.kind {
+                    MessageKind::Control => {
+                        if let Some(Control::Transfer(_) | Control::Names { .. }) =
+                            Control::decode(&msg.body)
+                        {
+                            route(msg);
+                            return Ok(());
+                        }
+                    }
+                    MessageKind::Blob | MessageKind::Output => route(msg),
+                    _ => {}
+                }
+            }
+        }
+        // upload chunks are acked by the framer and need no reply
+        _ => pi.send_message(&req.msg),
+    }
+}
+
+/// Talk to one client until it hangs up: answer its handshake, queue
+/// everything else for the Pi and pass replies back as they arrive.
+fn serve_client<T: Transport>(
+    mut framer: Framer<T>,
+    client: u64,
+    hello: &Hello,
+    requests: Sender<Request>,
+) {
+    let (reply, replies) = mpsc::channel();
+    loop {
+        match framer.recv_message_timeout(CLIENT_POLL) {
+            Ok(msg) => {
+                match Control::decode(&msg.body).filter(|_| msg.kind == MessageKind::Control) {
+                    Some(Control::Hello(peer)) => {
+                        framer.max_payload = hello.max_payload.min(peer.max_payload);
+                        if let Err(e) =
+                            framer.send_message(&Message::control(&Control::Hello(hello.clone())))
+                            && e.is_fatal()
+                        {
+                            return;
+                        }
+                    }
+                    // the server owns the line; clients can't change its rate
+                    Some(Control::SetBaud(_) | Control::Ping) => {}
+                    // only this client's own evaluation, not whichever
+                    // happens to be running
+                    Some(Control::Interrupt) => {
+                        if RUNNING.load(Ordering::SeqCst) == client {
+                            interrupt::request();
+                        }
+                    }
+                    _ => {
+                        let reply = reply.clone();
+                        if requests.send(Request { msg, client, reply }).is_err() {
+                            return;
+                        }
+                    }
+                }
+            }
+            Err(e) if e.is_fatal() => return,
+            Err(_) => {}
+        }
+        while let Ok(msg) = replies.try_recv() {
+            if let Err(e) = framer.send_message(&msg)
+                && e.is_fatal()
+            {
+                return;
+            }
+        }
+    }
+}
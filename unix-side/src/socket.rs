@@ -0,0 +1,59 @@
+//! A Unix-domain socket as a `Transport`, for the link between
+//! `unix-side serve` and its clients. Writes are buffered until the
+//! framer flushes at the end of each frame.
+
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::os::unix::net::UnixStream;
+use std::time::Duration;
+
+use shared::{Transport, TransportError};
+
+pub struct Socket {
+    reader: BufReader<UnixStream>,
+    writer: BufWriter<UnixStream>,
+}
+
+impl Socket {
+    pub fn new(stream: UnixStream) -> io::Result<Self> {
+        Ok(Self {
+            writer: BufWriter::new(stream.try_clone()?),
+            reader: BufReader::new(stream),
+        })
+    }
+}
+
+impl Transport for Socket {
+    fn put8(&mut self, b: u8) {
+        // a client that hung up shows up as `Closed` on the next read
+        let _ = self.writer.write_all(&[b]);
+    }
+
+    fn get8(&mut self, timeout: Option<Duration>) -> Result<u8, TransportError> {
+        // a zero read timeout means "forever" to the socket
+        let timeout = timeout.map(|t| t.max(Duration::from_millis(1)));
+        if self.reader.buffer().is_empty() {
+            self.reader
+                .get_ref()
+                .set_read_timeout(timeout)
+                .map_err(|_| TransportError::Closed)?;
+        }
+        let mut buf = [0u8; 1];
+        loop {
+            match self.reader.read(&mut buf) {
+                Ok(1) => return Ok(buf[0]),
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e)
+                    if e.kind() == io::ErrorKind::WouldBlock
+                        || e.kind() == io::ErrorKind::TimedOut =>
+                {
+                    return Err(TransportError::Timeout);
+                }
+                _ => return Err(TransportError::Closed),
+            }
+        }
+    }
+
+    fn flush(&mut self) {
+        let _ = self.writer.flush();
+    }
+}
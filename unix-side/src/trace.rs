@@ -0,0 +1,601 @@
+//! Recording what crosses the tty (`--capture FILE`) and decoding it
+//! afterwards (`unix-side trace FILE`), for when the framer itself is
+//! what's misbehaving.
+//!
+//! A capture is a short header followed by records:
+//!
+//! ```text
+//! [tag: u8]             'P' bytes from the Pi, 'U' bytes to it, 'B' baud change
+//! [time: u64]           microseconds since the capture started
+//! [n: u32]              length of data
+//! [data: u8 * n]        the bytes, or for 'B' the new rate as a u32
+//! ```
+//!
+//! All integers are little-endian. Consecutive bytes in one direction
+//! share a record; a record is written whenever the line goes idle or
+//! changes direction, so a capture cut short by a crash loses at most
+//! the bytes of the last burst.
+//!
+//! The decoder splits each direction back into what the framer would
+//! have seen: frames (with their sync run, kind, sequence number, length
+//! and a preview of the message inside), and everything else as runs of
+//! sync or padding words or plain noise, which is usually text printed
+//! before the kernel started framing its output.
+
+use std::fmt;
+use std::fs::File;
+use std::io::{self, Write};
+use std::path::Path;
+use std::time::Instant;
+
+use shared::crc::Crc32;
+use shared::{
+    Control, FLAG_COMPRESSED, FOOTER_COUNT, FrameKind, MAX_PAYLOAD_LEN, Message, MessageKind,
+    PI_FOOTER_WORD, PI_SYNC_WORD, UNIX_FOOTER_WORD, UNIX_SYNC_WORD, ZERO_DELIMITER_COUNT, lz,
+};
+
+const MAGIC: &[u8] = b"lispi-trace 1\n";
+
+/// Longest run kept in memory before it is written out.
+const MAX_RUN: usize = 4096;
+
+/// How much of a payload or of noise is shown.
+const PREVIEW_LEN: usize = 48;
+
+/// Which way bytes were going.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Dir {
+    FromPi,
+    ToPi,
+}
+
+impl Dir {
+    fn tag(self) -> u8 {
+        match self {
+            Dir::FromPi => b'P',
+            Dir::ToPi => b'U',
+        }
+    }
+
+    /// The sync and padding words of frames going this way.
+    fn words(self) -> (u32, u32) {
+        match self {
+            Dir::FromPi => (PI_SYNC_WORD, PI_FOOTER_WORD),
+            Dir::ToPi => (UNIX_SYNC_WORD, UNIX_FOOTER_WORD),
+        }
+    }
+}
+
+impl fmt::Display for Dir {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Dir::FromPi => write!(f, "pi->unix"),
+            Dir::ToPi => write!(f, "unix->pi"),
+        }
+    }
+}
+
+/// Records the bytes crossing a tty to a capture file (see `Tty::capture`).
+pub struct Capture {
+    file: File,
+    start: Instant,
+    /// The burst being collected: its direction, start time and bytes.
+    run: Option<(Dir, u64, Vec<u8>)>,
+}
+
+impl Capture {
+    pub fn create(path: &Path) -> io::Result<Self> {
+        let mut file = File::create(path)?;
+        file.write_all(MAGIC)?;
+        Ok(Self {
+            file,
+            start: Instant::now(),
+            run: None,
+        })
+    }
+
+    fn now(&self) -> u64 {
+        self.start.elapsed().as_micros() as u64
+    }
+
+    /// Note one byte going `dir`.
+    pub fn byte(&mut self, dir: Dir, b: u8) {
+        match &mut self.run {
+            Some((d, _, bytes)) if *d == dir && bytes.len() < MAX_RUN => bytes.push(b),
+            _ => {
+                self.idle();
+                self.run = Some((dir, self.now(), vec![b]));
+            }
+        }
+    }
+
+    /// Note a switch to `rate` baud.
+    pub fn baud(&mut self, rate: u32) {
+        self.idle();
+        let time = self.now();
+        self.write(b'B', time, &rate.to_le_bytes());
+    }
+
+    /// The line went quiet: write out the burst so far.
+    pub fn idle(&mut self) {
+        if let Some((dir, time, bytes)) = self.run.take() {
+            self.write(dir.tag(), time, &bytes);
+        }
+    }
+
+    fn write(&mut self, tag: u8, time: u64, data: &[u8]) {
+        let mut record = Vec::with_capacity(13 + data.len());
+        record.push(tag);
+        record.extend_from_slice(&time.to_le_bytes());
+        record.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        record.extend_from_slice(data);
+        // a capture that can't be written shouldn't take the session down
+        let _ = self.file.write_all(&record);
+    }
+}
+
+impl Drop for Capture {
+    fn drop(&mut self) {
+        self.idle();
+    }
+}
+
+/// One record of a capture.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Record {
+    Bytes { dir: Dir, time: u64, data: Vec<u8> },
+    Baud { time: u64, rate: u32 },
+}
+
+/// Read the records of the capture at `path`. A record cut off at the
+/// end (the process died while writing it) is dropped.
+pub fn load(path: &Path) -> io::Result<Vec<Record>> {
+    parse(&std::fs::read(path)?)
+}
+
+/// `load`, from the capture's contents.
+pub fn parse(bytes: &[u8]) -> io::Result<Vec<Record>> {
+    let mut rest = bytes
+        .strip_prefix(MAGIC)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "not a lispi capture file"))?;
+    let mut records = Vec::new();
+    while let Some((head, tail)) = rest.split_at_checked(13) {
+        let time = u64::from_le_bytes(head[1..9].try_into().unwrap());
+        let n = u32::from_le_bytes(head[9..13].try_into().unwrap()) as usize;
+        let Some((data, tail)) = tail.split_at_checked(n) else {
+            break;
+        };
+        records.push(match head[0] {
+            b'P' | b'U' => Record::Bytes {
+                dir: if head[0] == b'P' {
+                    Dir::FromPi
+                } else {
+                    Dir::ToPi
+                },
+                time,
+                data: data.to_vec(),
+            },
+            b'B' if n == 4 => Record::Baud {
+                time,
+                rate: u32::from_le_bytes(data.try_into().unwrap()),
+            },
+            _ => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("bad record tag {:#04x}", head[0]),
+                ));
+            }
+        });
+        rest = tail;
+    }
+    Ok(records)
+}
+
+/// Something wrong with a frame, as the framer would have found it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Anomaly {
+    /// The kind word names no `FrameKind`, or has unknown flags.
+    BadKind(u32),
+    /// The length word is over `MAX_PAYLOAD_LEN`; the framer drops the
+    /// frame there and looks for the next header.
+    Oversized(u32),
+    BadCrc {
+        sent: u32,
+        computed: u32,
+    },
+    /// Footer zero word `index` wasn't zero.
+    FooterZero {
+        index: usize,
+        got: u32,
+    },
+    /// Only `count` of the padding words after the footer were there.
+    ShortPadding {
+        count: usize,
+    },
+    /// The capture ends inside the frame.
+    Truncated,
+    /// A compressed payload that doesn't decompress.
+    BadCompression,
+}
+
+impl fmt::Display for Anomaly {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Anomaly::BadKind(k) => write!(f, "bad kind word {:#010x}", k),
+            Anomaly::Oversized(n) => write!(f, "length {} is over the maximum; skipped", n),
+            Anomaly::BadCrc { sent, computed } => {
+                write!(f, "crc {:#010x} sent, {:#010x} computed", sent, computed)
+            }
+            Anomaly::FooterZero { index, got } => {
+                write!(f, "footer word {} is {:#010x}, not zero", index, got)
+            }
+            Anomaly::ShortPadding { count } => {
+                write!(
+                    f,
+                    "{} of {} padding words after the footer",
+                    count, FOOTER_COUNT
+                )
+            }
+            Anomaly::Truncated => write!(f, "capture ends mid-frame"),
+            Anomaly::BadCompression => write!(f, "compressed payload doesn't decompress"),
+        }
+    }
+}
+
+/// A frame found in a capture.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Frame {
+    /// Sync words right before the header.
+    pub sync: usize,
+    /// The raw kind word, flags included.
+    pub kind: u32,
+    pub seq: u32,
+    pub len: u32,
+    /// The payload as sent (still compressed, if it was).
+    pub payload: Vec<u8>,
+    pub anomalies: Vec<Anomaly>,
+}
+
+impl Frame {
+    /// The payload, decompressed if need be. None if it doesn't
+    /// decompress.
+    pub fn data(&self) -> Option<Vec<u8>> {
+        if self.kind & FLAG_COMPRESSED == 0 {
+            return Some(self.payload.clone());
+        }
+        let (n, block) = self.payload.split_at_checked(4)?;
+        let n = u32::from_le_bytes(n.try_into().unwrap());
+        if n > MAX_PAYLOAD_LEN {
+            return None;
+        }
+        lz::decompress(block, n as usize)
+    }
+
+    /// A one-line description of the message in the payload.
+    pub fn preview(&self) -> String {
+        let Some(data) = self.data() else {
+            return "(bad compression)".into();
+        };
+        let Some(msg) = Message::decode(data) else {
+            return format!("undecodable message {}", quote(&self.payload));
+        };
+        match msg.kind {
+            MessageKind::Control => match Control::decode(&msg.body) {
+                Some(c) => {
+                    let text = format!("{:?}", c);
+                    format!("Control {}", shorten(&text))
+                }
+                None => format!("Control (unknown) {}", quote(&msg.body)),
+            },
+            MessageKind::Blob => format!("Blob #{} ({} bytes)", msg.id, msg.body.len()),
+            kind => format!("{:?} #{} {}", kind, msg.id, quote(&msg.body)),
+        }
+    }
+}
+
+/// What the decoder found at one point in a capture.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Item {
+    Frame(Frame),
+    /// `count` sync words that no frame followed.
+    Sync {
+        word: u32,
+        count: usize,
+    },
+    /// `count` padding words that no frame preceded.
+    Padding {
+        word: u32,
+        count: usize,
+    },
+    /// Bytes that are no part of a frame.
+    Noise(Vec<u8>),
+    /// The tty switched to `rate` baud.
+    Baud(u32),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Event {
+    /// Microseconds since the capture started.
+    pub time: u64,
+    /// None for baud changes.
+    pub dir: Option<Dir>,
+    pub item: Item,
+}
+
+impl fmt::Display for Event {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let dir = self.dir.map(|d| d.to_string()).unwrap_or_default();
+        write!(
+            f,
+            "{:>4}.{:06} {:<8} ",
+            self.time / 1_000_000,
+            self.time % 1_000_000,
+            dir
+        )?;
+        match &self.item {
+            Item::Frame(frame) => {
+                let kind = match FrameKind::from_u32(frame.kind & 0xFF) {
+                    Some(FrameKind::Data) => "data",
+                    Some(FrameKind::Ack) => "ack",
+                    Some(FrameKind::Nack) => "nack",
+                    None => "frame",
+                };
+                write!(f, "{} seq={:#010x}", kind, frame.seq)?;
+                if !frame.payload.is_empty() {
+                    write!(f, " len={}", frame.len)?;
+                }
+                if frame.kind & FLAG_COMPRESSED != 0 {
+                    write!(f, " compressed")?;
+                }
+                if frame.sync != shared::SYNC_COUNT as usize {
+                    write!(f, " sync={}", frame.sync)?;
+                }
+                if frame.kind & 0xFF == FrameKind::Data as u32
+                    && frame.payload.len() == frame.len as usize
+                {
+                    write!(f, " {}", frame.preview())?;
+                }
+                // under the item, past the time and direction
+                for a in &frame.anomalies {
+                    write!(f, "\n{:21}!! {}", "", a)?;
+                }
+                Ok(())
+            }
+            Item::Sync { word, count } => write!(f, "sync {:08X} x{}", word, count),
+            Item::Padding { word, count } => write!(f, "padding {:08X} x{}", word, count),
+            Item::Noise(bytes) => write!(f, "noise {} bytes {}", bytes.len(), quote(bytes)),
+            Item::Baud(rate) => write!(f, "baud -> {}", rate),
+        }
+    }
+}
+
+/// Decode a capture into events, in the order they happened.
+pub fn decode(records: &[Record]) -> Vec<Event> {
+    let mut events = Vec::new();
+    for dir in [Dir::FromPi, Dir::ToPi] {
+        // this direction's bytes, and when each record of them began
+        let mut stream = Vec::new();
+        let mut starts = Vec::new();
+        for r in records {
+            if let Record::Bytes { dir: d, time, data } = r
+                && *d == dir
+            {
+                starts.push((stream.len(), *time));
+                stream.extend_from_slice(data);
+            }
+        }
+        let time_at = |offset: usize| {
+            let i = starts.partition_point(|&(start, _)| start <= offset);
+            starts[i.saturating_sub(1)].1
+        };
+        for (offset, item) in decode_stream(&stream, dir) {
+            events.push(Event {
+                time: time_at(offset),
+                dir: Some(dir),
+                item,
+            });
+        }
+    }
+    for r in records {
+        if let Record::Baud { time, rate } = r {
+            events.push(Event {
+                time: *time,
+                dir: None,
+                item: Item::Baud(*rate),
+            });
+        }
+    }
+    // stable, so each direction keeps its own order
+    events.sort_by_key(|e| e.time);
+    events
+}
+
+/// Write the decoded `records` to `out`, one event per line.
+pub fn print(records: &[Record], out: &mut dyn Write) -> io::Result<()> {
+    for event in decode(records) {
+        writeln!(out, "{}", event)?;
+    }
+    Ok(())
+}
+
+fn word_at(b: &[u8], i: usize) -> Option<u32> {
+    b.get(i..i + 4)
+        .map(|w| u32::from_le_bytes(w.try_into().unwrap()))
+}
+
+/// Split one direction's bytes into items, each with the offset it
+/// starts at. Frames are found the way `Framer::recv` finds them.
+fn decode_stream(b: &[u8], dir: Dir) -> Vec<(usize, Item)> {
+    let header_len = ZERO_DELIMITER_COUNT as usize * 4;
+    let mut items = Vec::new();
+    let mut i = 0;
+    while i < b.len() {
+        // the low byte of the kind word, after enough zeros
+        let mut zeros = 0;
+        let kind_at = (i..b.len()).find(|&j| {
+            if b[j] == 0 {
+                zeros += 1;
+                false
+            } else {
+                let found = zeros >= header_len;
+                zeros = 0;
+                found
+            }
+        });
+        let Some(kind_at) = kind_at else {
+            gap(b, i, b.len(), dir, &mut items);
+            break;
+        };
+        let header_at = kind_at - header_len;
+        let sync = gap(b, i, header_at, dir, &mut items);
+        let start = header_at - sync * 4;
+        let (frame, end) = frame_at(b, kind_at, sync, dir);
+        items.push((start, Item::Frame(frame)));
+        i = end;
+    }
+    items
+}
+
+/// Parse the frame whose kind word starts at `at`. Returns it and where
+/// decoding should carry on.
+fn frame_at(b: &[u8], at: usize, sync: usize, dir: Dir) -> (Frame, usize) {
+    let mut frame = Frame {
+        sync,
+        kind: 0,
+        seq: 0,
+        len: 0,
+        payload: Vec::new(),
+        anomalies: Vec::new(),
+    };
+    let (Some(kind), Some(seq), Some(len)) =
+        (word_at(b, at), word_at(b, at + 4), word_at(b, at + 8))
+    else {
+        frame.kind = word_at(b, at).unwrap_or(b[at] as u32);
+        frame.anomalies.push(Anomaly::Truncated);
+        return (frame, b.len());
+    };
+    frame.kind = kind;
+    frame.seq = seq;
+    frame.len = len;
+    if FrameKind::from_u32(kind & 0xFF).is_none() || kind & !0xFF & !FLAG_COMPRESSED != 0 {
+        frame.anomalies.push(Anomaly::BadKind(kind));
+    }
+    if len > MAX_PAYLOAD_LEN {
+        frame.anomalies.push(Anomaly::Oversized(len));
+        return (frame, at + 12);
+    }
+    let payload_at = at + 12;
+    let crc_at = payload_at + len as usize;
+    let Some(payload) = b.get(payload_at..crc_at) else {
+        frame.payload = b[payload_at.min(b.len())..].to_vec();
+        frame.anomalies.push(Anomaly::Truncated);
+        return (frame, b.len());
+    };
+    frame.payload = payload.to_vec();
+    let Some(sent) = word_at(b, crc_at) else {
+        frame.anomalies.push(Anomaly::Truncated);
+        return (frame, b.len());
+    };
+    let mut crc = Crc32::new();
+    crc.update32(kind);
+    crc.update32(seq);
+    crc.update32(len);
+    crc.update(payload);
+    let computed = crc.finish();
+    if computed != sent {
+        frame.anomalies.push(Anomaly::BadCrc { sent, computed });
+    }
+    if computed == sent && frame.data().is_none() {
+        frame.anomalies.push(Anomaly::BadCompression);
+    }
+
+    let mut end = crc_at + 4;
+    for index in 0..ZERO_DELIMITER_COUNT as usize {
+        let Some(got) = word_at(b, end) else {
+            frame.anomalies.push(Anomaly::Truncated);
+            return (frame, b.len());
+        };
+        if got != 0 {
+            frame.anomalies.push(Anomaly::FooterZero { index, got });
+        }
+        end += 4;
+    }
+    let (_, padding) = dir.words();
+    let mut count = 0;
+    while count < FOOTER_COUNT as usize && word_at(b, end) == Some(padding) {
+        count += 1;
+        end += 4;
+    }
+    if count < FOOTER_COUNT as usize {
+        frame.anomalies.push(match word_at(b, end) {
+            None => Anomaly::Truncated,
+            Some(_) => Anomaly::ShortPadding { count },
+        });
+    }
+    (frame, end)
+}
+
+/// Push items for the bytes `b[from..to]` between frames. Returns how
+/// many sync words end the gap, which are left for the frame that
+/// follows rather than pushed.
+fn gap(b: &[u8], from: usize, to: usize, dir: Dir, items: &mut Vec<(usize, Item)>) -> usize {
+    let (sync, padding) = dir.words();
+    let mut runs: Vec<(usize, Item)> = Vec::new();
+    let mut noise_at = None;
+    let mut i = from;
+    while i < to {
+        let word = if i + 4 <= to { word_at(b, i) } else { None };
+        if let Some(word) = word.filter(|&w| w == sync || w == padding) {
+            if let Some(at) = noise_at.take() {
+                runs.push((at, Item::Noise(b[at..i].to_vec())));
+            }
+            let at = i;
+            let mut count = 0;
+            while i + 4 <= to && word_at(b, i) == Some(word) {
+                count += 1;
+                i += 4;
+            }
+            runs.push((
+                at,
+                if word == sync {
+                    Item::Sync { word, count }
+                } else {
+                    Item::Padding { word, count }
+                },
+            ));
+        } else {
+            noise_at.get_or_insert(i);
+            i += 1;
+        }
+    }
+    if let Some(at) = noise_at {
+        runs.push((at, Item::Noise(b[at..to].to_vec())));
+    }
+    // a frame's own sync run goes with the frame
+    let sync_before = match runs.last() {
+        Some((_, Item::Sync { count, .. })) if to < b.len() => *count,
+        _ => 0,
+    };
+    if sync_before > 0 {
+        runs.pop();
+    }
+    items.extend(runs);
+    sync_before
+}
+
+/// `bytes` as a quoted string, escaped and cut to `PREVIEW_LEN`.
+fn quote(bytes: &[u8]) -> String {
+    let shown = &bytes[..bytes.len().min(PREVIEW_LEN)];
+    let mut s: String = shown.escape_ascii().to_string();
+    if bytes.len() > PREVIEW_LEN {
+        s.push_str("...");
+    }
+    format!("\"{}\"", s)
+}
+
+fn shorten(text: &str) -> String {
+    match text.char_indices().nth(PREVIEW_LEN * 2) {
+        Some((i, _)) => format!("{}...", &text[..i]),
+        None => text.to_string(),
+    }
+}
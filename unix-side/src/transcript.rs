@@ -0,0 +1,252 @@
+//! Session transcripts (`--record FILE`) and replaying them against a
+//! freshly booted Pi (`unix-side replay FILE`).
+//!
+//! A transcript is a JSON-lines file (see `json`). Each session appends a
+//! header naming the kernel, then every form is written *before* it is
+//! sent and its reply once it arrives, so a form that crashed the board
+//! is the last line of the file:
+//!
+//! ```text
+//! {"event":"session","time":1760000000.5,"kernel":"0.1.0"}
+//! {"event":"form","n":1,"time":1760000001.25,"code":"(+ 1 2)"}
+//! {"event":"reply","n":1,"time":1760000001.26,"kind":"value","text":"3","output":"","elapsed_ms":4.21}
+//! ```
+//!
+//! `time` is seconds since the Unix epoch. A `{"event":"reboot",...}`
+//! line marks where the Pi rebooted and the link was re-established. Recording hooks into
+//! `link::eval_with`, which every way of evaluating (REPL, `--json`,
+//! `--script`) goes through, so the recorder is process-wide rather than
+//! threaded through each of them.
+
+use std::fmt;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use shared::{FrameError, Framer, Message, MessageKind, Transport};
+
+use crate::json::Value;
+use crate::link;
+
+static RECORDER: Mutex<Option<Recorder>> = Mutex::new(None);
+
+struct Recorder {
+    file: File,
+    /// Number of the last form recorded.
+    n: u64,
+}
+
+fn now() -> Value {
+    let t = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    Value::Number((t.as_secs_f64() * 1e3).round() / 1e3)
+}
+
+fn text(s: &str) -> Value {
+    Value::String(s.into())
+}
+
+fn object(fields: Vec<(&str, Value)>) -> Value {
+    Value::Object(fields.into_iter().map(|(k, v)| (k.into(), v)).collect())
+}
+
+impl Recorder {
+    /// Write one line and push it to disk, so it survives whatever
+    /// happens next.
+    fn write(&mut self, line: Value) {
+        if let Err(e) = writeln!(self.file, "{}", line).and_then(|_| self.file.sync_data()) {
+            eprintln!("transcript write failed: {}", e);
+        }
+    }
+}
+
+/// Start appending this session to the transcript at `path`. `kernel` is
+/// the build id from the kernel's hello.
+pub fn start(path: &str, kernel: &str) -> io::Result<()> {
+    let file = OpenOptions::new().create(true).append(true).open(path)?;
+    let mut recorder = Recorder { file, n: 0 };
+    recorder.write(object(vec![
+        ("event", text("session")),
+        ("time", now()),
+        ("kernel", text(kernel)),
+    ]));
+    *RECORDER.lock().unwrap() = Some(recorder);
+    Ok(())
+}
+
+/// Record that `code` is about to be sent. Returns its number in the
+/// transcript, if recording.
+pub(crate) fn record_form(code: &str) -> Option<u64> {
+    let mut recorder = RECORDER.lock().unwrap();
+    let recorder = recorder.as_mut()?;
+    recorder.n += 1;
+    let n = recorder.n;
+    recorder.write(object(vec![
+        ("event", text("form")),
+        ("n", Value::Number(n as f64)),
+        ("time", now()),
+        ("code", text(code)),
+    ]));
+    Some(n)
+}
+
+/// Record the reply to form `n`, and the console output printed while it
+/// ran.
+pub(crate) fn record_reply(n: u64, reply: &Message, output: &[u8], elapsed: Duration) {
+    let mut recorder = RECORDER.lock().unwrap();
+    let Some(recorder) = recorder.as_mut() else {
+        return;
+    };
+    let (kind, body) = describe(reply);
+    let ms = (elapsed.as_secs_f64() * 1e6).round() / 1e3;
+    recorder.write(object(vec![
+        ("event", text("reply")),
+        ("n", Value::Number(n as f64)),
+        ("time", now()),
+        ("kind", text(kind)),
+        ("text", text(&body)),
+        ("output", text(&String::from_utf8_lossy(output))),
+        ("elapsed_ms", Value::Number(ms)),
+    ]));
+}
+
+/// Record that the Pi rebooted mid-session.
+pub(crate) fn record_reboot() {
+    if let Some(recorder) = RECORDER.lock().unwrap().as_mut() {
+        recorder.write(object(vec![("event", text("reboot")), ("time", now())]));
+    }
+}
+
+fn describe(reply: &Message) -> (&'static str, String) {
+    let kind = match reply.kind {
+        MessageKind::EvalError => "error",
+        _ => "value",
+    };
+    (kind, String::from_utf8_lossy(&reply.body).into_owned())
+}
+
+/// A recorded reply.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Reply {
+    /// `value` or `error`.
+    pub kind: String,
+    pub text: String,
+}
+
+impl fmt::Display for Reply {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.kind.as_str() {
+            "error" => write!(f, "error: {}", self.text),
+            _ => f.write_str(&self.text),
+        }
+    }
+}
+
+/// A recorded form and, unless the session ended before it arrived, its
+/// reply.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Entry {
+    pub code: String,
+    pub reply: Option<Reply>,
+}
+
+/// Read the forms and replies from a transcript, across all the sessions
+/// in it.
+pub fn load(path: &str) -> io::Result<Vec<Entry>> {
+    let src = fs::read_to_string(path)?;
+    let mut entries: Vec<Entry> = Vec::new();
+    for (i, line) in src.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let bad = |what: &str| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{}:{}: {}", path, i + 1, what),
+            )
+        };
+        let v = Value::parse(line).map_err(|e| bad(&e))?;
+        let field = |k: &str| match v.get(k) {
+            Some(Value::String(s)) => Ok(s.clone()),
+            _ => Err(bad(&format!("missing \"{}\"", k))),
+        };
+        match field("event")?.as_str() {
+            "form" => entries.push(Entry {
+                code: field("code")?,
+                reply: None,
+            }),
+            "reply" => {
+                let entry = entries
+                    .last_mut()
+                    .filter(|e| e.reply.is_none())
+                    .ok_or_else(|| bad("reply without a form"))?;
+                entry.reply = Some(Reply {
+                    kind: field("kind")?,
+                    text: field("text")?,
+                });
+            }
+            _ => {}
+        }
+    }
+    Ok(entries)
+}
+
+/// A form whose reply didn't match the transcript.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Divergence {
+    /// 1-based position of the form in the transcript.
+    pub index: usize,
+    pub expected: Reply,
+    pub got: Reply,
+}
+
+/// Send the forms of `entries` in order, writing each form and its reply
+/// to `out` and comparing replies with the recorded ones. With
+/// `stop_at_divergence`, stops after the first mismatch. Forms recorded
+/// without a reply are sent but not compared.
+pub fn replay<T: Transport, W: Write>(
+    framer: &mut Framer<T>,
+    entries: &[Entry],
+    stop_at_divergence: bool,
+    out: &mut W,
+) -> Result<Vec<Divergence>, FrameError> {
+    let mut diverged = Vec::new();
+    for (i, entry) in entries.iter().enumerate() {
+        let index = i + 1;
+        let _ = writeln!(out, "[{}] {}", index, entry.code);
+        let _ = out.flush();
+
+        let reply = link::eval(framer, index as u32, &entry.code)?;
+        let (kind, text) = describe(&reply);
+        let got = Reply {
+            kind: kind.into(),
+            text,
+        };
+        match &entry.reply {
+            Some(expected) if *expected != got => {
+                let _ = writeln!(
+                    out,
+                    "diverged:\n  expected: {}\n  got:      {}",
+                    expected, got
+                );
+                diverged.push(Divergence {
+                    index,
+                    expected: expected.clone(),
+                    got,
+                });
+                if stop_at_divergence {
+                    break;
+                }
+            }
+            None => {
+                let _ = writeln!(out, "{} (no recorded reply)", got);
+            }
+            Some(_) => {
+                let _ = writeln!(out, "{}", got);
+            }
+        }
+    }
+    Ok(diverged)
+}
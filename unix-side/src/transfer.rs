@@ -0,0 +1,214 @@
+//! Host side of the bulk transfer protocol (see `shared::transfer`):
+//! uploading a local file into Pi memory and downloading a memory range
+//! into a local file.
+
+use std::fmt;
+use std::fs;
+use std::io;
+use std::time::Duration;
+
+use shared::transfer::{self, Chunk, Progress};
+use shared::{Control, FrameError, Framer, Message, MessageKind, Transport};
+
+use crate::link;
+
+/// How many status round trips an upload or download gets to make
+/// progress before we give up on it.
+const MAX_RESUMES: u32 = 8;
+
+/// How long to wait for the Pi to start its next message during a
+/// transfer before asking where it got to.
+const TRANSFER_TIMEOUT: Duration = Duration::from_secs(2);
+
+#[derive(Debug)]
+pub enum TransferError {
+    Io(io::Error),
+    Link(FrameError),
+    /// The Pi couldn't allocate a destination buffer, or the range runs
+    /// past the end of its address space.
+    Refused,
+    /// `MAX_RESUMES` attempts in a row made no progress.
+    Stalled {
+        offset: u32,
+        len: u32,
+    },
+    /// The file doesn't fit the 32-bit length field.
+    TooLarge(u64),
+}
+
+impl From<io::Error> for TransferError {
+    fn from(e: io::Error) -> Self {
+        TransferError::Io(e)
+    }
+}
+
+impl From<FrameError> for TransferError {
+    fn from(e: FrameError) -> Self {
+        TransferError::Link(e)
+    }
+}
+
+impl fmt::Display for TransferError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TransferError::Io(e) => write!(f, "{}", e),
+            TransferError::Link(e) => write!(f, "link error: {}", e),
+            TransferError::Refused => write!(f, "pi refused the transfer"),
+            TransferError::Stalled { offset, len } => {
+                write!(f, "transfer stalled at byte {} of {}", offset, len)
+            }
+            TransferError::TooLarge(n) => write!(f, "{} bytes is too large to transfer", n),
+        }
+    }
+}
+
+/// Upload the contents of `path` to Pi address `addr`, or to a freshly
+/// allocated buffer if `addr` is None. Returns where the data ended up,
+/// which for an allocated buffer changes if the Pi reboots partway.
+pub fn upload<T: Transport>(
+    framer: &mut Framer<T>,
+    path: &str,
+    addr: Option<u32>,
+) -> Result<Progress, TransferError> {
+    let data = fs::read(path)?;
+    let len = u32::try_from(data.len()).map_err(|_| TransferError::TooLarge(data.len() as u64))?;
+    let step = transfer::chunk_len(framer.max_payload);
+
+    let begin = Control::Upload {
+        addr: addr.unwrap_or(0),
+        len,
+    };
+    let mut progress = request_progress(framer, &begin)?;
+    if progress.addr == 0 {
+        return Err(TransferError::Refused);
+    }
+
+    let mut stalls = 0;
+    while !progress.is_done() {
+        let start = progress.offset;
+        for (i, piece) in data[start as usize..].chunks(step).enumerate() {
+            let chunk = Chunk::new(start + (i * step) as u32, piece);
+            let msg = Message::new(MessageKind::Blob, 0, &chunk.encode());
+            match framer.send_message(&msg) {
+                Ok(()) => {}
+                Err(e) if e.is_fatal() => return Err(e.into()),
+                // the status query below tells us where to pick up
+                Err(_) => break,
+            }
+        }
+        let resumed_from = progress.offset;
+        // the Pi might have answered with a different transfer (e.g. it
+        // rebooted and lost ours); start it over as first asked, since a
+        // buffer it allocated went with the old kernel's heap
+        progress = request_progress(framer, &Control::QueryTransfer)?;
+        if progress.addr == 0 || progress.len != len {
+            progress = request_progress(framer, &begin)?;
+            if progress.addr == 0 {
+                return Err(TransferError::Refused);
+            }
+        }
+        if progress.offset <= resumed_from {
+            stalls += 1;
+            if stalls >= MAX_RESUMES {
+                return Err(TransferError::Stalled {
+                    offset: progress.offset,
+                    len,
+                });
+            }
+        } else {
+            stalls = 0;
+        }
+    }
+    Ok(progress)
+}
+
+/// Download `len` bytes starting at Pi address `addr` into `path`.
+pub fn download<T: Transport>(
+    framer: &mut Framer<T>,
+    addr: u32,
+    len: u32,
+    path: &str,
+) -> Result<(), TransferError> {
+    // `len` is whatever was typed; let the buffer grow with what arrives
+    let mut data = Vec::new();
+    let mut stalls = 0;
+    while (data.len() as u32) < len {
+        let resumed_from = data.len();
+        let request = Control::Download {
+            addr,
+            len,
+            offset: data.len() as u32,
+        };
+        framer.send_message(&Message::control(&request))?;
+        loop {
+            let msg = match framer.recv_message_timeout(TRANSFER_TIMEOUT) {
+                Ok(m) => m,
+                Err(e) if e.is_fatal() => return Err(e.into()),
+                Err(e) if e.is_corrupt() => continue,
+                // the Pi gave up mid-stream; ask again from where we are
+                Err(_) => break,
+            };
+            match msg.kind {
+                MessageKind::Blob => {
+                    let Some(chunk) = Chunk::decode(msg.body) else {
+                        continue;
+                    };
+                    // drop anything after a gap; it gets re-requested
+                    if chunk.offset as usize == data.len() && chunk.is_intact() {
+                        data.extend_from_slice(&chunk.data);
+                    }
+                }
+                MessageKind::Control => {
+                    match Control::decode(&msg.body) {
+                        // a refusal comes back for address 0, which
+                        // can't be the one asked for (0 + len can't wrap)
+                        Some(Control::Transfer(p)) if p.addr != addr => {
+                            return Err(TransferError::Refused);
+                        }
+                        Some(Control::Transfer(_)) => break,
+                        _ => {}
+                    }
+                }
+                MessageKind::Output => link::print_output(&msg.body),
+                _ => {}
+            }
+        }
+        if data.len() <= resumed_from {
+            stalls += 1;
+            if stalls >= MAX_RESUMES {
+                return Err(TransferError::Stalled {
+                    offset: data.len() as u32,
+                    len,
+                });
+            }
+        } else {
+            stalls = 0;
+        }
+    }
+    fs::write(path, &data)?;
+    Ok(())
+}
+
+/// Send a transfer control request and wait for the Pi's `Progress`.
+fn request_progress<T: Transport>(
+    framer: &mut Framer<T>,
+    request: &Control,
+) -> Result<Progress, TransferError> {
+    framer.send_message(&Message::control(request))?;
+    loop {
+        let msg = match framer.recv_message_timeout(TRANSFER_TIMEOUT) {
+            Ok(m) => m,
+            Err(e) if e.is_corrupt() => continue,
+            Err(e) => return Err(e.into()),
+        };
+        match msg.kind {
+            MessageKind::Control => {
+                if let Some(Control::Transfer(p)) = Control::decode(&msg.body) {
+                    return Ok(p);
+                }
+            }
+            MessageKind::Output => link::print_output(&msg.body),
+            _ => {}
+        }
+    }
+}
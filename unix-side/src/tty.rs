@@ -4,6 +4,10 @@ use std::os::unix::io::{AsRawFd, RawFd};
 use std::thread;
 use std::time::Duration;
 
+use shared::{BAUD_RATE, TransportError};
+
+use crate::trace::{Capture, Dir};
+
 const TTY_PREFIXES: &[&str] = &[
     "ttyUSB",       // linux
     "ttyACM",       // linux
@@ -11,6 +15,13 @@ const TTY_PREFIXES: &[&str] = &[
     "cu.usbserial", // mac os
 ];
 
+/// Baud rates `set_8n1` knows how to configure.
+#[cfg(not(target_os = "linux"))]
+pub const SUPPORTED_BAUD_RATES: &[u32] = &[9600, 19200, 38400, 57600, 115200, 230400];
+#[cfg(target_os = "linux")]
+pub const SUPPORTED_BAUD_RATES: &[u32] =
+    &[9600, 19200, 38400, 57600, 115200, 230400, 460800, 921600];
+
 /// Find a /dev/ttyUSB* (or equivalent) device.
 /// Panics if zero or more than one match.
 pub fn find_ttyusb() -> String {
@@ -32,6 +43,9 @@ pub fn find_ttyusb() -> String {
 
 pub struct Tty {
     file: fs::File,
+    speed: u32,
+    device: String,
+    capture: Option<Capture>,
 }
 
 impl Tty {
@@ -48,19 +62,131 @@ impl Tty {
         let fd = file.as_raw_fd();
         set_8n1(fd, speed);
 
-        eprintln!("opened tty port <{}>", device);
-        Tty { file }
+        Tty {
+            file,
+            speed,
+            device,
+            capture: None,
+        }
+    }
+
+    /// Wrap an already-open tty (e.g. one end of a `pty::pair`),
+    /// configuring it for 8N1 at `speed`.
+    pub fn from_file(file: fs::File, speed: u32) -> Self {
+        set_8n1(file.as_raw_fd(), speed);
+        Tty {
+            file,
+            speed,
+            device: "<pty>".into(),
+            capture: None,
+        }
+    }
+
+    /// Path of the device this tty was opened from.
+    pub fn device(&self) -> &str {
+        &self.device
+    }
+
+    /// Record everything sent and received from now on to `capture`,
+    /// starting with the current rate.
+    pub fn capture(&mut self, mut capture: Capture) {
+        capture.baud(self.speed);
+        self.capture = Some(capture);
+    }
+
+    /// The baud rate the port is currently configured for.
+    pub fn speed(&self) -> u32 {
+        self.speed
+    }
+
+    /// Reconfigure the port for `speed`, discarding anything received at
+    /// the old rate. Call `flush` first so pending output isn't garbled.
+    pub fn set_speed(&mut self, speed: u32) {
+        let fd = self.file.as_raw_fd();
+        set_8n1(fd, speed);
+        unsafe {
+            libc::tcflush(fd, libc::TCIFLUSH);
+        }
+        self.speed = speed;
+        if let Some(c) = &mut self.capture {
+            c.baud(speed);
+        }
     }
 }
 
 impl shared::Transport for Tty {
     fn put8(&mut self, b: u8) {
         self.file.write_all(&[b]).expect("tty write failed");
+        if let Some(c) = &mut self.capture {
+            c.byte(Dir::ToPi, b);
+        }
     }
-    fn get8(&mut self) -> u8 {
+    fn get8(&mut self, timeout: Option<Duration>) -> Result<u8, TransportError> {
+        let fd = self.file.as_raw_fd();
+        if let Some(c) = &mut self.capture
+            && wait_readable(fd, Duration::ZERO).is_err()
+        {
+            // about to wait, so the burst is over
+            c.idle();
+        }
+        if let Some(t) = timeout {
+            wait_readable(fd, t)?;
+        }
         let mut buf = [0u8; 1];
-        self.file.read_exact(&mut buf).expect("tty read failed");
-        buf[0]
+        loop {
+            match self.file.read(&mut buf) {
+                Ok(1) => {
+                    if let Some(c) = &mut self.capture {
+                        c.byte(Dir::FromPi, buf[0]);
+                    }
+                    return Ok(buf[0]);
+                }
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                // EOF or I/O error: the device went away (e.g. USB unplugged)
+                _ => return Err(TransportError::Closed),
+            }
+        }
+    }
+    /// Block until everything written so far has left the UART.
+    fn flush(&mut self) {
+        unsafe {
+            libc::tcdrain(self.file.as_raw_fd());
+        }
+        if let Some(c) = &mut self.capture {
+            c.idle();
+        }
+    }
+    fn reset(&mut self) {
+        if self.speed != BAUD_RATE {
+            self.set_speed(BAUD_RATE);
+        }
+    }
+}
+
+/// Block until `fd` is readable or `timeout` elapses.
+fn wait_readable(fd: RawFd, timeout: Duration) -> Result<(), TransportError> {
+    let mut pfd = libc::pollfd {
+        fd,
+        events: libc::POLLIN,
+        revents: 0,
+    };
+    let ms = timeout.as_millis().min(libc::c_int::MAX as u128) as libc::c_int;
+    loop {
+        let n = unsafe { libc::poll(&mut pfd, 1, ms) };
+        if n < 0 {
+            if io::Error::last_os_error().kind() == io::ErrorKind::Interrupted {
+                continue;
+            }
+            return Err(TransportError::Closed);
+        }
+        if n == 0 {
+            return Err(TransportError::Timeout);
+        }
+        if pfd.revents & libc::POLLIN != 0 {
+            return Ok(());
+        }
+        // POLLHUP / POLLERR / POLLNVAL without data
+        return Err(TransportError::Closed);
     }
 }
 
@@ -88,6 +214,10 @@ fn set_8n1(fd: RawFd, speed: u32) {
         57600 => B57600,
         38400 => B38400,
         19200 => B19200,
+        #[cfg(target_os = "linux")]
+        460800 => B460800,
+        #[cfg(target_os = "linux")]
+        921600 => B921600,
         _ => panic!("unsupported baud rate: {}", speed),
     };
 
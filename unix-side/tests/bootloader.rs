@@ -0,0 +1,94 @@
+use std::thread;
+use std::time::Duration;
+
+use shared::Transport;
+use shared::crc::crc32;
+use unix_side::bootloader::{self, BootError};
+use unix_side::loopback::{self, Loopback};
+
+fn put32(t: &mut Loopback, v: u32) {
+    for b in v.to_le_bytes() {
+        t.put8(b);
+    }
+}
+
+fn get32(t: &mut Loopback) -> u32 {
+    let mut word = [0u8; 4];
+    for b in &mut word {
+        *b = t.get8(Some(Duration::from_secs(5))).unwrap();
+    }
+    u32::from_le_bytes(word)
+}
+
+/// Play the Pi's bootloader: some line noise, a couple of requests, a
+/// message, then check what we're sent and report `verdict`. Returns the
+/// code received.
+fn fake_bootloader(mut pi: Loopback, verdict: u32) -> thread::JoinHandle<Vec<u8>> {
+    thread::spawn(move || {
+        pi.put8(0x42);
+        put32(&mut pi, bootloader::GET_PROG_INFO);
+        put32(&mut pi, bootloader::GET_PROG_INFO);
+        assert_eq!(get32(&mut pi), bootloader::PUT_PROG_INFO);
+        assert_eq!(get32(&mut pi), bootloader::LOAD_ADDR);
+        let len = get32(&mut pi);
+        let crc = get32(&mut pi);
+
+        put32(&mut pi, bootloader::PRINT_STRING);
+        put32(&mut pi, 3);
+        for &b in b"hi\n" {
+            pi.put8(b);
+        }
+        put32(&mut pi, bootloader::GET_CODE);
+        put32(&mut pi, crc);
+        assert_eq!(get32(&mut pi), bootloader::PUT_CODE);
+        let code: Vec<u8> = (0..len)
+            .map(|_| pi.get8(Some(Duration::from_secs(5))).unwrap())
+            .collect();
+        assert_eq!(crc32(&code), crc);
+        put32(&mut pi, verdict);
+        code
+    })
+}
+
+#[test]
+fn uploads_a_kernel() {
+    let (mut host, pi) = loopback::pair();
+    let pi = fake_bootloader(pi, bootloader::BOOT_SUCCESS);
+    let code: Vec<u8> = (0..5000u32).map(|i| (i * 7) as u8).collect();
+    bootloader::upload(
+        &mut host,
+        bootloader::LOAD_ADDR,
+        &code,
+        Duration::from_secs(5),
+    )
+    .unwrap();
+    assert_eq!(pi.join().unwrap(), code);
+}
+
+#[test]
+fn reports_bootloader_errors() {
+    let (mut host, pi) = loopback::pair();
+    let pi = fake_bootloader(pi, bootloader::BAD_CODE_CKSUM);
+    assert_eq!(
+        bootloader::upload(
+            &mut host,
+            bootloader::LOAD_ADDR,
+            b"code",
+            Duration::from_secs(5)
+        ),
+        Err(BootError::Refused(bootloader::BAD_CODE_CKSUM))
+    );
+    pi.join().unwrap();
+
+    // nobody there
+    let (mut host, _pi) = loopback::pair();
+    assert_eq!(
+        bootloader::upload(
+            &mut host,
+            bootloader::LOAD_ADDR,
+            b"code",
+            Duration::from_millis(50)
+        ),
+        Err(BootError::NoBootloader)
+    );
+}
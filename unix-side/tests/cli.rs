@@ -0,0 +1,106 @@
+use unix_side::cli::{Command, Options};
+
+fn parse(args: &[&str]) -> Result<Options, String> {
+    Options::parse(args.iter().map(|s| s.to_string()))
+}
+
+#[test]
+fn defaults_to_interactive() {
+    let opts = parse(&[]).unwrap();
+    assert_eq!(opts, Options::default());
+    assert!(!opts.is_batch());
+}
+
+#[test]
+fn parses_all_flags() {
+    let opts = parse(&[
+        "--device",
+        "/dev/ttyUSB1",
+        "--baud=115200",
+        "--script",
+        "os.lispi",
+        "--eval",
+        "(a)",
+        "--eval=(b)",
+        "--quiet",
+    ])
+    .unwrap();
+    assert_eq!(opts.device.as_deref(), Some("/dev/ttyUSB1"));
+    assert_eq!(opts.baud, Some(115200));
+    assert_eq!(opts.script.as_deref(), Some("os.lispi"));
+    assert_eq!(opts.eval, vec!["(a)", "(b)"]);
+    assert!(opts.quiet);
+    assert!(opts.is_batch());
+}
+
+#[test]
+fn rejects_bad_arguments() {
+    assert!(parse(&["--baud", "12345"]).is_err());
+    assert!(parse(&["--baud", "fast"]).is_err());
+    assert!(parse(&["--device"]).is_err());
+    assert!(parse(&["--frobnicate"]).is_err());
+}
+
+#[test]
+fn parses_commands() {
+    let opts = parse(&["serve", "--socket", "/tmp/x.sock"]).unwrap();
+    assert_eq!(opts.command, Command::Serve);
+    assert_eq!(opts.socket.as_deref(), Some("/tmp/x.sock"));
+
+    let opts = parse(&["connect", "--eval", "(a)"]).unwrap();
+    assert_eq!(opts.command, Command::Connect);
+    assert!(opts.is_batch());
+
+    assert!(parse(&["serve", "--eval", "(a)"]).is_err());
+    assert!(parse(&["connect", "--json"]).unwrap().json);
+    assert!(parse(&["--json", "--eval", "(a)"]).is_err());
+    let opts = parse(&["replay", "run.jsonl", "--stop-at-divergence"]).unwrap();
+    assert_eq!(opts.command, Command::Replay);
+    assert_eq!(opts.replay.as_deref(), Some("run.jsonl"));
+    assert!(opts.stop_at_divergence);
+    assert!(parse(&["replay"]).is_err());
+    assert!(parse(&["replay", "--quiet"]).is_err());
+    assert!(parse(&["--stop-at-divergence"]).is_err());
+    assert!(parse(&["serve", "--record", "x"]).is_err());
+    assert_eq!(
+        parse(&["--record", "x"]).unwrap().record.as_deref(),
+        Some("x")
+    );
+    assert_eq!(
+        parse(&["serve", "--prelude", "a.l", "--prelude=b.l"])
+            .unwrap()
+            .preludes,
+        ["a.l", "b.l"]
+    );
+    assert!(parse(&["connect", "--prelude", "a.l"]).is_err());
+    assert_eq!(
+        parse(&["serve", "--host-dir", "lib"])
+            .unwrap()
+            .host_dir
+            .as_deref(),
+        Some("lib")
+    );
+    assert!(parse(&["connect", "--host-dir", "lib"]).is_err());
+
+    let opts = parse(&["boot", "kernel.bin", "--eval", "(a)"]).unwrap();
+    assert_eq!(opts.command, Command::Boot);
+    assert_eq!(opts.kernel.as_deref(), Some("kernel.bin"));
+    assert!(parse(&["boot", "--quiet"]).is_err());
+
+    let opts = parse(&["trace", "run.cap"]).unwrap();
+    assert_eq!(opts.command, Command::Trace);
+    assert_eq!(opts.trace.as_deref(), Some("run.cap"));
+    assert!(parse(&["trace"]).is_err());
+    assert!(parse(&["trace", "run.cap", "--capture", "x"]).is_err());
+    assert_eq!(
+        parse(&["boot", "k.bin", "--capture", "x"])
+            .unwrap()
+            .capture
+            .as_deref(),
+        Some("x")
+    );
+    assert!(parse(&["connect", "--capture", "x"]).is_err());
+
+    // only as the first argument
+    assert!(parse(&["--quiet", "serve"]).is_err());
+}
@@ -0,0 +1,192 @@
+//! A stand-in for the Pi kernel: answers the handshake and "evaluates"
+//! requests by echoing them back, over any transport.
+
+// each test binary uses its own subset of these
+#![allow(dead_code)]
+
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use shared::control::feature;
+use shared::host::HostError;
+use shared::{Control, Framer, Hello, Message, MessageKind, Transport};
+
+/// Short timeouts so lost frames are retransmitted quickly in tests.
+pub fn fast<T: Transport>(mut framer: Framer<T>) -> Framer<T> {
+    framer.timeout = Duration::from_millis(50);
+    framer.ack_timeout = Duration::from_millis(100);
+    framer
+}
+
+pub fn kernel_hello() -> Hello {
+    Hello {
+        version: shared::PROTOCOL_VERSION,
+        max_payload: shared::MAX_PAYLOAD_LEN,
+        baud_rates: vec![shared::BAUD_RATE, 460800],
+        build_id: "fake".into(),
+        features: feature::COMPRESSION,
+    }
+}
+
+/// Run a fake kernel on `transport` until the link closes. Requests
+/// whose source starts with `(error` get an `EvalError`; those starting
+/// with `(reboot` are dropped and answered with a `Boot`, as if the
+/// kernel had crashed; those starting with `(spin` run until a
+/// `Control::Interrupt` arrives; everything else is echoed back as the
+/// result. `(define NAME` adds NAME to the globals listed in `Names`.
+/// `(cat PATH)` and `(save PATH TEXT)` read and write host files,
+/// answering with the file or `saved` (or the host's error).
+/// `(print TEXT)` sends TEXT as console output, then answers `nil`.
+/// Baud switches are confirmed but not acted on.
+pub fn spawn_fake_pi<T: Transport + Send + 'static>(transport: T) -> JoinHandle<()> {
+    spawn_fake_pi_with(transport, kernel_hello())
+}
+
+/// `spawn_fake_pi`, advertising `hello` instead of `kernel_hello()`.
+pub fn spawn_fake_pi_with<T: Transport + Send + 'static>(
+    transport: T,
+    hello: Hello,
+) -> JoinHandle<()> {
+    run_fake_pi(transport, hello, Arc::default())
+}
+
+/// `spawn_fake_pi`, also returning the source of every request it
+/// receives, in order.
+pub fn spawn_logged_fake_pi<T: Transport + Send + 'static>(
+    transport: T,
+) -> (JoinHandle<()>, Arc<Mutex<Vec<String>>>) {
+    let log = Arc::default();
+    (
+        run_fake_pi(transport, kernel_hello(), Arc::clone(&log)),
+        log,
+    )
+}
+
+fn run_fake_pi<T: Transport + Send + 'static>(
+    transport: T,
+    hello: Hello,
+    log: Arc<Mutex<Vec<String>>>,
+) -> JoinHandle<()> {
+    thread::spawn(move || {
+        let mut framer = fast(Framer::pi_side(transport));
+        let mut globals = Vec::new();
+        loop {
+            let msg = match framer.recv_message() {
+                Ok(m) => m,
+                Err(e) if e.is_fatal() => return,
+                Err(_) => continue,
+            };
+            if msg.kind == MessageKind::EvalRequest {
+                log.lock()
+                    .unwrap()
+                    .push(String::from_utf8_lossy(&msg.body).into_owned());
+                if let Some(rest) = msg.body.strip_prefix(b"(define ") {
+                    let name = rest.split(|&b| b == b' ' || b == b')').next().unwrap();
+                    globals.push(String::from_utf8_lossy(name).into_owned());
+                }
+            }
+            let reply = match msg.kind {
+                MessageKind::EvalRequest if msg.body.starts_with(b"(reboot") => {
+                    Message::control(&Control::Boot)
+                }
+                MessageKind::EvalRequest if msg.body.starts_with(b"(spin") => loop {
+                    match framer.recv_message() {
+                        Ok(m) if Control::decode(&m.body) == Some(Control::Interrupt) => {
+                            break Message::new(MessageKind::EvalError, msg.id, b"interrupted");
+                        }
+                        Err(e) if e.is_fatal() => return,
+                        _ => {}
+                    }
+                },
+                MessageKind::EvalRequest if msg.body.starts_with(b"(cat ") => {
+                    let src = String::from_utf8_lossy(&msg.body);
+                    let path = src["(cat ".len()..].trim_end_matches(')').to_string();
+                    match host_request(&mut framer, Control::HostRead { path }) {
+                        Some(Ok(data)) => Message::new(MessageKind::EvalResult, msg.id, &data),
+                        Some(Err(e)) => {
+                            Message::new(MessageKind::EvalError, msg.id, e.message().as_bytes())
+                        }
+                        None => return,
+                    }
+                }
+                MessageKind::EvalRequest if msg.body.starts_with(b"(save ") => {
+                    let src = String::from_utf8_lossy(&msg.body);
+                    let args = src["(save ".len()..].trim_end_matches(')');
+                    let (path, text) = args.split_once(' ').unwrap();
+                    let write = Control::HostWrite {
+                        path: path.into(),
+                        data: text.into(),
+                        append: false,
+                    };
+                    match host_request(&mut framer, write) {
+                        Some(Ok(_)) => Message::new(MessageKind::EvalResult, msg.id, b"saved"),
+                        Some(Err(e)) => {
+                            Message::new(MessageKind::EvalError, msg.id, e.message().as_bytes())
+                        }
+                        None => return,
+                    }
+                }
+                MessageKind::EvalRequest if msg.body.starts_with(b"(print ") => {
+                    let text = msg.body["(print ".len()..].strip_suffix(b")").unwrap();
+                    let output = Message::new(MessageKind::Output, 0, text);
+                    if framer.send_message(&output).is_err() {
+                        return;
+                    }
+                    Message::new(MessageKind::EvalResult, msg.id, b"nil")
+                }
+                MessageKind::EvalRequest if msg.body.starts_with(b"(error") => {
+                    Message::new(MessageKind::EvalError, msg.id, b"boom")
+                }
+                MessageKind::EvalRequest => {
+                    Message::new(MessageKind::EvalResult, msg.id, &msg.body)
+                }
+                MessageKind::Control => match Control::decode(&msg.body) {
+                    Some(Control::Hello(peer)) => {
+                        framer.compress = hello.has_feature(feature::COMPRESSION)
+                            && peer.has_feature(feature::COMPRESSION);
+                        Message::control(&Control::Hello(hello.clone()))
+                    }
+                    // a pty doesn't care about speed; just confirm
+                    Some(Control::SetBaud(rate)) => Message::control(&Control::SetBaud(rate)),
+                    Some(Control::QueryNames) => Message::control(&Control::Names {
+                        globals: globals.clone(),
+                        specials: ["define", "if", "lambda", "let", "list"]
+                            .map(String::from)
+                            .into(),
+                        syscalls: ["monitor/clear", "timer/us", "timer/us64"]
+                            .map(String::from)
+                            .into(),
+                    }),
+                    _ => continue,
+                },
+                _ => continue,
+            };
+            if let Err(e) = framer.send_message(&reply)
+                && e.is_fatal()
+            {
+                return;
+            }
+        }
+    })
+}
+
+/// Send a host file request and wait for the answer, or None if the link
+/// closed.
+fn host_request<T: Transport>(
+    framer: &mut Framer<T>,
+    request: Control,
+) -> Option<Result<Vec<u8>, HostError>> {
+    framer.send_message(&Message::control(&request)).ok()?;
+    loop {
+        match framer.recv_message() {
+            Ok(m) => {
+                if let Some(Control::HostReply(reply)) = Control::decode(&m.body) {
+                    return Some(reply);
+                }
+            }
+            Err(e) if e.is_fatal() => return None,
+            Err(_) => {}
+        }
+    }
+}
@@ -0,0 +1,126 @@
+mod common;
+
+use std::io::{self, Write};
+
+use common::{fast, spawn_fake_pi};
+use shared::Framer;
+use unix_side::complete::{self, Names};
+use unix_side::editor::{Completion, LineSource, Prompt};
+use unix_side::{link, loopback, repl};
+
+fn names() -> Names {
+    let strings = |v: &[&str]| v.iter().map(|s| s.to_string()).collect();
+    Names {
+        globals: strings(&["life/neighbors", "life/step", "lst"]),
+        specials: strings(&["let", "list", "lambda"]),
+        syscalls: strings(&["monitor/clear", "timer/us", "timer/us64"]),
+    }
+}
+
+fn candidates(line: &str) -> (usize, Vec<String>) {
+    let Completion { start, candidates } = complete::complete(&names(), line);
+    (start, candidates)
+}
+
+#[test]
+fn completion_depends_on_position() {
+    // head position offers the specials too
+    assert_eq!(
+        candidates("(l").1,
+        [
+            "lambda",
+            "let",
+            "life/neighbors",
+            "life/step",
+            "list",
+            "lst"
+        ]
+    );
+    assert_eq!(
+        candidates("( li").1,
+        ["life/neighbors", "life/step", "list"]
+    );
+    // arguments only name globals
+    assert_eq!(
+        candidates("(map l").1,
+        ["life/neighbors", "life/step", "lst"]
+    );
+    assert_eq!(
+        candidates("(f 'life/n"),
+        (4, vec!["life/neighbors".to_string()])
+    );
+    // syscalls keep their @
+    assert_eq!(candidates("(@timer/").1, ["@timer/us", "@timer/us64"]);
+    assert_eq!(
+        candidates("(+ 1 (@m"),
+        (6, vec!["@monitor/clear".to_string()])
+    );
+    // but `,@` is a splice
+    assert_eq!(candidates("`(a ,@ls"), (6, vec!["lst".to_string()]));
+    assert_eq!(candidates("(frob").1, Vec::<String>::new());
+}
+
+/// Types each line, first asking for completion of its prefix up to the
+/// `|`, and keeps what was offered.
+struct Typist {
+    lines: Vec<&'static str>,
+    offered: Vec<Vec<String>>,
+}
+
+impl LineSource for &mut Typist {
+    fn read_line(
+        &mut self,
+        _prompt: Prompt,
+        _out: &mut dyn Write,
+        complete: &mut dyn FnMut(&str) -> Completion,
+    ) -> io::Result<Option<String>> {
+        if self.lines.is_empty() {
+            return Ok(None);
+        }
+        let line = self.lines.remove(0);
+        let (before, after) = line.split_once('|').unwrap_or((line, ""));
+        self.offered.push(complete(before).candidates);
+        Ok(Some(format!("{}{}", before, after)))
+    }
+}
+
+#[test]
+fn repl_completes_names_defined_since() {
+    let (host, pi) = loopback::pair();
+    let pi = spawn_fake_pi(pi);
+    let mut framer = fast(Framer::unix_side(host));
+
+    let mut typist = Typist {
+        lines: vec!["(define life/step 1)", "(life/s|)", "(@timer|/us)"],
+        offered: Vec::new(),
+    };
+    repl::run(&mut framer, &mut typist, &mut Vec::new());
+    assert_eq!(
+        typist.offered,
+        [
+            vec![],
+            vec!["life/step".to_string()],
+            vec!["@timer/us".to_string(), "@timer/us64".to_string()]
+        ]
+    );
+
+    drop(framer);
+    pi.join().unwrap();
+}
+
+#[test]
+fn names_come_from_the_kernel() {
+    let (host, pi) = loopback::pair();
+    let pi = spawn_fake_pi(pi);
+    let mut framer = fast(Framer::unix_side(host));
+
+    link::handshake(&mut framer).unwrap();
+    link::eval(&mut framer, 1, "(define x 1)").unwrap();
+    let names = complete::query_names(&mut framer).unwrap();
+    assert_eq!(names.globals, ["x"]);
+    assert!(names.specials.contains(&"lambda".to_string()));
+    assert!(names.syscalls.contains(&"timer/us64".to_string()));
+
+    drop(framer);
+    pi.join().unwrap();
+}
@@ -0,0 +1,272 @@
+mod common;
+
+use common::{fast, kernel_hello, spawn_fake_pi, spawn_fake_pi_with};
+use shared::control::Incompatible;
+use shared::{Control, FrameError, Framer, Message, MessageKind, Transport, TransportError};
+use std::time::Duration;
+use unix_side::fault::{Faults, Faulty};
+use unix_side::{link, loopback};
+
+fn eval<T: Transport>(framer: &mut Framer<T>, id: u32, src: &str) -> Message {
+    let request = Message::new(MessageKind::EvalRequest, id, src.as_bytes());
+    framer.send_message(&request).expect("send failed");
+    link::await_reply(framer, id).expect("no reply")
+}
+
+#[test]
+fn round_trip_over_loopback() {
+    let (host, pi) = loopback::pair();
+    let pi = spawn_fake_pi(pi);
+    let mut framer = fast(Framer::unix_side(host));
+
+    let kernel = link::handshake(&mut framer).unwrap();
+    assert_eq!(kernel.build_id, "fake");
+
+    for id in 1..=10 {
+        let src = format!("(+ {} 1)", id);
+        let reply = eval(&mut framer, id, &src);
+        assert_eq!(reply.kind, MessageKind::EvalResult);
+        assert_eq!(reply.id, id);
+        assert_eq!(reply.text(), Some(src.as_str()));
+    }
+    let reply = eval(&mut framer, 11, "(error)");
+    assert_eq!(reply.kind, MessageKind::EvalError);
+
+    drop(framer);
+    pi.join().unwrap();
+}
+
+#[test]
+fn console_output_comes_before_the_reply() {
+    let (host, pi) = loopback::pair();
+    let pi = spawn_fake_pi(pi);
+    let mut framer = fast(Framer::unix_side(host));
+
+    link::handshake(&mut framer).unwrap();
+    let mut printed = Vec::new();
+    let reply = link::eval_with(&mut framer, 1, "(print hello)", &mut |bytes| {
+        printed.extend_from_slice(bytes)
+    })
+    .unwrap();
+    assert_eq!(printed, b"hello");
+    assert_eq!(reply.text(), Some("nil"));
+
+    drop(framer);
+    pi.join().unwrap();
+}
+
+#[test]
+fn resyncs_after_line_noise() {
+    let (mut host, pi) = loopback::pair();
+    let pi = spawn_fake_pi(pi);
+
+    // a partial frame (header zeros, then silence) followed by junk that
+    // happens to contain zero bytes
+    for _ in 0..16 {
+        host.put8(0);
+    }
+    for b in [0x01, 0x00, 0xEF, 0xBE, 0x00, 0x00, 0x13, 0x37] {
+        host.put8(b);
+    }
+    let mut framer = fast(Framer::unix_side(host));
+    let reply = eval(&mut framer, 1, "(hello)");
+    assert_eq!(reply.text(), Some("(hello)"));
+
+    drop(framer);
+    pi.join().unwrap();
+}
+
+#[test]
+fn realigns_after_dropped_byte() {
+    let (mut host, pi) = loopback::pair();
+    let pi = spawn_fake_pi(pi);
+
+    // an odd number of stray bytes leaves a word-based scanner
+    // permanently misaligned
+    for b in [0xAA, 0xBB, 0xCC] {
+        host.put8(b);
+    }
+    let mut framer = fast(Framer::unix_side(host));
+    for id in 1..=3 {
+        let reply = eval(&mut framer, id, "(aligned)");
+        assert_eq!(reply.text(), Some("(aligned)"));
+    }
+
+    drop(framer);
+    pi.join().unwrap();
+}
+
+#[test]
+fn peer_stopping_mid_frame_times_out() {
+    let (host, mut pi) = loopback::pair();
+    let mut framer = fast(Framer::unix_side(host));
+
+    // header and kind, then nothing
+    for _ in 0..16 {
+        pi.put8(0);
+    }
+    pi.put32(1);
+    assert_eq!(framer.recv().unwrap_err(), FrameError::Timeout);
+
+    drop(pi);
+    assert_eq!(framer.recv().unwrap_err(), FrameError::TransportClosed);
+}
+
+#[test]
+fn survives_faulty_link() {
+    let faults = Faults {
+        drop: 0.0002,
+        duplicate: 0.0002,
+        corrupt: 0.0002,
+        ..Faults::default()
+    };
+    let (host, pi) = loopback::pair();
+    let pi = spawn_fake_pi(Faulty::new(pi, faults, 0x5EED));
+    let mut framer = fast(Framer::unix_side(Faulty::new(host, faults, 0xFEED)));
+
+    for id in 1..=40 {
+        // vary the size so faults land in headers, payloads and footers
+        let src = format!("({} {})", id, "x".repeat(id as usize * 3));
+        let reply = eval(&mut framer, id, &src);
+        assert_eq!(reply.id, id);
+        assert_eq!(reply.text(), Some(src.as_str()));
+    }
+
+    drop(framer);
+    pi.join().unwrap();
+}
+
+/// Counts the bytes written through it.
+struct Counting<T: Transport> {
+    inner: T,
+    sent: usize,
+}
+
+impl<T: Transport> Transport for Counting<T> {
+    fn put8(&mut self, b: u8) {
+        self.sent += 1;
+        self.inner.put8(b);
+    }
+
+    fn get8(&mut self, timeout: Option<Duration>) -> Result<u8, TransportError> {
+        self.inner.get8(timeout)
+    }
+}
+
+#[test]
+fn compresses_after_handshake() {
+    let (host, pi) = loopback::pair();
+    let pi = spawn_fake_pi(pi);
+    let mut framer = fast(Framer::unix_side(Counting {
+        inner: host,
+        sent: 0,
+    }));
+
+    link::handshake(&mut framer).unwrap();
+    assert!(framer.compress);
+    framer.transport.sent = 0;
+
+    let src = format!("(list {})", "(1 2 3) ".repeat(1000));
+    let reply = eval(&mut framer, 1, &src);
+    assert_eq!(reply.text(), Some(src.as_str()));
+    // the request went out as a fraction of its size (plus framing
+    // overhead for the request and the ack of the reply)
+    assert!(framer.transport.sent < src.len() / 4);
+
+    drop(framer);
+    pi.join().unwrap();
+}
+
+#[test]
+fn no_compression_with_plain_peer() {
+    let (host, pi) = loopback::pair();
+    let plain = shared::Hello {
+        features: 0,
+        ..kernel_hello()
+    };
+    let pi = spawn_fake_pi_with(pi, plain);
+    let mut framer = fast(Framer::unix_side(host));
+
+    link::handshake(&mut framer).unwrap();
+    assert!(!framer.compress);
+    let src = "(x) ".repeat(100);
+    assert_eq!(eval(&mut framer, 1, &src).text(), Some(src.as_str()));
+
+    drop(framer);
+    pi.join().unwrap();
+}
+
+#[test]
+fn refuses_incompatible_kernel() {
+    let (host, pi) = loopback::pair();
+    let newer = shared::Hello {
+        version: shared::PROTOCOL_VERSION + 1,
+        ..kernel_hello()
+    };
+    let pi = spawn_fake_pi_with(pi, newer);
+    let mut framer = fast(Framer::unix_side(host));
+
+    assert_eq!(
+        link::handshake(&mut framer).unwrap_err(),
+        FrameError::Incompatible(Incompatible::Version {
+            ours: shared::PROTOCOL_VERSION,
+            theirs: shared::PROTOCOL_VERSION + 1,
+        })
+    );
+
+    drop(framer);
+    pi.join().unwrap();
+}
+
+#[test]
+fn picks_a_message_out_of_the_queue() {
+    let (host, pi) = loopback::pair();
+    let sender = std::thread::spawn(move || {
+        let mut framer = fast(Framer::unix_side(host));
+        let reply = Message::control(&Control::HostReply(Ok(b"data".to_vec())));
+        framer.send_message(&reply).unwrap();
+        framer
+            .send_message(&Message::control(&Control::Interrupt))
+            .unwrap();
+        framer
+    });
+    let mut framer = fast(Framer::pi_side(pi));
+
+    let is_interrupt = |m: &Message| Control::decode(&m.body) == Some(Control::Interrupt);
+    framer.poll(Duration::from_secs(5)).unwrap();
+    assert!(framer.take_queued(is_interrupt).is_none());
+    framer.poll(Duration::from_secs(5)).unwrap();
+    assert!(framer.take_queued(is_interrupt).is_some());
+    // what wasn't picked is still there for `recv`
+    let left = framer.recv_message().unwrap();
+    assert_eq!(
+        Control::decode(&left.body),
+        Some(Control::HostReply(Ok(b"data".to_vec())))
+    );
+
+    drop(sender.join().unwrap());
+}
+
+#[test]
+fn posts_without_waiting_for_an_ack() {
+    let (host, pi) = loopback::pair();
+    let mut framer = fast(Framer::pi_side(pi));
+    // nobody is reading yet, and posting doesn't care
+    framer.post_message(&Message::new(MessageKind::Output, 0, b"hello"));
+
+    let reader = std::thread::spawn(move || {
+        let mut framer = fast(Framer::unix_side(host));
+        let output = framer.recv_message().unwrap();
+        let reply = framer.recv_message().unwrap();
+        (output, reply)
+    });
+    // the ack for the post is skipped while waiting for this one's
+    let reply = Message::new(MessageKind::EvalResult, 1, b"nil");
+    framer.send_message(&reply).unwrap();
+
+    let (output, got) = reader.join().unwrap();
+    assert_eq!(output.kind, MessageKind::Output);
+    assert_eq!(output.body, b"hello");
+    assert_eq!(got.kind, MessageKind::EvalResult);
+    assert_eq!(got.text(), Some("nil"));
+}
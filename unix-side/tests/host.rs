@@ -0,0 +1,79 @@
+mod common;
+
+use std::fs;
+use std::path::PathBuf;
+
+use common::{fast, spawn_fake_pi};
+use shared::host::HostError;
+use shared::{Framer, MessageKind};
+use unix_side::{host, link, loopback};
+
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("lispi-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    dir.canonicalize().unwrap()
+}
+
+#[test]
+fn paths_stay_in_the_directory() {
+    let dir = temp_dir("resolve");
+    fs::create_dir(dir.join("drivers")).unwrap();
+
+    assert_eq!(
+        host::resolve(&dir, "drivers/gpio.lispi"),
+        Ok(dir.join("drivers/gpio.lispi"))
+    );
+    assert_eq!(
+        host::resolve(&dir, "./new/log.txt"),
+        Ok(dir.join("new/log.txt"))
+    );
+    for outside in ["", "/etc/passwd", "../x", "drivers/../../x"] {
+        assert_eq!(
+            host::resolve(&dir, outside),
+            Err(HostError::Outside),
+            "{}",
+            outside
+        );
+    }
+
+    // nor through a symlink
+    std::os::unix::fs::symlink("/tmp", dir.join("escape")).unwrap();
+    assert_eq!(host::resolve(&dir, "escape/x"), Err(HostError::Outside));
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn kernel_reads_and_writes_host_files() {
+    let (host_end, pi) = loopback::pair();
+    let pi = spawn_fake_pi(pi);
+    let mut framer = fast(Framer::unix_side(host_end));
+
+    // nothing is shared until asked
+    let reply = link::eval(&mut framer, 1, "(cat a.lispi)").unwrap();
+    assert_eq!(reply.kind, MessageKind::EvalError);
+    assert_eq!(reply.body, HostError::NoDirectory.message().as_bytes());
+
+    let dir = temp_dir("serve");
+    host::share(&dir).unwrap();
+    fs::write(dir.join("a.lispi"), "(define x 1)").unwrap();
+
+    let reply = link::eval(&mut framer, 2, "(cat a.lispi)").unwrap();
+    assert_eq!(reply.kind, MessageKind::EvalResult);
+    assert_eq!(reply.body, b"(define x 1)");
+
+    let reply = link::eval(&mut framer, 3, "(save logs/run.txt hello)").unwrap();
+    assert_eq!(reply.kind, MessageKind::EvalResult);
+    assert_eq!(
+        fs::read_to_string(dir.join("logs/run.txt")).unwrap(),
+        "hello"
+    );
+
+    let reply = link::eval(&mut framer, 4, "(cat missing)").unwrap();
+    assert_eq!(reply.body, HostError::NotFound.message().as_bytes());
+
+    drop(framer);
+    pi.join().unwrap();
+    fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,39 @@
+//! Interrupt requests are process-wide, so they get their own test
+//! binary.
+
+mod common;
+
+use std::thread;
+use std::time::Duration;
+
+use common::{fast, spawn_fake_pi};
+use shared::{Framer, MessageKind};
+use unix_side::{interrupt, link, loopback};
+
+#[test]
+fn interrupts_a_running_evaluation() {
+    let (host, pi) = loopback::pair();
+    let fake = spawn_fake_pi(pi);
+    let mut framer = fast(Framer::unix_side(host));
+
+    // nothing running: nothing to interrupt
+    assert!(!interrupt::request());
+
+    let ctrl_c = thread::spawn(|| {
+        while !interrupt::request() {
+            thread::sleep(Duration::from_millis(10));
+        }
+    });
+    let reply = link::eval(&mut framer, 1, "(spin)").unwrap();
+    ctrl_c.join().unwrap();
+    assert_eq!(reply.kind, MessageKind::EvalError);
+    assert_eq!(reply.body, b"interrupted");
+
+    // the request doesn't outlive its evaluation
+    assert!(!interrupt::request());
+    let reply = link::eval(&mut framer, 2, "(+ 1 2)").unwrap();
+    assert_eq!(reply.kind, MessageKind::EvalResult);
+
+    drop(framer);
+    fake.join().unwrap();
+}
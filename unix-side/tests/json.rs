@@ -0,0 +1,103 @@
+mod common;
+
+use std::io::Cursor;
+
+use common::{fast, spawn_fake_pi};
+use shared::Framer;
+use unix_side::json::{self, Value};
+use unix_side::loopback;
+
+#[test]
+fn values_round_trip() {
+    let src = r#"{"a":[1,-2.5,true,false,null],"b":"q\"\\\n\t\u0001é","c":{}}"#;
+    let v = Value::parse(src).unwrap();
+    assert_eq!(v.get("b"), Some(&Value::String("q\"\\\n\t\u{1}é".into())));
+    assert_eq!(v.to_string(), src);
+    assert_eq!(Value::parse(&v.to_string()).unwrap(), v);
+
+    // escaped surrogate pairs, and whitespace between tokens
+    let v = Value::parse(" { \"s\" : \"\\ud83d\\ude00\\u00e9\" } ").unwrap();
+    assert_eq!(v.get("s"), Some(&Value::String("😀é".into())));
+}
+
+#[test]
+fn rejects_malformed_json() {
+    for bad in [
+        "",
+        "{",
+        "{\"a\" 1}",
+        "{\"a\":1,}",
+        "[1 2]",
+        "\"open",
+        "\"\\x\"",
+        "nul",
+        "1 2",
+        "{a:1}",
+    ] {
+        assert!(Value::parse(bad).is_err(), "{:?} parsed", bad);
+    }
+}
+
+#[test]
+fn limits_nesting() {
+    let nested = |n: usize| format!("{}{}", "[".repeat(n), "]".repeat(n));
+    assert!(Value::parse(&nested(128)).is_ok());
+    assert_eq!(Value::parse(&nested(129)), Err("nesting too deep".into()));
+    // deep enough to overflow the stack without the limit
+    assert!(Value::parse(&"[".repeat(1_000_000)).is_err());
+}
+
+/// The events `json::run` writes for `input`, with `elapsed_ms` checked
+/// and dropped since it varies.
+fn events(input: &str) -> Vec<String> {
+    let (host, pi) = loopback::pair();
+    let pi = spawn_fake_pi(pi);
+    let mut framer = fast(Framer::unix_side(host));
+
+    let mut out = Vec::new();
+    json::run(&mut framer, Cursor::new(input), &mut out);
+    drop(framer);
+    pi.join().unwrap();
+
+    String::from_utf8(out)
+        .unwrap()
+        .lines()
+        .map(|line| match Value::parse(line).unwrap() {
+            Value::Object(fields) => {
+                let (timed, rest): (Vec<_>, Vec<_>) =
+                    fields.into_iter().partition(|(k, _)| k == "elapsed_ms");
+                if let Some((_, Value::Number(ms))) = timed.first() {
+                    assert!(*ms >= 0.0);
+                }
+                Value::Object(rest).to_string()
+            }
+            v => panic!("not an object: {}", v),
+        })
+        .collect()
+}
+
+#[test]
+fn replies_are_paired_with_requests() {
+    let input = concat!(
+        "{\"id\": 7, \"code\": \"(+ 1 2)\"}\n",
+        "\n",
+        "{\"code\": \"(car '(\\\"a\\\"))\"}\n",
+        "{\"id\": \"e1\", \"code\": \"(error 'x)\"}\n",
+        "(not json)\n",
+        "{\"id\": 9}\n",
+        "{\"id\": [1], \"code\": \"x\"}\n",
+    );
+    assert_eq!(
+        events(input),
+        [
+            r#"{"id":null,"kind":"ready","text":""}"#,
+            r#"{"id":7,"kind":"value","text":"(+ 1 2)"}"#,
+            // no id: we number it ourselves
+            r#"{"id":2,"kind":"value","text":"(car '(\"a\"))"}"#,
+            r#"{"id":"e1","kind":"error","text":"boom"}"#,
+            r#"{"id":null,"kind":"error","text":"bad request: unexpected character at offset 0"}"#,
+            r#"{"id":9,"kind":"error","text":"bad request: expected an object with a \"code\" string"}"#,
+            r#"{"id":null,"kind":"error","text":"bad request: id must be a number or a string"}"#,
+        ]
+    );
+}
@@ -0,0 +1,58 @@
+use shared::lz::{compress, decompress};
+
+fn round_trip(input: &[u8]) -> usize {
+    let block = compress(input);
+    assert_eq!(decompress(&block, input.len()).as_deref(), Some(input));
+    block.len()
+}
+
+/// Deterministic incompressible bytes.
+fn noise(n: usize) -> Vec<u8> {
+    let mut x: u32 = 0x1234_5678;
+    (0..n)
+        .map(|_| {
+            x ^= x << 13;
+            x ^= x >> 17;
+            x ^= x << 5;
+            x as u8
+        })
+        .collect()
+}
+
+#[test]
+fn round_trips() {
+    round_trip(b"");
+    round_trip(b"a");
+    round_trip(b"abcd");
+    round_trip(b"(define (f x) (+ x 1)) (define (g x) (+ x 1))");
+    round_trip(&noise(5000));
+    // long literal runs and long matches both need extension bytes
+    let mut mixed = noise(700);
+    mixed.extend(std::iter::repeat_n(b'z', 3000));
+    mixed.extend(noise(300));
+    round_trip(&mixed);
+}
+
+#[test]
+fn shrinks_repetitive_input() {
+    let src = "(put32 0x20200000 0) ".repeat(200);
+    assert!(round_trip(src.as_bytes()) < src.len() / 10);
+    assert!(round_trip(&[0u8; 65536]) < 300);
+}
+
+#[test]
+fn rejects_malformed_blocks() {
+    let src = b"hello hello hello hello hello";
+    let block = compress(src);
+    // wrong length, either way
+    assert_eq!(decompress(&block, src.len() - 1), None);
+    assert_eq!(decompress(&block, src.len() + 1), None);
+    // truncated
+    for cut in 1..block.len() {
+        assert_eq!(decompress(&block[..cut], src.len()), None);
+    }
+    // match reaching back before the start of the output
+    assert_eq!(decompress(&[0x10, b'a', 0x05, 0x00], 5), None);
+    // zero offset
+    assert_eq!(decompress(&[0x10, b'a', 0x00, 0x00], 5), None);
+}
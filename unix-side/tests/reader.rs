@@ -0,0 +1,51 @@
+use unix_side::reader::{ReadError, split_forms};
+
+fn forms(src: &str) -> (Vec<&str>, &str) {
+    let split = split_forms(src).unwrap();
+    (split.forms, split.rest)
+}
+
+#[test]
+fn splits_top_level_forms() {
+    assert_eq!(forms(""), (vec![], ""));
+    assert_eq!(forms("  ; just a comment\n"), (vec![], ""));
+    assert_eq!(
+        forms("(a b) (c)\n42 foo"),
+        (vec!["(a b)", "(c)", "42", "foo"], "")
+    );
+    assert_eq!(
+        forms("(defun f (x)\n  ; (not code\n  (+ x 1))\n"),
+        (vec!["(defun f (x)\n  ; (not code\n  (+ x 1))"], "")
+    );
+}
+
+#[test]
+fn prefixes_stick_to_their_form() {
+    assert_eq!(
+        forms("'x `(a ,b ,@c) ' (d)"),
+        (vec!["'x", "`(a ,b ,@c)", "' (d)"], "")
+    );
+}
+
+#[test]
+fn strings_hide_delimiters() {
+    assert_eq!(
+        forms(r#"(print "(;\" )") "x""#),
+        (vec![r#"(print "(;\" )")"#, r#""x""#], "")
+    );
+}
+
+#[test]
+fn unfinished_forms_are_left_over() {
+    assert_eq!(forms("(a) (b\n  (c)"), (vec!["(a)"], "(b\n  (c)"));
+    assert_eq!(forms("(s \"open"), (vec![], "(s \"open"));
+    assert_eq!(forms("(a) '"), (vec!["(a)"], "'"));
+}
+
+#[test]
+fn stray_close_paren_is_an_error() {
+    assert_eq!(
+        split_forms("(a)\n(b))\n"),
+        Err(ReadError::UnexpectedClose { line: 2 })
+    );
+}
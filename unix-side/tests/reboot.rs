@@ -0,0 +1,42 @@
+//! The preludes are process-wide, so reboot handling gets its own test
+//! binary.
+
+mod common;
+
+use common::{fast, spawn_logged_fake_pi};
+use shared::{Framer, MessageKind};
+use unix_side::{link, loopback};
+
+#[test]
+fn reconnects_and_resends_preludes_after_a_reboot() {
+    let path = std::env::temp_dir().join(format!("lispi-prelude-{}", std::process::id()));
+    std::fs::write(&path, "(defun f (x) x)\n; restored\n(setq y 2)\n").unwrap();
+    link::set_preludes(vec![path.to_str().unwrap().into()]);
+
+    let (host, pi) = loopback::pair();
+    let (fake, log) = spawn_logged_fake_pi(pi);
+    let mut framer = fast(Framer::unix_side(host));
+    link::handshake(&mut framer).unwrap();
+
+    // the request is lost with the old Image
+    let reply = link::eval(&mut framer, 1, "(reboot)").unwrap();
+    assert_eq!(reply.kind, MessageKind::EvalError);
+    assert_eq!(reply.id, 1);
+    assert_eq!(
+        reply.body,
+        b"pi rebooted before replying; its Image was reset"
+    );
+    assert_eq!(
+        *log.lock().unwrap(),
+        ["(reboot)", "(defun f (x) x)", "(setq y 2)"]
+    );
+
+    // and the link carries on as before
+    let reply = link::eval(&mut framer, 2, "(f 1)").unwrap();
+    assert_eq!(reply.kind, MessageKind::EvalResult);
+    assert_eq!(reply.body, b"(f 1)");
+
+    drop(framer);
+    fake.join().unwrap();
+    let _ = std::fs::remove_file(&path);
+}
@@ -0,0 +1,203 @@
+mod common;
+
+use std::cell::RefCell;
+use std::io::{self, Cursor, Write};
+use std::rc::Rc;
+
+use common::{fast, spawn_fake_pi};
+use shared::{BAUD_RATE, Framer};
+use unix_side::batch::{self, BatchError};
+use unix_side::editor::{Completion, History, LineSource, Prompt};
+use unix_side::{link, loopback, pty, repl};
+
+#[test]
+fn repl_over_pty() {
+    let (host, pi) = pty::pair(BAUD_RATE).unwrap();
+    let pi = spawn_fake_pi(pi);
+    let mut framer = fast(Framer::unix_side(host));
+
+    let kernel = link::handshake(&mut framer).unwrap();
+    assert_eq!(kernel.build_id, "fake");
+
+    let input = Cursor::new("(+ 1 2)\n\n  (error \"x\")  \n(car '(a b))\n");
+    let mut out = Vec::new();
+    repl::run(&mut framer, input, &mut out);
+
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        "> (+ 1 2)\n> > error: boom\n> (car '(a b))\n> "
+    );
+
+    // hanging up the slave end closes the fake Pi's master end
+    drop(framer);
+    pi.join().unwrap();
+}
+
+#[test]
+fn repl_accumulates_multiline_forms() {
+    let (host, pi) = loopback::pair();
+    let pi = spawn_fake_pi(pi);
+    let mut framer = fast(Framer::unix_side(host));
+
+    let input = Cursor::new(
+        "(defun f (x) ; a ) in a comment\n  \"(\"\n  x)\n(a) (b\n)\n) (c)\n(d)\n(open\n",
+    );
+    let mut out = Vec::new();
+    repl::run(&mut framer, input, &mut out);
+
+    // continuation lines get no prompt on a plain input; the stray ')'
+    // discards its whole line
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        "> (defun f (x) ; a ) in a comment\n  \"(\"\n  x)\n> (a)\n(b\n)\n> > (d)\n> "
+    );
+
+    drop(framer);
+    pi.join().unwrap();
+}
+
+#[test]
+fn history_skips_blanks_and_repeats() {
+    let path = std::env::temp_dir().join(format!("lispi-history-{}", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+
+    let mut history = History::load(Some(path.clone()));
+    for line in ["(a)", "(a)", "  ", "(b)", "(a)"] {
+        history.add(line);
+    }
+    assert_eq!(history.entries(), ["(a)", "(b)", "(a)"]);
+
+    let reloaded = History::load(Some(path.clone()));
+    assert_eq!(reloaded.entries(), history.entries());
+    std::fs::remove_file(&path).unwrap();
+}
+
+/// Plain lines from a string, keeping what the REPL enters for history.
+struct Recorder {
+    lines: Cursor<&'static str>,
+    entries: Rc<RefCell<Vec<String>>>,
+}
+
+impl LineSource for Recorder {
+    fn read_line(
+        &mut self,
+        prompt: Prompt,
+        out: &mut dyn Write,
+        complete: &mut dyn FnMut(&str) -> Completion,
+    ) -> io::Result<Option<String>> {
+        LineSource::read_line(&mut self.lines, prompt, out, complete)
+    }
+
+    fn entered(&mut self, entry: &str) {
+        self.entries.borrow_mut().push(entry.to_string());
+    }
+}
+
+#[test]
+fn history_keeps_whole_forms() {
+    let (host, pi) = loopback::pair();
+    let pi = spawn_fake_pi(pi);
+    let mut framer = fast(Framer::unix_side(host));
+
+    let entries = Rc::new(RefCell::new(Vec::new()));
+    let input = Recorder {
+        lines: Cursor::new("(defun f (x)\n  x)\n(a) (b\n)\n:nope\n"),
+        entries: Rc::clone(&entries),
+    };
+    repl::run(&mut framer, input, &mut Vec::new());
+    assert_eq!(
+        *entries.borrow(),
+        ["(defun f (x)\n  x)", "(a) (b\n)", ":nope"]
+    );
+
+    let path = std::env::temp_dir().join(format!("lispi-history-forms-{}", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+    let mut history = History::load(Some(path.clone()));
+    for entry in entries.borrow().iter() {
+        history.add(entry);
+    }
+    let reloaded = History::load(Some(path.clone()));
+    assert_eq!(reloaded.entries(), history.entries());
+    std::fs::remove_file(&path).unwrap();
+
+    drop(framer);
+    pi.join().unwrap();
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn baud_switch_over_pty() {
+    let (host, pi) = pty::pair(BAUD_RATE).unwrap();
+    let pi = spawn_fake_pi(pi);
+    let mut framer = fast(Framer::unix_side(host));
+
+    let kernel = link::handshake(&mut framer).unwrap();
+    assert_eq!(link::negotiate_baud(&mut framer, &kernel, None), 460800);
+    assert_eq!(framer.transport.speed(), 460800);
+
+    drop(framer);
+    pi.join().unwrap();
+}
+
+#[cfg(target_os = "linux")]
+#[test]
+fn explicit_baud_must_be_offered() {
+    let (host, pi) = pty::pair(BAUD_RATE).unwrap();
+    let pi = spawn_fake_pi(pi);
+    let mut framer = fast(Framer::unix_side(host));
+
+    let kernel = link::handshake(&mut framer).unwrap();
+    // the fake kernel doesn't offer 921600
+    assert_eq!(
+        link::negotiate_baud(&mut framer, &kernel, Some(921600)),
+        BAUD_RATE
+    );
+    assert_eq!(
+        link::negotiate_baud(&mut framer, &kernel, Some(460800)),
+        460800
+    );
+
+    drop(framer);
+    pi.join().unwrap();
+}
+
+#[test]
+fn batch_evaluates_forms_in_order() {
+    let (host, pi) = loopback::pair();
+    let pi = spawn_fake_pi(pi);
+    let mut framer = fast(Framer::unix_side(host));
+
+    let exprs = vec!["(+ 1 2) 'x\n; comment\n(f \"a ) b\")".to_string()];
+    let mut out = Vec::new();
+    batch::run(&mut framer, None, &exprs, &mut out).unwrap();
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        "(+ 1 2)\n'x\n(f \"a ) b\")\n"
+    );
+
+    drop(framer);
+    pi.join().unwrap();
+}
+
+#[test]
+fn batch_stops_at_first_error() {
+    let (host, pi) = loopback::pair();
+    let pi = spawn_fake_pi(pi);
+    let mut framer = fast(Framer::unix_side(host));
+
+    let exprs = vec!["(a)\n\n  (error 1) (b)".to_string(), "(c)".to_string()];
+    let mut out = Vec::new();
+    let err = batch::run(&mut framer, None, &exprs, &mut out).unwrap_err();
+    assert!(matches!(err, BatchError::Eval { ref form, line: 3, .. } if form == "(error 1)"));
+    assert!(
+        err.to_string()
+            .starts_with("--eval:3: error in (error 1): ")
+    );
+    assert_eq!(String::from_utf8(out).unwrap(), "(a)\n");
+
+    let err = batch::run(&mut framer, None, &["(open".to_string()], &mut Vec::new());
+    assert!(matches!(err, Err(BatchError::Unterminated(_))));
+
+    drop(framer);
+    pi.join().unwrap();
+}
@@ -0,0 +1,109 @@
+mod common;
+
+use std::os::unix::net::UnixStream;
+use std::path::{Path, PathBuf};
+use std::thread;
+
+use common::{fast, spawn_fake_pi};
+use shared::control::feature;
+use shared::{Framer, MessageKind};
+use unix_side::socket::Socket;
+use unix_side::{complete, link, loopback, server};
+
+fn socket_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("lispi-{}-{}.sock", name, std::process::id()))
+}
+
+/// Start a server in front of a fake Pi, listening on `path`.
+fn spawn_server(path: &Path) {
+    let (host, pi) = loopback::pair();
+    spawn_fake_pi(pi);
+    let listener = server::bind(path).unwrap();
+    thread::spawn(move || {
+        let mut framer = fast(Framer::unix_side(host));
+        let kernel = link::handshake(&mut framer).unwrap();
+        server::serve(&mut framer, &kernel, listener);
+    });
+}
+
+fn connect(path: &Path) -> Framer<Socket> {
+    let stream = Socket::new(UnixStream::connect(path).unwrap()).unwrap();
+    fast(Framer::unix_side(stream))
+}
+
+#[test]
+fn clients_share_one_pi() {
+    let path = socket_path("share");
+    spawn_server(&path);
+
+    let clients: Vec<_> = (0..3)
+        .map(|c| {
+            let mut framer = connect(&path);
+            thread::spawn(move || {
+                let kernel = link::handshake(&mut framer).unwrap();
+                assert_eq!(kernel.build_id, "fake");
+                // compression is left to the server's own link
+                assert!(!kernel.has_feature(feature::COMPRESSION));
+
+                // every client numbers its requests from 1
+                for id in 1..=20 {
+                    let src = format!("(client {} request {})", c, id);
+                    let reply = link::eval(&mut framer, id, &src).unwrap();
+                    assert_eq!(reply.kind, MessageKind::EvalResult);
+                    assert_eq!(reply.id, id);
+                    assert_eq!(reply.body, src.as_bytes());
+                }
+                let reply = link::eval(&mut framer, 21, "(error)").unwrap();
+                assert_eq!(reply.kind, MessageKind::EvalError);
+            })
+        })
+        .collect();
+    for c in clients {
+        c.join().unwrap();
+    }
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn names_are_forwarded() {
+    let path = socket_path("names");
+    spawn_server(&path);
+
+    let mut framer = connect(&path);
+    link::handshake(&mut framer).unwrap();
+    link::eval(&mut framer, 1, "(define life/step (lambda (b) b))").unwrap();
+    let names = complete::query_names(&mut framer).unwrap();
+    assert_eq!(names.globals, ["life/step"]);
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn output_goes_to_the_client_that_asked() {
+    let path = socket_path("output");
+    spawn_server(&path);
+
+    let mut framer = connect(&path);
+    link::handshake(&mut framer).unwrap();
+    let mut printed = Vec::new();
+    link::eval_with(&mut framer, 1, "(print dump)", &mut |bytes| {
+        printed.extend_from_slice(bytes)
+    })
+    .unwrap();
+    assert_eq!(printed, b"dump");
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn bind_refuses_a_live_socket() {
+    let path = socket_path("live");
+    spawn_server(&path);
+    assert!(server::bind(&path).is_err());
+
+    // a stale socket file is replaced
+    let stale = socket_path("stale");
+    drop(std::os::unix::net::UnixListener::bind(&stale).unwrap());
+    assert!(server::bind(&stale).is_ok());
+
+    std::fs::remove_file(&path).unwrap();
+    std::fs::remove_file(&stale).unwrap();
+}
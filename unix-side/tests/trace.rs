@@ -0,0 +1,173 @@
+mod common;
+
+use common::{fast, spawn_fake_pi};
+use shared::crc::Crc32;
+use shared::{
+    BAUD_RATE, FrameKind, Framer, Message, MessageKind, PI_FOOTER_WORD, PI_SYNC_WORD,
+    UNIX_SYNC_WORD,
+};
+use unix_side::trace::{self, Anomaly, Capture, Dir, Item, Record};
+use unix_side::{link, pty};
+
+/// A frame as `Framer::send` puts it on the wire, with `crc` added to
+/// the right checksum.
+fn frame(sync: u32, footer: u32, kind: FrameKind, seq: u32, payload: &[u8], crc: u32) -> Vec<u8> {
+    let mut words = vec![sync; 8];
+    words.extend([0; 4]);
+    words.extend([kind as u32, seq, payload.len() as u32]);
+    let mut b: Vec<u8> = words.iter().flat_map(|w| w.to_le_bytes()).collect();
+    b.extend_from_slice(payload);
+    let mut sum = Crc32::new();
+    sum.update32(kind as u32);
+    sum.update32(seq);
+    sum.update32(payload.len() as u32);
+    sum.update(payload);
+    let tail = [
+        sum.finish().wrapping_add(crc),
+        0,
+        0,
+        0,
+        0,
+        footer,
+        footer,
+        footer,
+        footer,
+    ];
+    b.extend(tail.iter().flat_map(|w| w.to_le_bytes()));
+    b
+}
+
+#[test]
+fn captures_a_session_over_pty() {
+    let path = std::env::temp_dir().join(format!("lispi-capture-{}", std::process::id()));
+    let (mut host, pi) = pty::pair(BAUD_RATE).unwrap();
+    host.capture(Capture::create(&path).unwrap());
+    let pi = spawn_fake_pi(pi);
+    let mut framer = fast(Framer::unix_side(host));
+
+    link::handshake(&mut framer).unwrap();
+    link::eval(&mut framer, 7, "(+ 1 2)").unwrap();
+    // dropping the tty writes out the last burst
+    drop(framer);
+    pi.join().unwrap();
+
+    let records = trace::load(&path).unwrap();
+    std::fs::remove_file(&path).unwrap();
+    // the clock starts in `create`, so the baud record is a few
+    // microseconds in at most
+    assert!(matches!(
+        records[0],
+        Record::Baud { time, rate: BAUD_RATE } if time < 1_000_000
+    ));
+    let events = trace::decode(&records);
+    let frames: Vec<_> = events
+        .iter()
+        .filter_map(|e| match &e.item {
+            Item::Frame(f) => Some((e.dir.unwrap(), f)),
+            _ => None,
+        })
+        .collect();
+    assert!(frames.iter().all(|(_, f)| f.anomalies.is_empty()));
+
+    let data = |dir: Dir| -> Vec<String> {
+        frames
+            .iter()
+            .filter(|(d, f)| *d == dir && f.kind == FrameKind::Data as u32)
+            .map(|(_, f)| f.preview())
+            .collect()
+    };
+    let sent = data(Dir::ToPi);
+    assert!(sent[0].starts_with("Control Hello"), "{:?}", sent);
+    assert_eq!(sent.last().unwrap(), "EvalRequest #7 \"(+ 1 2)\"");
+    let received = data(Dir::FromPi);
+    assert!(received[0].starts_with("Control Hello"), "{:?}", received);
+    assert_eq!(received.last().unwrap(), "EvalResult #7 \"(+ 1 2)\"");
+    // every data frame was acked
+    let acks = frames
+        .iter()
+        .filter(|(_, f)| f.kind == FrameKind::Ack as u32)
+        .count();
+    assert_eq!(acks, sent.len() + received.len());
+}
+
+#[test]
+fn annotates_noise_and_broken_frames() {
+    let request = Message::new(MessageKind::EvalRequest, 1, b"(a)").encode();
+    let mut boot = b"LISPI Taking over...\n".to_vec();
+    boot.extend(frame(
+        PI_SYNC_WORD,
+        PI_FOOTER_WORD,
+        FrameKind::Ack,
+        5,
+        &[],
+        0,
+    ));
+    let bad = frame(PI_SYNC_WORD, PI_FOOTER_WORD, FrameKind::Data, 6, b"xyz", 1);
+    let mut cut = frame(
+        UNIX_SYNC_WORD,
+        PI_SYNC_WORD,
+        FrameKind::Data,
+        9,
+        &request,
+        0,
+    );
+    cut.truncate(cut.len() - 20);
+    let records = [
+        Record::Bytes {
+            dir: Dir::FromPi,
+            time: 10,
+            data: boot,
+        },
+        Record::Bytes {
+            dir: Dir::FromPi,
+            time: 20,
+            data: bad,
+        },
+        Record::Baud {
+            time: 25,
+            rate: 460800,
+        },
+        Record::Bytes {
+            dir: Dir::ToPi,
+            time: 30,
+            data: cut,
+        },
+    ];
+    let events = trace::decode(&records);
+    let items: Vec<_> = events.iter().map(|e| (e.time, e.dir, &e.item)).collect();
+
+    assert_eq!(
+        items[0],
+        (
+            10,
+            Some(Dir::FromPi),
+            &Item::Noise(b"LISPI Taking over...\n".to_vec())
+        )
+    );
+    let Item::Frame(ack) = items[1].2 else {
+        panic!("{:?}", items[1])
+    };
+    assert_eq!((ack.kind, ack.seq, ack.sync), (FrameKind::Ack as u32, 5, 8));
+    assert!(ack.anomalies.is_empty());
+    let Item::Frame(bad) = items[2].2 else {
+        panic!("{:?}", items[2])
+    };
+    assert_eq!(bad.payload, b"xyz");
+    assert!(
+        matches!(bad.anomalies[..], [Anomaly::BadCrc { sent, computed }] if sent == computed + 1)
+    );
+    assert_eq!(items[3], (25, None, &Item::Baud(460800)));
+    let Item::Frame(cut) = items[4].2 else {
+        panic!("{:?}", items[4])
+    };
+    assert_eq!(cut.preview(), "EvalRequest #1 \"(a)\"");
+    assert_eq!(cut.anomalies, [Anomaly::Truncated]);
+    assert_eq!(items.len(), 5);
+
+    let mut out = Vec::new();
+    trace::print(&records, &mut out).unwrap();
+    let out = String::from_utf8(out).unwrap();
+    assert!(out.contains("   0.000020 pi->unix data seq=0x00000006 len=3 "));
+    assert!(out.contains("\n                     !! crc "));
+    assert!(out.contains("   0.000025          baud -> 460800\n"));
+}
@@ -0,0 +1,88 @@
+//! The recorder is process-wide, so everything touching it lives in this
+//! one test binary, in a single test.
+
+mod common;
+
+use std::io::Cursor;
+
+use common::{fast, spawn_fake_pi};
+use shared::Framer;
+use unix_side::transcript::{self, Entry, Reply};
+use unix_side::{loopback, repl};
+
+fn reply(kind: &str, text: &str) -> Option<Reply> {
+    Some(Reply {
+        kind: kind.into(),
+        text: text.into(),
+    })
+}
+
+#[test]
+fn record_then_replay() {
+    let path = std::env::temp_dir().join(format!("lispi-transcript-{}", std::process::id()));
+    let path = path.to_str().unwrap().to_string();
+    let _ = std::fs::remove_file(&path);
+
+    transcript::start(&path, "fake").unwrap();
+    let (host, pi) = loopback::pair();
+    let fake = spawn_fake_pi(pi);
+    let mut framer = fast(Framer::unix_side(host));
+    repl::run(
+        &mut framer,
+        Cursor::new("(a \"q\")\n(error 1)\n"),
+        &mut Vec::new(),
+    );
+    drop(framer);
+    fake.join().unwrap();
+
+    // a session that ended with a form in flight
+    let crashed = r#"{"event":"form","n":3,"time":1.0,"code":"(crash)"}"#;
+    std::fs::write(
+        &path,
+        std::fs::read_to_string(&path).unwrap() + crashed + "\n",
+    )
+    .unwrap();
+
+    let entries = transcript::load(&path).unwrap();
+    assert_eq!(
+        entries,
+        [
+            Entry {
+                code: "(a \"q\")".into(),
+                reply: reply("value", "(a \"q\")"),
+            },
+            Entry {
+                code: "(error 1)".into(),
+                reply: reply("error", "boom"),
+            },
+            Entry {
+                code: "(crash)".into(),
+                reply: None,
+            },
+        ]
+    );
+
+    let (host, pi) = loopback::pair();
+    let fake = spawn_fake_pi(pi);
+    let mut framer = fast(Framer::unix_side(host));
+
+    let mut out = Vec::new();
+    let diverged = transcript::replay(&mut framer, &entries, false, &mut out).unwrap();
+    assert!(diverged.is_empty());
+    assert_eq!(
+        String::from_utf8(out).unwrap(),
+        "[1] (a \"q\")\n(a \"q\")\n[2] (error 1)\nerror: boom\n[3] (crash)\n(crash) (no recorded reply)\n"
+    );
+
+    // a kernel that now answers differently
+    let mut changed = entries.clone();
+    changed[0].reply = reply("value", "(b)");
+    let diverged = transcript::replay(&mut framer, &changed, true, &mut Vec::new()).unwrap();
+    assert_eq!(diverged.len(), 1);
+    assert_eq!(diverged[0].index, 1);
+    assert_eq!(diverged[0].got, reply("value", "(a \"q\")").unwrap());
+
+    drop(framer);
+    fake.join().unwrap();
+    std::fs::remove_file(&path).unwrap();
+}